@@ -0,0 +1,366 @@
+// Mechanical fixes of validation failures (mod-building validate --fix): submaterials missing in
+// the *.mtl, dangling COST_WORK_BUILDING_NODE, $TEXTURE of a stock file which has moved and a missing
+// imagegui.png. Changes of the config files are collected first, so that they can be shown as a diff.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::read_text;
+use crate::cfg::APP_SETTINGS;
+use crate::encoding::TextEncoding;
+use crate::building_def::ModBuildingDef;
+use crate::nmf::{self, NmfInfo};
+use crate::thumbnail;
+use crate::ini::{self, IniFile, IniToken, BuildingToken as BT, MaterialToken as MT};
+use crate::ini::common::IdStringParam;
+
+
+const DIFF_CONTEXT: usize = 2;
+
+
+pub struct FileFix {
+    pub path: PathBuf,
+    old: String,
+    new: String,
+    encoding: TextEncoding,
+}
+
+
+pub struct Fixes {
+    pub files: Vec<FileFix>,
+    // issues of the kinds above which have no safe fix
+    pub not_fixed: Vec<String>,
+}
+
+
+impl FileFix {
+    // Unified diff with DIFF_CONTEXT lines around the changes
+    pub fn diff(&self) -> Vec<String> {
+        diff_lines(&self.old, &self.new)
+    }
+
+    // Writes the file back in the encoding it was read in
+    pub fn write(&self) -> io::Result<()> {
+        fs::write(&self.path, self.encoding.encode(&self.new)?)
+    }
+}
+
+
+pub fn collect(bld: &ModBuildingDef) -> Fixes {
+    let mut fixes = Fixes { files: Vec::with_capacity(4), not_fixed: Vec::with_capacity(0) };
+
+    // submaterials used by the models of every material
+    let mut materials: Vec<(&Path, Vec<String>)> = Vec::with_capacity(4);
+    for model in [Some(&bld.model), bld.model_lod.as_ref(), bld.model_lod2.as_ref()].iter().flatten() {
+        add_usage(&mut materials, &bld.material, model);
+    }
+
+    if let Some(model_e) = bld.model_e.as_ref() {
+        add_usage(&mut materials, bld.material_e.as_ref().unwrap_or(&bld.material), model_e);
+    }
+
+    for mesh in bld.extra_meshes.iter() {
+        add_usage(&mut materials, &mesh.material, &mesh.model);
+    }
+
+    for (mtl, used) in materials.iter() {
+        fix_mtl(mtl, used, &mut fixes);
+    }
+
+    fix_building_ini(bld, &mut fixes);
+    fixes
+}
+
+
+fn add_usage<'b>(materials: &mut Vec<(&'b Path, Vec<String>)>, mtl: &'b Path, model: &Path) {
+    let idx = match materials.iter().position(|(p, _)| *p == mtl) {
+        Some(idx) => idx,
+        None => { materials.push((mtl, Vec::with_capacity(8))); materials.len() - 1 }
+    };
+
+    // models which cannot be loaded are reported by the validation
+    if let Ok(nmf) = NmfInfo::from_path(model) {
+        let sms = &mut materials[idx].1;
+        for sm in nmf.get_used_sumbaterials() {
+            if sms.iter().all(|s| s != sm) {
+                sms.push(sm.to_string());
+            }
+        }
+    }
+}
+
+
+// Renders imagegui.png next to renderconfig, as mod-building thumbnail does with the default size and angle
+pub fn render_imagegui(bld: &ModBuildingDef) -> Result<PathBuf, String> {
+    let nmf = nmf::NmfBufFull::from_path(&bld.model).map_err(|e| format!("Cannot read the nmf file: {:?}", e))?;
+    let (textures, _) = thumbnail::load_diffuse_textures(&nmf, &bld.material);
+    let image = thumbnail::render(&nmf, &textures, 256, 45.0);
+    let path = bld.render.parent().unwrap().join("imagegui.png");
+    image.write_png(&path).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+
+// Rewrites the stock textures which do not exist and appends the missing submaterials
+// with the textures of the first one
+fn fix_mtl(path: &Path, used: &[String], fixes: &mut Fixes) {
+    // files which cannot be read or parsed are reported by the validation
+    let (src, encoding) = match read_text(path) {
+        Ok(x) => x,
+        Err(_) => return
+    };
+
+    let mut mtl = match ini::parse_mtl(&src) {
+        Ok(mtl) => mtl,
+        Err(_) => return
+    };
+
+    let moved = find_moved_textures(&mtl, path, fixes);
+    let new_path = |p: &str| moved.iter().find(|(old, _)| old == p).map(|(_, new)| new.clone());
+    for t in mtl.tokens_mut() {
+        t.modify(|t| match t {
+            MT::Texture((_, p)) | MT::TextureNoMip((_, p)) => new_path(p.as_str()).map(|new| with_texture(t, new).unwrap()),
+            _ => None
+        });
+    }
+
+    // the block of the first submaterial, as it is after the fixes above
+    let template: Vec<MT> = mtl.tokens()
+        .skip_while(|t| !matches!(t, MT::Submaterial(_)))
+        .skip(1)
+        .take_while(|t| !matches!(t, MT::Submaterial(_)))
+        .filter_map(|t| match t {
+            MT::Texture((_, p)) | MT::TextureNoMip((_, p)) | MT::TextureMtl((_, p)) | MT::TextureNoMipMtl((_, p)) => {
+                with_texture(t, new_path(p.as_str()).unwrap_or_else(|| p.as_str().to_string()))
+            },
+            _ => None
+        })
+        .collect();
+
+    let existing: Vec<String> = mtl.tokens().filter_map(|t| match t {
+        MT::Submaterial(sm) => Some(sm.as_str().to_string()),
+        _ => None
+    }).collect();
+
+    for sm in used.iter().filter(|sm| !existing.contains(sm)) {
+        mtl.append(MT::Submaterial(IdStringParam::new_cloned(sm)));
+        for t in template.iter() {
+            if let Some(t) = with_texture(t, texture_path(t).to_string()) {
+                mtl.append(t);
+            }
+        }
+    }
+
+    push_fix(path, &src, encoding, &mtl, fixes);
+}
+
+
+// Texture token of the same kind and index with another path
+fn with_texture<'b>(t: &MT, path: String) -> Option<MT<'b>> {
+    let p = IdStringParam::new_owned(path);
+    match t {
+        MT::Texture((i, _))         => Some(MT::Texture((*i, p))),
+        MT::TextureNoMip((i, _))    => Some(MT::TextureNoMip((*i, p))),
+        MT::TextureMtl((i, _))      => Some(MT::TextureMtl((*i, p))),
+        MT::TextureNoMipMtl((i, _)) => Some(MT::TextureNoMipMtl((*i, p))),
+        _ => None
+    }
+}
+
+
+fn texture_path<'b>(t: &'b MT) -> &'b str {
+    match t {
+        MT::Texture((_, p)) | MT::TextureNoMip((_, p)) | MT::TextureMtl((_, p)) | MT::TextureNoMipMtl((_, p)) => p.as_str(),
+        _ => ""
+    }
+}
+
+
+// (old token, new token) of $TEXTURE and $TEXTURE_NOMIP paths which do not exist in the stock files,
+// but there is exactly one stock file with the same name (ignoring case) elsewhere
+fn find_moved_textures(mtl: &ini::MaterialMtl, mtl_path: &Path, fixes: &mut Fixes) -> Vec<(String, String)> {
+    let mut missing: Vec<&str> = Vec::with_capacity(0);
+    for t in mtl.tokens() {
+        if let MT::Texture((_, p)) | MT::TextureNoMip((_, p)) = t {
+            if !ini::resolve_stock_path(p).exists() && !missing.contains(&p.as_str()) {
+                missing.push(p.as_str());
+            }
+        }
+    }
+
+    if missing.is_empty() {
+        return Vec::with_capacity(0);
+    }
+
+    let names: Vec<String> = missing.iter().map(|p| p.rsplit(['/', '\\']).next().unwrap().to_lowercase()).collect();
+    let mut found: Vec<Vec<PathBuf>> = vec![Vec::with_capacity(1); names.len()];
+    let stock = APP_SETTINGS.path_stock.as_path();
+    let mut backlog = vec![stock.to_path_buf()];
+    while let Some(d) = backlog.pop() {
+        for entry in fs::read_dir(&d).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                backlog.push(path);
+            } else if let Some(i) = names.iter().position(|n| entry.file_name().to_string_lossy().to_lowercase() == *n) {
+                found[i].push(path);
+            }
+        }
+    }
+
+    let mut result = Vec::with_capacity(missing.len());
+    for (old, mut paths) in missing.into_iter().zip(found) {
+        match paths.len() {
+            1 => {
+                let rel = paths.pop().unwrap();
+                let rel = rel.strip_prefix(stock).unwrap();
+                let new = rel.iter().map(|c| c.to_string_lossy()).collect::<Vec<_>>().join("/");
+                result.push((old.to_string(), new));
+            },
+            0 => fixes.not_fixed.push(format!("{}: texture '{}' is not in the stock files", mtl_path.display(), old)),
+            n => fixes.not_fixed.push(format!("{}: texture '{}' is not in the stock files, there are {} files with this name:\n{}",
+                                              mtl_path.display(), old, n,
+                                              paths.iter().map(|p| p.strip_prefix(stock).unwrap().display().to_string()).collect::<Vec<_>>().join("\n"))),
+        }
+    }
+
+    result
+}
+
+
+// COST_WORK_BUILDING_NODE of a node which is not in the main model is removed. References of
+// any kind which differ from a node name only in case get the name of the node.
+fn fix_building_ini(bld: &ModBuildingDef, fixes: &mut Fixes) {
+    let (src, encoding) = match read_text(&bld.building_ini) {
+        Ok(x) => x,
+        Err(_) => return
+    };
+
+    let mut building_ini = match ini::parse_building_ini(&src) {
+        Ok(b) => b,
+        Err(_) => return
+    };
+
+    let model = match NmfInfo::from_path(&bld.model) {
+        Ok(m) => m,
+        Err(_) => return
+    };
+
+    let names: Vec<&str> = model.object_names().collect();
+    let actual = |node: &str| -> Option<String> {
+        if names.contains(&node) {
+            return None;
+        }
+
+        let mut same = names.iter().filter(|n| n.eq_ignore_ascii_case(node));
+        match (same.next(), same.next()) {
+            (Some(n), None) => Some(n.to_string()),
+            _ => None
+        }
+    };
+
+    for t in building_ini.tokens_mut() {
+        t.modify(|t| match t {
+            BT::StorageLivingAuto(id)          => actual(id.as_str()).map(|n| BT::StorageLivingAuto(IdStringParam::new_owned(n))),
+            BT::CostWorkBuildingNode(id)       => actual(id.as_str()).map(|n| BT::CostWorkBuildingNode(IdStringParam::new_owned(n))),
+            BT::CostWorkVehicleStationNode(id) => actual(id.as_str()).map(|n| BT::CostWorkVehicleStationNode(IdStringParam::new_owned(n))),
+            _ => None
+        });
+    }
+
+    building_ini.remove_tokens(|t| match t {
+        BT::CostWorkBuildingNode(id) => !names.contains(&id.as_str()),
+        _ => false
+    });
+
+    for r in building_ini.get_model_refs() {
+        match r {
+            ini::BuildingNodeRef::Exact(node) if !names.contains(&node) => {
+                fixes.not_fixed.push(format!("{}: node '{}' is not in the main model", bld.building_ini.display(), node));
+            },
+            ini::BuildingNodeRef::Keyword(key) if names.iter().all(|n| !n.starts_with(key)) => {
+                fixes.not_fixed.push(format!("{}: no node of the main model starts with '{}'", bld.building_ini.display(), key));
+            },
+            _ => { }
+        }
+    }
+
+    push_fix(&bld.building_ini, &src, encoding, &building_ini, fixes);
+}
+
+
+fn push_fix<T: IniToken>(path: &Path, src: &str, encoding: TextEncoding, ini: &IniFile<T>, fixes: &mut Fixes) {
+    // the text as it is written in UTF-8, it is encoded back when the fix is written
+    let mut buf = Vec::with_capacity(src.len() + 256);
+    ini.write_to(&mut buf).expect("Cannot serialize tokens");
+    let new = String::from_utf8(buf).expect("Tokens are serialized as UTF-8");
+    if new != src {
+        fixes.files.push(FileFix { path: path.to_path_buf(), old: src.to_string(), new, encoding });
+    }
+}
+
+
+// Line diff (longest common subsequence) in the unified format
+fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // the common head is cut off (not the tail: appended tokens would be aligned with the wrong lines)
+    let head = a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count();
+    let (am, bm) = (&a[head ..], &b[head ..]);
+
+    // lcs[i * w + j]: length of the common subsequence of am[i..] and bm[j..]
+    let w = bm.len() + 1;
+    let mut lcs = vec![0u32; (am.len() + 1) * w];
+    for i in (0 .. am.len()).rev() {
+        for j in (0 .. bm.len()).rev() {
+            lcs[i * w + j] = if am[i] == bm[j] {
+                lcs[(i + 1) * w + j + 1] + 1
+            } else {
+                lcs[(i + 1) * w + j].max(lcs[i * w + j + 1])
+            };
+        }
+    }
+
+    let mut ops: Vec<(char, &str)> = a[.. head].iter().map(|l| (' ', *l)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < am.len() || j < bm.len() {
+        if i < am.len() && j < bm.len() && am[i] == bm[j] {
+            ops.push((' ', am[i]));
+            i += 1;
+            j += 1;
+        } else if i < am.len() && (j == bm.len() || lcs[(i + 1) * w + j] >= lcs[i * w + j + 1]) {
+            ops.push(('-', am[i]));
+            i += 1;
+        } else {
+            ops.push(('+', bm[j]));
+            j += 1;
+        }
+    }
+
+    let mut result = Vec::with_capacity(16);
+    let mut pos = 0;
+    while let Some(first) = ops[pos ..].iter().position(|(c, _)| *c != ' ').map(|p| p + pos) {
+        // changes closer than 2 * DIFF_CONTEXT lines go to the same hunk
+        let mut last = first;
+        for (k, (c, _)) in ops.iter().enumerate().skip(first) {
+            if k > last + 2 * DIFF_CONTEXT {
+                break;
+            } else if *c != ' ' {
+                last = k;
+            }
+        }
+
+        let start = first.saturating_sub(DIFF_CONTEXT);
+        let end = (last + DIFF_CONTEXT + 1).min(ops.len());
+        let count = |ops: &[(char, &str)], skip: char| ops.iter().filter(|(c, _)| *c != skip).count();
+        let hunk = &ops[start .. end];
+        result.push(format!("@@ -{},{} +{},{} @@",
+                            count(&ops[.. start], '+') + 1, count(hunk, '+'),
+                            count(&ops[.. start], '-') + 1, count(hunk, '-')));
+        result.extend(hunk.iter().map(|(c, l)| format!("{}{}", c, l)));
+        pos = end;
+    }
+
+    result
+}
@@ -1,17 +1,25 @@
 use std::path::{Path, PathBuf};
 use std::fs;
-use std::fmt::{Display, Formatter, Write};
+use std::fmt::{Display, Formatter};
 use std::io::Error as IOErr;
 
-use crate::{read_to_string_buf};
+use crate::{read_to_string_buf, read_text};
+use crate::cfg::APP_SETTINGS;
 use crate::nmf::NmfInfo;
+use crate::nmf::object_full::RawBBox;
+use crate::dds;
+use crate::portability;
+use crate::modpack::{make_relative_token, make_asset_token};
 use crate::ini::{self,
                  BuildingIni,
                  RenderIni,
                  MaterialMtl,
+                 BuildingToken as BT,
                  RenderToken as RT,
                  MaterialToken as MT,
                  common::IdStringParam,
+                 building::lint,
+                 diagnostic::{Diagnostic, Severity},
                  };
 
 
@@ -30,17 +38,96 @@ pub struct ModBuildingDef {
     pub material: PathBuf,
     pub material_e: Option<PathBuf>,
 
+    // in the order of their tokens: building.ini first, then renderconfig
+    pub extra_meshes: Vec<ExtraMesh>,
+    // same order as extra_meshes
+    pub effects: Vec<EffectRef>,
+
     pub textures: Vec<PathBuf>,
 }
 
 
+// Model with its own material, referenced by building.ini ANIMATION_MESH, ANIMATION_MESH_WORKSHOP,
+// UNDERGROUND_MESH or renderconfig DERBIS_MESH
+#[derive(Debug, Clone)]
+pub struct ExtraMesh {
+    pub kind: ExtraMeshKind,
+    pub model: PathBuf,
+    pub material: PathBuf,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExtraMeshKind {
+    Animation,
+    AnimationWorkshop,
+    Underground,
+    Derbis,
+}
+
+
+// Sound or effect file of building.ini WORKING_SFX or renderconfig DERBIS_FALLED_SFX,
+// DERBIS_FALLING_FX, DERBIS_FALLED_FX
+#[derive(Debug, Clone)]
+pub struct EffectRef {
+    pub token: &'static str,
+    pub path: PathBuf,
+}
+
+
+impl EffectRef {
+    pub const WORKING_SFX: &'static str = "WORKING_SFX";
+
+    // Resolved as resolve_source_path does. A plain relative path which does not exist
+    // next to the config file falls back to the same path in the stock game files.
+    fn resolve(token: &'static str, root: &Path, p: &IdStringParam) -> Self {
+        let mut path = ini::resolve_source_path(root, p);
+        if !path.exists() {
+            let stock = ini::resolve_stock_path(p);
+            if stock.exists() {
+                path = stock;
+            }
+        }
+
+        EffectRef { token, path }
+    }
+}
+
+
+impl ExtraMeshKind {
+    pub fn token_name(&self) -> &'static str {
+        match self {
+            Self::Animation         => "ANIMATION_MESH",
+            Self::AnimationWorkshop => "ANIMATION_MESH_WORKSHOP",
+            Self::Underground       => "UNDERGROUND_MESH",
+            Self::Derbis            => "DERBIS_MESH",
+        }
+    }
+}
+
+
 #[derive(Debug, Clone)]
 pub enum BuildingError {
     FileIO(PathBuf, String),
-    Parse(PathBuf, String),
-    ModelMissing,
-    MaterialMissing,
-    Validation(Vec<String>),
+    Parse(Vec<Diagnostic>),
+    // renderconfig without the token
+    ModelMissing(PathBuf),
+    MaterialMissing(PathBuf),
+    // errors and warnings
+    Validation(Vec<Diagnostic>),
+}
+
+
+impl BuildingError {
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        match self {
+            BuildingError::FileIO(path, e)       => vec![Diagnostic::error("file-read", format!("Cannot read file: {}", e)).in_file(&path)],
+            BuildingError::Parse(d)              => d,
+            BuildingError::ModelMissing(path)    => vec![Diagnostic::error("model-missing", "MODEL is missing".to_string()).in_file(&path)],
+            BuildingError::MaterialMissing(path) => vec![Diagnostic::error("material-missing", "MATERIAL is missing".to_string()).in_file(&path)],
+            BuildingError::Validation(d)         => d,
+        }
+    }
 }
 
 
@@ -51,6 +138,7 @@ impl ModBuildingDef {
         render: &Path,
         render_root: &Path, 
         render_ini: RenderIni, 
+        (building_meshes, building_effects): (Vec<ExtraMesh>, Vec<EffectRef>),
         render_path_resolver: fn(&Path, &IdStringParam) -> PathBuf,
         mtl_path_resolver:    fn(&Path, &IdStringParam) -> PathBuf) -> Result<Self, BuildingError> 
     {
@@ -68,18 +156,42 @@ impl ModBuildingDef {
             }};
         }
 
-        let model      = get_render_value!(RT::Model(s),            s).ok_or(BuildingError::ModelMissing)?;
+        let model      = get_render_value!(RT::Model(s),            s).ok_or_else(|| BuildingError::ModelMissing(render.to_path_buf()))?;
         let model_lod  = get_render_value!(RT::ModelLod((s, _)),    s);
         let model_lod2 = get_render_value!(RT::ModelLod2((s, _)),   s);
         let model_e    = get_render_value!(RT::ModelEmissive(s),    s);
-        let material   = get_render_value!(RT::Material(s),         s).ok_or(BuildingError::MaterialMissing)?;
+        let material   = get_render_value!(RT::Material(s),         s).ok_or_else(|| BuildingError::MaterialMissing(render.to_path_buf()))?;
         let material_e = get_render_value!(RT::MaterialEmissive(s), s);
 
+        let mut extra_meshes = building_meshes;
+        for t in render_ini.tokens() {
+            if let RT::DerbisMesh((m, mtl)) = t {
+                extra_meshes.push(ExtraMesh {
+                    kind: ExtraMeshKind::Derbis,
+                    model: render_path_resolver(render_root, m),
+                    material: render_path_resolver(render_root, mtl),
+                });
+            }
+        }
+
+        let mut effects = building_effects;
+        for t in render_ini.tokens() {
+            match t {
+                RT::DerbisFalledSfx(p)      => effects.push(EffectRef::resolve("DERBIS_FALLED_SFX", render_root, p)),
+                RT::DerbisFallingFx((p, _)) => effects.push(EffectRef::resolve("DERBIS_FALLING_FX", render_root, p)),
+                RT::DerbisFalledFx((p, _))  => effects.push(EffectRef::resolve("DERBIS_FALLED_FX",  render_root, p)),
+                _ => { }
+            }
+        }
+
         let mut textures = Vec::with_capacity(10);
         push_textures(&material, &mut textures, mtl_path_resolver)?;
         if let Some(ref material_e) = material_e {
             push_textures(material_e, &mut textures, mtl_path_resolver)?;
         }
+        for mesh in extra_meshes.iter() {
+            push_textures(&mesh.material, &mut textures, mtl_path_resolver)?;
+        }
 
         Ok(ModBuildingDef {
             render: render.to_path_buf(),
@@ -91,81 +203,172 @@ impl ModBuildingDef {
             model_e,
             material,
             material_e,
+            extra_meshes,
+            effects,
             textures
         })
     }
 
 
     // Does not re-parse renderconfig!
-    pub fn parse_and_validate(&self, nmf_override: Option<&NmfInfo>) -> Result<(), BuildingError> {
-        let mut errors = Vec::<String>::with_capacity(0);
+    // model_actions are applied to every loaded model of the building before the checks (modpack actions).
+    // Ok(warnings) when there are no errors.
+    pub fn parse_and_validate(&self, model_actions: Option<&dyn Fn(&mut NmfInfo)>) -> Result<Vec<Diagnostic>, BuildingError> {
+        let mut errors = Vec::<Diagnostic>::with_capacity(0);
 
+        // $file: the file with the reference, if known
         macro_rules! check_path {
-            ($name:expr, $path:expr) => { 
+            ($name:expr, $path:expr, $file:expr) => { 
                 if !$path.exists() {
-                    errors.push(format!("{} ({}) does not exist", $name, $path.display())); 
+                    let d = Diagnostic::error("file-missing", format!("{} ({}) does not exist", $name, $path.display()));
+                    errors.push(match $file { Some(f) => d.in_file(f), None => d });
                 }
             };
         }
 
         macro_rules! check_popt {
-            ($name:expr, $path:expr) => { 
+            ($name:expr, $path:expr, $file:expr) => { 
                 if let Some(path) = $path {
-                    check_path!($name, path);
+                    check_path!($name, path, $file);
                 }
             };
         }
 
-        check_path!("renderconfig.ini", &self.render);
-        check_path!("building.ini",     &self.building_ini);
-        check_popt!("imagegui",         &self.image_gui);
-        check_path!("MODEL",            &self.model);
-        check_popt!("MODEL_LOD",        &self.model_lod);
-        check_popt!("MODEL_LOD2",       &self.model_lod2);
-        check_popt!("MODELEMISSIVE",    &self.model_e);
-        check_path!("MATERIAL",         &self.material);
-        check_popt!("MATERIALEMISSIVE", &self.material_e);
+        let render = Some(&self.render);
+        let building = Some(&self.building_ini);
+        check_path!("renderconfig.ini", &self.render,       None::<&Path>);
+        check_path!("building.ini",     &self.building_ini, None::<&Path>);
+        check_popt!("imagegui",         &self.image_gui,    None::<&Path>);
+        check_path!("MODEL",            &self.model,        render);
+        check_popt!("MODEL_LOD",        &self.model_lod,    render);
+        check_popt!("MODEL_LOD2",       &self.model_lod2,   render);
+        check_popt!("MODELEMISSIVE",    &self.model_e,      render);
+        check_path!("MATERIAL",         &self.material,     render);
+        check_popt!("MATERIALEMISSIVE", &self.material_e,   render);
+        for mesh in self.extra_meshes.iter() {
+            let file = if mesh.kind == ExtraMeshKind::Derbis { render } else { building };
+            check_path!(format!("{} model", mesh.kind.token_name()),    &mesh.model,    file);
+            check_path!(format!("{} material", mesh.kind.token_name()), &mesh.material, file);
+        }
+        for fx in self.effects.iter() {
+            let file = if fx.token == EffectRef::WORKING_SFX { building } else { render };
+            check_path!(fx.token, &fx.path, file);
+        }
         for tx in self.textures.iter() {
-            check_path!("texture", tx);
+            check_path!("texture", tx, None::<&Path>);
+        }
+        errors.extend(portability::check(&self.render, &self.building_ini));
+
+        let mut str_buf = String::with_capacity(0);
+        macro_rules! push_errors {
+            ($ini_path:expr, $parser:expr, $model_data:expr, $pusher:ident, $pfx:expr) => {
+                let read_res = read_to_string_buf($ini_path, &mut str_buf);
+                match read_res {
+                    Ok(_) => match $parser(&str_buf) {
+                        Ok(ini) => {
+                            $pusher(&ini, $model_data, &mut errors, $pfx)
+                        },
+                        Err(e) => errors.extend(ini::diagnostic::parse_diagnostics($ini_path, &str_buf, &e))
+                    },
+                    Err(e) => errors.push(Diagnostic::error("file-read", format!("Cannot read file: {}", e)).in_file($ini_path))
+                };
+            };
         }
 
-        match NmfInfo::from_path(&self.model) {
-            Ok(model) => {
-                let model = nmf_override.unwrap_or(&model);
-                let mut str_buf = String::with_capacity(0);
-                macro_rules! push_errors {
-                    ($ini_path:expr, $parser:expr, $model_data:expr, $pusher:ident, $pfx:expr) => {
-                        let read_res = read_to_string_buf($ini_path, &mut str_buf);
-                        match read_res {
-                            Ok(()) => match $parser(&str_buf) {
-                                Ok(ini) => {
-                                    $pusher(&ini, $model_data, &mut errors, $pfx)
-                                },
-                                Err(e) => errors.push(format!("Cannot parse file {}: {:#?}", $ini_path.display(), e))
-                            },
-                            Err(e) => errors.push(format!("Cannot read file {}: {:#?}", $ini_path.display(), e))
-                        };
-                    };
-                }
+        let load_model = |path: &Path| NmfInfo::from_path(path).map(|mut m| {
+            if let Some(f) = model_actions {
+                f(&mut m);
+            }
+            m
+        });
 
-                push_errors!(&self.building_ini, ini::parse_building_ini, &model,        push_buildingini_errors, "building.ini");
+        let main_model = match load_model(&self.model) {
+            Ok(model) => {
+                push_errors!(&self.building_ini, ini::parse_building_ini, (&model, self.building_ini.as_path(), &str_buf[..]), push_buildingini_errors, "building.ini");
 
                 let sm_usage = model.get_used_sumbaterials().collect::<Vec<_>>();
-                push_errors!(&self.material,     ini::parse_mtl,          sm_usage.iter(), push_mtl_errors,         "primary material");
+                push_errors!(&self.material,     ini::parse_mtl,          (&self.material, sm_usage.iter()), push_mtl_errors, "primary material");
                 if let Some(material_e) = &self.material_e {
-                    push_errors!(&material_e,    ini::parse_mtl,          sm_usage.iter(), push_mtl_errors,         "emissive material");
+                    push_errors!(&material_e,    ini::parse_mtl,          (material_e, sm_usage.iter()),     push_mtl_errors, "emissive material");
                 }
+
+                push_errors!(&self.material,     ini::parse_mtl,          self.material.parent().unwrap(), push_texture_errors, "primary material");
+                if let Some(material_e) = &self.material_e {
+                    push_errors!(&material_e,    ini::parse_mtl,          material_e.parent().unwrap(),    push_texture_errors, "emissive material");
+                }
+
+                Some(model)
             },
             Err(e) => { 
-                errors.push(format!("Cannot load model nmf: {:?}", e));
+                errors.push(Diagnostic::error("nmf-error", format!("Cannot load MODEL nmf: {:?}", e)).in_file(&self.model));
+                None
             }
         };
 
+        // LODs use the primary material and are compared with the main model and with the previous LOD
+        let mut parent = main_model.as_ref().map(|m| ("MODEL", (m.bbox(), m.triangles())));
+        for (name, path) in [("MODEL_LOD", &self.model_lod), ("MODEL_LOD2", &self.model_lod2)].iter() {
+            let path = match path {
+                Some(p) if p.exists() => p,
+                _ => continue
+            };
 
-        if errors.is_empty() {
-            Ok(())
-        } else {
+            match load_model(path) {
+                Ok(lod) => {
+                    let sm_usage = lod.get_used_sumbaterials().collect::<Vec<_>>();
+                    push_errors!(&self.material, ini::parse_mtl, (&self.material, sm_usage.iter()), push_mtl_errors, format!("primary material ({})", name));
+
+                    let (bbox, triangles) = (lod.bbox(), lod.triangles());
+                    if let Some(main_model) = main_model.as_ref() {
+                        if let Some(w) = check_lod_bbox(name, bbox.as_ref(), main_model.bbox().as_ref()) {
+                            errors.push(Diagnostic::warning("lod-bbox", w).in_file(path));
+                        }
+                    }
+
+                    if let Some((parent_name, (_, parent_triangles))) = parent {
+                        if triangles > parent_triangles {
+                            let msg = format!("{} has more triangles ({}) than {} ({})", name, triangles, parent_name, parent_triangles);
+                            errors.push(Diagnostic::warning("lod-triangles", msg).in_file(path));
+                        }
+                    }
+
+                    parent = Some((name, (bbox, triangles)));
+                },
+                Err(e) => errors.push(Diagnostic::error("nmf-error", format!("Cannot load {} nmf: {:?}", name, e)).in_file(path))
+            }
+        }
+
+        if let Some(model_e) = self.model_e.as_ref().filter(|p| p.exists()) {
+            match load_model(model_e) {
+                Ok(model) => {
+                    let sm_usage = model.get_used_sumbaterials().collect::<Vec<_>>();
+                    let (material, pfx) = match &self.material_e {
+                        Some(material_e) => (material_e, "emissive material (MODELEMISSIVE)"),
+                        None             => (&self.material, "primary material (MODELEMISSIVE)"),
+                    };
+                    push_errors!(material, ini::parse_mtl, (material, sm_usage.iter()), push_mtl_errors, pfx);
+                },
+                Err(e) => errors.push(Diagnostic::error("nmf-error", format!("Cannot load MODELEMISSIVE nmf: {:?}", e)).in_file(model_e))
+            }
+        }
+
+        for mesh in self.extra_meshes.iter().filter(|m| m.model.exists() && m.material.exists()) {
+            let pfx = format!("{} material", mesh.kind.token_name());
+            match NmfInfo::from_path(&mesh.model) {
+                Ok(model) => {
+                    let sm_usage = model.get_used_sumbaterials().collect::<Vec<_>>();
+                    push_errors!(&mesh.material, ini::parse_mtl, (&mesh.material, sm_usage.iter()), push_mtl_errors,     &pfx);
+                    push_errors!(&mesh.material, ini::parse_mtl, mesh.material.parent().unwrap(),  push_texture_errors, &pfx);
+                },
+                Err(e) => errors.push(Diagnostic::error("nmf-error", format!("Cannot load {} nmf: {:?}", mesh.kind.token_name(), e)).in_file(&mesh.model))
+            }
+        }
+
+
+        if errors.iter().any(Diagnostic::is_error) {
             Err(BuildingError::Validation(errors))
+        } else {
+            Ok(errors)
         }
     }
 
@@ -173,10 +376,11 @@ impl ModBuildingDef {
     pub fn from_render_path(building_ini: &Path, renderconfig: &Path, path_resolver: fn(&Path, &IdStringParam) -> PathBuf, validate: bool) -> Result<Self, BuildingError> {
         let render_root = renderconfig.parent().expect(&format!("Cannot get render root from {}", renderconfig.display()));
 
-        let render_buf = fs::read_to_string(renderconfig).map_err(|e| BuildingError::FileIO(renderconfig.to_path_buf(), e.to_string()))?;
-        let render_ini = ini::parse_renderconfig_ini(&render_buf).map_err(|e| BuildingError::Parse(renderconfig.to_path_buf(), concat_parse_errors(e)))?;
+        let (render_buf, _) = read_text(renderconfig).map_err(|e| BuildingError::FileIO(renderconfig.to_path_buf(), e.to_string()))?;
+        let render_ini = ini::parse_renderconfig_ini(&render_buf).map_err(|e| BuildingError::Parse(ini::diagnostic::parse_diagnostics(renderconfig, &render_buf, &e)))?;
 
-        let mut result = Self::from_render_ini(building_ini, renderconfig, render_root, render_ini, path_resolver, path_resolver)?;
+        let building_assets = read_building_assets(building_ini, path_resolver)?;
+        let mut result = Self::from_render_ini(building_ini, renderconfig, render_root, render_ini, building_assets, path_resolver, path_resolver)?;
 
         result.image_gui = {
             let img_path = render_root.join("imagegui.png");
@@ -195,6 +399,34 @@ impl ModBuildingDef {
     }
 
 
+    // Every file the building uses: configs, models, materials, textures, sounds and effects
+    pub fn files(&self) -> Vec<&Path> {
+        let mut result: Vec<&Path> = vec![&self.render, &self.building_ini, &self.model, &self.material];
+        result.extend([&self.image_gui, &self.model_lod, &self.model_lod2, &self.model_e, &self.material_e].iter().copied().filter_map(Option::as_deref));
+        for mesh in self.extra_meshes.iter() {
+            result.push(&mesh.model);
+            result.push(&mesh.material);
+        }
+        result.extend(self.effects.iter().map(|fx| fx.path.as_path()));
+        result.extend(self.textures.iter().map(PathBuf::as_path));
+        result
+    }
+
+
+    // Same order as files
+    fn files_mut(&mut self) -> Vec<&mut PathBuf> {
+        let mut result: Vec<&mut PathBuf> = vec![&mut self.render, &mut self.building_ini, &mut self.model, &mut self.material];
+        result.extend(vec![&mut self.image_gui, &mut self.model_lod, &mut self.model_lod2, &mut self.model_e, &mut self.material_e].into_iter().filter_map(Option::as_mut));
+        for mesh in self.extra_meshes.iter_mut() {
+            result.push(&mut mesh.model);
+            result.push(&mut mesh.material);
+        }
+        result.extend(self.effects.iter_mut().map(|fx| &mut fx.path));
+        result.extend(self.textures.iter_mut());
+        result
+    }
+
+
     pub fn shallow_copy_to(&self, target_dir: &Path) -> Result<Self, IOErr> {
         let source_root = self.render.parent().unwrap();
         
@@ -224,6 +456,16 @@ impl ModBuildingDef {
         let material     = mk_fld(&self.material)?;
         let material_e   = mk_fld_opt!(&self.material_e)?;
 
+        let mut extra_meshes = Vec::with_capacity(self.extra_meshes.len());
+        for mesh in self.extra_meshes.iter() {
+            extra_meshes.push(ExtraMesh { kind: mesh.kind, model: mk_fld(&mesh.model)?, material: mk_fld(&mesh.material)? });
+        }
+
+        let mut effects = Vec::with_capacity(self.effects.len());
+        for fx in self.effects.iter() {
+            effects.push(EffectRef { token: fx.token, path: mk_fld(&fx.path)? });
+        }
+
         let mut textures = Vec::with_capacity(self.textures.len());
         for tx in self.textures.iter() {
             let tx = mk_fld(tx)?;
@@ -240,9 +482,228 @@ impl ModBuildingDef {
             model_e,
             material,
             material_e,
+            extra_meshes,
+            effects,
             textures
         })
     }
+
+
+    // shallow_copy_to, then make_local. Expects paths resolved with ini::resolve_source_path.
+    pub fn deep_copy_to(&self, target_dir: &Path) -> Result<Self, IOErr> {
+        let mut result = self.shallow_copy_to(target_dir)?;
+        result.make_local()?;
+        Ok(result)
+    }
+
+
+    // Copies the files outside of the render root (stock, workshop, '../') into its 'external' folder.
+    // Tokens of renderconfig, building.ini and *.mtl files referring to them are rewritten to local relative paths.
+    pub fn make_local(&mut self) -> Result<(), IOErr> {
+        let root = self.render.parent().unwrap().to_path_buf();
+        let source = self.clone();
+
+        // (source, copy) of the external files
+        let mut external = Vec::<(PathBuf, PathBuf)>::with_capacity(0);
+        for (src, dest) in source.files().into_iter().zip(self.files_mut()) {
+            if src.starts_with(&root) {
+                continue;
+            }
+
+            *dest = match external.iter().find(|(s, _)| s == src) {
+                Some((_, copy)) => copy.clone(),
+                None => {
+                    let copy = external_copy_path(src, &root, &external);
+                    fs::create_dir_all(copy.parent().unwrap())?;
+                    fs::copy(src, &copy)?;
+                    external.push((src.to_path_buf(), copy.clone()));
+                    copy
+                }
+            };
+        }
+
+        let is_external = |p: &Path| external.iter().any(|(_, c)| c == p);
+        self.relink_configs(is_external)?;
+
+        // *.mtl files: textures are resolved from the source mtl, the tokens are rewritten when
+        // the texture or the mtl itself is an external copy
+        let mut mtls: Vec<(&Path, &Path)> = vec![(&source.material, &self.material)];
+        if let (Some(src), Some(dest)) = (&source.material_e, &self.material_e) {
+            mtls.push((src, dest));
+        }
+        for (src, dest) in source.extra_meshes.iter().zip(self.extra_meshes.iter()) {
+            mtls.push((&src.material, &dest.material));
+        }
+
+        let mut str_buf = String::with_capacity(0);
+        for (i, (src_mtl, mtl_path)) in mtls.iter().enumerate() {
+            if mtls[.. i].iter().any(|(_, p)| p == mtl_path) {
+                continue;
+            }
+
+            let src_root = src_mtl.parent().unwrap();
+            let mtl_moved = is_external(mtl_path);
+            let tx_token = |src_tx: PathBuf| {
+                let tx = external.iter().find(|(s, _)| *s == src_tx).map_or(src_tx, |(_, c)| c.clone());
+                if mtl_moved || is_external(&tx) {
+                    Some(IdStringParam::new_owned(make_relative_token(mtl_path, &tx).expect("Could not construct relative texture token")))
+                } else {
+                    None
+                }
+            };
+
+            let enc = read_to_string_buf(mtl_path, &mut str_buf)?;
+            let mut mtl = ini::parse_mtl(&str_buf).expect("Invalid *.mtl");
+            mtl.set_encoding(enc);
+            for token_state in mtl.tokens_mut() {
+                token_state.modify(|t| match t {
+                    MT::Texture((i, p))         => tx_token(ini::resolve_stock_path(p)).map(|p| MT::TextureMtl((*i, p))),
+                    MT::TextureNoMip((i, p))    => tx_token(ini::resolve_stock_path(p)).map(|p| MT::TextureNoMipMtl((*i, p))),
+                    MT::TextureMtl((i, p))      => tx_token(ini::resolve_source_path(src_root, p)).map(|p| MT::TextureMtl((*i, p))),
+                    MT::TextureNoMipMtl((i, p)) => tx_token(ini::resolve_source_path(src_root, p)).map(|p| MT::TextureNoMipMtl((*i, p))),
+                    _ => None
+                });
+            }
+            mtl.write_file(mtl_path)?;
+        }
+
+        Ok(())
+    }
+
+
+    // Rewrites the path tokens of renderconfig and building.ini which refer to the files selected by relink,
+    // as relative to the config file ('~'-prefixed for the files of the game). For the configs copied or
+    // moved away from the files they refer to.
+    pub fn relink_configs<F: Fn(&Path) -> bool>(&self, relink: F) -> Result<(), IOErr> {
+        let token = |from: &Path, p: &Path| if relink(p) {
+            Some(IdStringParam::new_owned(make_asset_token(from, p).expect("Could not construct relative token")))
+        } else {
+            None
+        };
+
+        let mesh_param = |from: &Path, mesh: &ExtraMesh, (m, mtl): &(IdStringParam, IdStringParam)| match (token(from, &mesh.model), token(from, &mesh.material)) {
+            (None, None) => None,
+            (new_m, new_mtl) => Some((new_m.unwrap_or_else(|| IdStringParam::new_cloned(m.as_str())),
+                                      new_mtl.unwrap_or_else(|| IdStringParam::new_cloned(mtl.as_str()))))
+        };
+
+        let mut str_buf = String::with_capacity(0);
+
+        // renderconfig
+        let render = self.render.as_path();
+        let enc = read_to_string_buf(render, &mut str_buf)?;
+        let mut render_ini = ini::parse_renderconfig_ini(&str_buf).expect("Invalid building renderconfig");
+        render_ini.set_encoding(enc);
+        let mut derbis_meshes = self.extra_meshes.iter().filter(|m| m.kind == ExtraMeshKind::Derbis);
+        let mut render_fx = self.effects.iter().filter(|fx| fx.token != EffectRef::WORKING_SFX);
+        for token_state in render_ini.tokens_mut() {
+            token_state.modify(|t| match t {
+                RT::Model(_)                => token(render, &self.model).map(RT::Model),
+                RT::ModelLod((_, z))        => self.model_lod.as_ref().and_then(|p| token(render, p)).map(|p| RT::ModelLod((p, *z))),
+                RT::ModelLod2((_, z))       => self.model_lod2.as_ref().and_then(|p| token(render, p)).map(|p| RT::ModelLod2((p, *z))),
+                RT::ModelEmissive(_)        => self.model_e.as_ref().and_then(|p| token(render, p)).map(RT::ModelEmissive),
+                RT::Material(_)             => token(render, &self.material).map(RT::Material),
+                RT::MaterialEmissive(_)     => self.material_e.as_ref().and_then(|p| token(render, p)).map(RT::MaterialEmissive),
+                RT::DerbisMesh(p)           => derbis_meshes.next().and_then(|m| mesh_param(render, m, p)).map(RT::DerbisMesh),
+                RT::DerbisFalledSfx(_)      => render_fx.next().and_then(|fx| token(render, &fx.path)).map(RT::DerbisFalledSfx),
+                RT::DerbisFallingFx((_, x)) => render_fx.next().and_then(|fx| token(render, &fx.path)).map(|p| RT::DerbisFallingFx((p, *x))),
+                RT::DerbisFalledFx((_, x))  => render_fx.next().and_then(|fx| token(render, &fx.path)).map(|p| RT::DerbisFalledFx((p, *x))),
+                _ => None
+            });
+        }
+        render_ini.write_file(render)?;
+
+        // building.ini
+        let bld = self.building_ini.as_path();
+        let enc = read_to_string_buf(bld, &mut str_buf)?;
+        let mut bld_ini = ini::parse_building_ini(&str_buf).expect("Invalid building ini");
+        bld_ini.set_encoding(enc);
+        let mut building_meshes = self.extra_meshes.iter().filter(|m| m.kind != ExtraMeshKind::Derbis);
+        let mut building_fx = self.effects.iter().filter(|fx| fx.token == EffectRef::WORKING_SFX);
+        for token_state in bld_ini.tokens_mut() {
+            token_state.modify(|t| match t {
+                BT::AnimationMesh(p)         => building_meshes.next().and_then(|m| mesh_param(bld, m, p)).map(BT::AnimationMesh),
+                BT::AnimationMeshWorkshop(p) => building_meshes.next().and_then(|m| mesh_param(bld, m, p)).map(BT::AnimationMeshWorkshop),
+                BT::UndergroundMesh(p)       => building_meshes.next().and_then(|m| mesh_param(bld, m, p)).map(BT::UndergroundMesh),
+                BT::WorkingSfx(_)            => building_fx.next().and_then(|fx| token(bld, &fx.path)).map(BT::WorkingSfx),
+                _ => None
+            });
+        }
+        bld_ini.write_file(bld)
+    }
+}
+
+
+// Place of an external file in the deep copy: 'external/stock/...' and 'external/workshop/...' keep
+// the path below the game and workshop roots, other files go to 'external' under their own name
+fn external_copy_path(path: &Path, target_dir: &Path, copied: &[(PathBuf, PathBuf)]) -> PathBuf {
+    let ext_root = target_dir.join("external");
+    if let Ok(rel) = path.strip_prefix(APP_SETTINGS.path_stock.as_path()) {
+        return ext_root.join("stock").join(rel);
+    }
+
+    if let Ok(rel) = path.strip_prefix(APP_SETTINGS.path_workshop.as_path()) {
+        return ext_root.join("workshop").join(rel);
+    }
+
+    let name = path.file_name().unwrap().to_string_lossy();
+    let mut copy = ext_root.join(&*name);
+    let mut i = 1;
+    while copied.iter().any(|(_, c)| *c == copy) {
+        copy = ext_root.join(format!("{}_{}", i, name));
+        i += 1;
+    }
+
+    copy
+}
+
+
+// ANIMATION_MESH, ANIMATION_MESH_WORKSHOP and UNDERGROUND_MESH models and WORKING_SFX files of building.ini.
+// Tokens which do not parse are skipped here, they are reported by the validation.
+fn read_building_assets(building_ini: &Path, path_resolver: fn(&Path, &IdStringParam) -> PathBuf) -> Result<(Vec<ExtraMesh>, Vec<EffectRef>), BuildingError> {
+    let bld_root = building_ini.parent().unwrap_or_else(|| panic!("Cannot get building root from {}", building_ini.display()));
+    let (bld_buf, _) = read_text(building_ini).map_err(|e| BuildingError::FileIO(building_ini.to_path_buf(), e.to_string()))?;
+
+    let mut meshes = Vec::with_capacity(0);
+    let mut effects = Vec::with_capacity(0);
+    for (_, t) in ini::parse_building_tokens(&bld_buf) {
+        let (kind, (m, mtl)) = match t {
+            Ok((BT::AnimationMesh(p), _))         => (ExtraMeshKind::Animation, p),
+            Ok((BT::AnimationMeshWorkshop(p), _)) => (ExtraMeshKind::AnimationWorkshop, p),
+            Ok((BT::UndergroundMesh(p), _))       => (ExtraMeshKind::Underground, p),
+            Ok((BT::WorkingSfx(p), _)) => {
+                effects.push(EffectRef::resolve(EffectRef::WORKING_SFX, bld_root, &p));
+                continue;
+            },
+            _ => continue
+        };
+
+        meshes.push(ExtraMesh { kind, model: path_resolver(bld_root, &m), material: path_resolver(bld_root, &mtl) });
+    }
+
+    Ok((meshes, effects))
+}
+
+
+// LOD is expected to have about the same extents as the main model. Some(warning) when any side
+// of the bounding box is off by more than 10% of the model's largest dimension (and at least 0.5 m).
+fn check_lod_bbox(name: &str, lod: Option<&RawBBox>, main: Option<&RawBBox>) -> Option<String> {
+    let (lod, main) = match (lod, main) {
+        (Some(lod), Some(main)) => (lod, main),
+        (None, Some(_)) => return Some(format!("{} has no geometry", name)),
+        _ => return None
+    };
+
+    let size = (main.v_max.x - main.v_min.x).max(main.v_max.y - main.v_min.y).max(main.v_max.z - main.v_min.z);
+    let diff = [lod.v_min.x - main.v_min.x, lod.v_min.y - main.v_min.y, lod.v_min.z - main.v_min.z,
+                lod.v_max.x - main.v_max.x, lod.v_max.y - main.v_max.y, lod.v_max.z - main.v_max.z]
+               .iter().fold(0f32, |acc, d| acc.max(d.abs()));
+
+    if diff > (size * 0.1).max(0.5) {
+        Some(format!("{} bounding box differs from MODEL by up to {:.1} m (model size {:.1} m)", name, diff, size))
+    } else {
+        None
+    }
 }
 
 
@@ -270,13 +731,18 @@ where REFS: Iterator<Item = ini::BuildingNodeRef<'a>>,
 }
 
 
-fn push_buildingini_errors(building_ini: &BuildingIni, model: &NmfInfo, errors: &mut Vec<String>, _pfx: &str) {
+// Lints with the 'deny' level are errors, others are warnings
+fn push_buildingini_errors(building_ini: &BuildingIni, (model, path, src): (&NmfInfo, &Path, &str), errors: &mut Vec<Diagnostic>, _pfx: &str) {
     let obj_names: Vec<_> = model.object_names().collect();
-    if let Err(mut e) = validate_building_ini_refs(building_ini.get_model_refs(), &obj_names[..]) {
-        errors.append(&mut e);
+    if let Err(e) = validate_building_ini_refs(building_ini.get_model_refs(), &obj_names[..]) {
+        errors.extend(e.into_iter().map(|msg| Diagnostic::error("node-missing", msg).in_file(path)));
     }
 
-    // TODO: add other building.ini checks
+    let bbox = model.bbox();
+    for l in lint::check(building_ini, bbox.as_ref(), &APP_SETTINGS.lints) {
+        let severity = if l.level == lint::Level::Deny { Severity::Error } else { Severity::Warning };
+        errors.push(Diagnostic::new(severity, l.rule, l.message).in_file(path).at(src, l.span));
+    }
 }
 
 pub fn validate_mtl_refs<REF, SM, SMS>(mtl_refs: &[REF], used_submaterials: SMS) -> Result<(), Vec<String>>
@@ -300,7 +766,7 @@ where REF:  AsRef<str>,
     }
 }
 
-pub fn push_mtl_errors<P: Display, SM, SMS>(mtl: &MaterialMtl, used_submaterials: SMS, errors: &mut Vec<String>, pfx: P)
+pub fn push_mtl_errors<P: Display, SM, SMS>(mtl: &MaterialMtl, (mtl_path, used_submaterials): (&Path, SMS), errors: &mut Vec<Diagnostic>, pfx: P)
 where SM:  AsRef<str>,
       SMS: Iterator<Item = SM>
 {
@@ -314,20 +780,41 @@ where SM:  AsRef<str>,
         _ => None
     }).collect::<Vec<_>>();
 
-    if let Err(mut e) = validate_mtl_refs(&mtl_tokens[..], used_submaterials) {
-        errors.push(format!("Errors in {}", pfx));
-        errors.append(&mut e);
+    if let Err(e) = validate_mtl_refs(&mtl_tokens[..], used_submaterials) {
+        errors.extend(e.into_iter().map(|msg| Diagnostic::error("submaterial-missing", format!("{} ({})", msg, pfx)).in_file(mtl_path)));
     }
 }
 
 
-fn concat_parse_errors(errors: Vec<(&str, String)>) -> String {
-    let mut result = String::with_capacity(4 * 1024);
-    for (chunk, err) in errors.iter() {
-        writeln!(result, "Error: {}\nChunk: [{}]", err, chunk).unwrap();
-    }
+// Checks DDS headers of all existing textures referenced in the mtl.
+// Missing textures are reported by the caller.
+pub fn push_texture_errors<P: Display>(mtl: &MaterialMtl, mtl_root: &Path, errors: &mut Vec<Diagnostic>, pfx: P) {
+    let mut checked = Vec::<(PathBuf, bool)>::with_capacity(8);
+
+    for t in mtl.tokens() {
+        let (tx_path, need_mips) = match t {
+            MT::Texture((_, p))         => (ini::resolve_stock_path(p), true),
+            MT::TextureNoMip((_, p))    => (ini::resolve_stock_path(p), false),
+            MT::TextureMtl((_, p))      => (ini::resolve_source_path(mtl_root, p), true),
+            MT::TextureNoMipMtl((_, p)) => (ini::resolve_source_path(mtl_root, p), false),
+            _ => continue
+        };
+
+        if !tx_path.exists() || checked.iter().any(|(p, m)| *p == tx_path && *m == need_mips) {
+            continue;
+        }
 
-    result
+        match dds::Header::from_path(&tx_path) {
+            Ok(header) => {
+                for (severity, e) in header.validate(need_mips) {
+                    errors.push(Diagnostic::new(severity, "texture-invalid", format!("{} ({})", e, pfx)).in_file(&tx_path));
+                }
+            },
+            Err(e) => errors.push(Diagnostic::error("texture-invalid", format!("{} ({})", e, pfx)).in_file(&tx_path))
+        }
+
+        checked.push((tx_path, need_mips));
+    }
 }
 
 
@@ -335,8 +822,8 @@ fn push_textures<F>(mtl_path: &Path, textures: &mut Vec<PathBuf>, mtl_path_resol
 where F: Fn(&Path, &IdStringParam) -> PathBuf 
 {
     let mtl_root = mtl_path.parent().expect(&format!("Cannot get mtl root from {}", mtl_path.display()));
-    let mtl_buf = fs::read_to_string(mtl_path).map_err(|e| BuildingError::FileIO(mtl_path.to_path_buf(), e.to_string()))?;
-    let mtl = ini::parse_mtl(&mtl_buf).map_err(|e| BuildingError::Parse(mtl_path.to_path_buf(), concat_parse_errors(e)))?;
+    let (mtl_buf, _) = read_text(mtl_path).map_err(|e| BuildingError::FileIO(mtl_path.to_path_buf(), e.to_string()))?;
+    let mtl = ini::parse_mtl(&mtl_buf).map_err(|e| BuildingError::Parse(ini::diagnostic::parse_diagnostics(mtl_path, &mtl_buf, &e)))?;
     for tx_path in mtl.get_texture_paths(|p| mtl_path_resolver(mtl_root, p)) {
         if textures.iter().all(|x| *x != tx_path) {
             textures.push(tx_path);
@@ -373,6 +860,12 @@ impl Display for ModBuildingDef {
         w_optln!(f, "  model_e:          {}", self.model_e)?;
         writeln!(f, "  material:         {}", self.material.display())?;
         w_optln!(f, "  material_e:       {}", self.material_e)?;
+        for mesh in self.extra_meshes.iter() {
+            writeln!(f, "  {:<17} {}, {}", format!("{}:", mesh.kind.token_name().to_lowercase()), mesh.model.display(), mesh.material.display())?;
+        }
+        for fx in self.effects.iter() {
+            writeln!(f, "  {:<17} {}", format!("{}:", fx.token.to_lowercase()), fx.path.display())?;
+        }
         writeln!(f, "  textures: [")?;
         for tx in self.textures.iter() {
             writeln!(f, "    {}", tx.display())?;
@@ -385,11 +878,20 @@ impl Display for ModBuildingDef {
 impl Display for BuildingError {
     fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
         match self {
-            BuildingError::FileIO(path, e)    => write!(f, "File error ({}): {}", path.display(), e),
-            BuildingError::Parse(path, e)     => write!(f, "Parse error ({}): {}", path.display(), e),
-            BuildingError::ModelMissing       => write!(f, "Model is missing"),
-            BuildingError::MaterialMissing    => write!(f, "Material is missing"),
-            BuildingError::Validation(e)      => write!(f, "Validation failed: {:#?}", e),
+            BuildingError::FileIO(path, e)       => write!(f, "File error ({}): {}", path.display(), e),
+            BuildingError::Parse(d)              => {
+                writeln!(f, "Parse error:")?;
+                d.iter().try_for_each(|d| write!(f, "{}", d))
+            },
+            BuildingError::ModelMissing(path)    => write!(f, "Model is missing ({})", path.display()),
+            BuildingError::MaterialMissing(path) => write!(f, "Material is missing ({})", path.display()),
+            BuildingError::Validation(d)         => {
+                writeln!(f, "Validation failed:")?;
+                for line in d.iter().flat_map(|d| d.to_string().lines().map(str::to_string).collect::<Vec<_>>()) {
+                    writeln!(f, "    {}", line)?;
+                }
+                Ok(())
+            },
         }
     }
 }
@@ -4,6 +4,13 @@ use std::str::FromStr;
 use lazy_static::lazy_static;
 use normpath::BasePathBuf;
 
+use crate::dds;
+use crate::encoding::TextEncoding;
+use crate::ini::building::BuildingType;
+use crate::ini::building::lint::{self, LintConfig};
+use crate::ini::schema::Schema;
+use crate::report::Format;
+
 
 pub const RENDERCONFIG_INI: &str = "renderconfig.ini";
 pub const BUILDING_INI:     &str = "building.ini";
@@ -14,6 +21,8 @@ pub enum AppCommand {
     Nmf(NmfCommand),
     ModBuilding(ModCommand),
     Ini(IniCommand),
+    Dds(DdsCommand),
+    Tokens(TokensCommand),
 }
 
 //-----------------------------
@@ -29,9 +38,57 @@ pub enum NmfCommand {
 //-------------------------------
 
 pub enum ModCommand {
-    Validate(PathBuf),
+    Validate(ValidateCommand),
     Scale(ScaleCommand),
     Mirror(FromToCommand),
+    ApplyGizmos(ApplyGizmosCommand),
+    Thumbnail(ThumbnailCommand),
+    Unused(UnusedCommand),
+    Copy(CopyCommand),
+    CloneStock(CloneStockCommand),
+    New(NewCommand),
+}
+
+pub struct ValidateCommand {
+    pub dir_input: PathBuf,
+    pub fix_case: bool,
+    pub fix: bool,
+    pub dry_run: bool,
+}
+
+pub struct ApplyGizmosCommand {
+    pub dir_input: PathBuf,
+    pub gizmos: PathBuf,
+}
+
+pub struct UnusedCommand {
+    pub dir_input: PathBuf,
+    pub delete: bool,
+}
+
+pub struct CopyCommand {
+    pub dir_input: PathBuf,
+    pub dir_output: PathBuf,
+    pub deep: bool,
+}
+
+pub struct CloneStockCommand {
+    pub name: String,
+    pub dir_output: PathBuf,
+    pub copy: bool,
+}
+
+pub struct NewCommand {
+    pub dir_output: PathBuf,
+    pub model: PathBuf,
+    pub textures: Vec<PathBuf>,
+    pub building_type: BuildingType,
+}
+
+pub struct ThumbnailCommand {
+    pub dir_input: PathBuf,
+    pub size: usize,
+    pub angle: f32,
 }
 
 //-------------------------------
@@ -44,6 +101,61 @@ pub enum IniCommand {
     ScaleRender(ScaleCommand),
     MirrorBuilding(FromToCommand),
     MirrorRender(FromToCommand),
+    ExportBuilding(PathBuf),
+    ExportRender(PathBuf),
+    ExportMtl(PathBuf),
+    ImportBuilding(FromToCommand),
+    ImportRender(FromToCommand),
+    ImportMtl(FromToCommand),
+    DiffBuilding(DiffCommand),
+    DiffRender(DiffCommand),
+    FmtBuilding(FmtCommand),
+}
+
+pub enum TokensCommand {
+    List(TokensListCommand),
+    Explain(String),
+}
+
+pub struct TokensListCommand {
+    pub file: Option<String>,
+    pub filter: Option<String>,
+}
+
+//-------------------------------
+
+pub struct FmtCommand {
+    pub path: PathBuf,
+    pub check: bool,
+    pub sort: bool,
+}
+
+pub struct DiffCommand {
+    pub old: PathBuf,
+    pub new: PathBuf,
+    pub tolerance: f64,
+}
+
+//-------------------------------
+
+pub enum DdsCommand {
+    Show(PathBuf),
+    ToPng(FromToCommand),
+    FromPng(DdsFromPngCommand),
+    Resize(DdsResizeCommand),
+}
+
+pub struct DdsFromPngCommand {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub format: dds::Format,
+    pub mips: bool,
+}
+
+pub struct DdsResizeCommand {
+    pub input: PathBuf,
+    pub max_size: usize,
+    pub output: PathBuf,
 }
 
 //-------------------------------
@@ -76,6 +188,12 @@ pub struct ScaleCommand {
 pub struct AppSettings {
     pub path_stock: BasePathBuf,
     pub path_workshop: BasePathBuf,
+    pub lints: LintConfig,
+    pub schema: Schema,
+    // encoding of text files which are not UTF-8; None: detect
+    pub encoding: Option<TextEncoding>,
+    // output of the validation commands
+    pub format: Format,
 
     pub command: AppCommand,
 }
@@ -144,7 +262,13 @@ lazy_static! {
         let cmd_modbuilding = {
             let cmd_mod_validate = SubCommand::with_name("validate")
                 .about("Checks the specified building mod for errors")
-                .arg(Arg::with_name("dir-input").required(true));
+                .arg(Arg::with_name("dir-input").required(true))
+                .arg(Arg::with_name("fix-case").long("fix-case")
+                    .help("Rewrites the path tokens to the case of the files on disk before validating"))
+                .arg(Arg::with_name("fix").long("fix")
+                    .help("Applies the safe fixes (missing submaterials, dangling nodes, moved stock textures, imagegui.png) before validating"))
+                .arg(Arg::with_name("dry-run").long("dry-run").requires("fix")
+                    .help("Shows the changes of --fix without writing them"));
 
             let cmd_modbuilding_scale = SubCommand::with_name("scale")
                 .about("Scales the whole building (models and .ini files) by the specified factor")
@@ -157,11 +281,60 @@ lazy_static! {
                 .arg(Arg::with_name("dir-input").required(true))
                 .arg(Arg::with_name("dir-output").required(true));
 
+            let cmd_modbuilding_gizmos = SubCommand::with_name("apply-gizmos")
+                .about("Updates building.ini coordinates from named markers in the *.obj / *.gltf / *.glb file (see README for the naming scheme)")
+                .arg(Arg::with_name("dir-input").required(true))
+                .arg(Arg::with_name("gizmos-file").required(true));
+
+            let cmd_modbuilding_thumbnail = SubCommand::with_name("thumbnail")
+                .about("Renders the building's main model with its diffuse textures into imagegui.png")
+                .arg(Arg::with_name("dir-input").required(true))
+                .arg(Arg::with_name("size").long("size").takes_value(true).default_value("256"))
+                .arg(Arg::with_name("angle").long("angle").takes_value(true).default_value("45")
+                    .help("Camera rotation around the vertical axis, in degrees"));
+
+            let cmd_modbuilding_unused = SubCommand::with_name("unused")
+                .about("Lists the files in the building directory which the building does not use")
+                .arg(Arg::with_name("dir-input").required(true))
+                .arg(Arg::with_name("delete").long("delete")
+                    .help("Moves the unused files into the '_unused' folder of the building directory"));
+
+            let cmd_modbuilding_copy = SubCommand::with_name("copy")
+                .about("Copies the building files into the output directory")
+                .arg(Arg::with_name("dir-input").required(true))
+                .arg(Arg::with_name("dir-output").required(true))
+                .arg(Arg::with_name("deep").long("deep")
+                    .help("Also copies the files outside of the building directory (stock, workshop, '../') and makes the references local"));
+
+            let cmd_modbuilding_clone_stock = SubCommand::with_name("clone-stock")
+                .about("Starts a new mod in the target directory from a stock building")
+                .arg(Arg::with_name("stock-building-name").required(true)
+                    .help("Name of the stock building directory, or its path relative to the stock game files"))
+                .arg(Arg::with_name("target-dir").required(true))
+                .arg(Arg::with_name("copy").long("copy")
+                    .help("Copies the models, materials, textures and sounds into the mod instead of referencing the stock files"));
+
+            let cmd_modbuilding_new = SubCommand::with_name("new")
+                .about("Creates a new building from the model: renderconfig.ini, material.mtl and a building.ini skeleton")
+                .arg(Arg::with_name("dir-output").required(true))
+                .arg(Arg::with_name("model").long("model").takes_value(true).required(true))
+                .arg(Arg::with_name("textures").long("textures").takes_value(true).multiple(true)
+                    .help("Diffuse textures (*.dds). A submaterial gets the texture with its name, or the first one"))
+                .arg(Arg::with_name("type").long("type").takes_value(true).required(true)
+                    .possible_values(BuildingType::NAMES).case_insensitive(true)
+                    .help("Building type ($TYPE_ token of building.ini)"));
+
             SubCommand::with_name("mod-building")
                 .about("Operations for whole mods")
                 .subcommand(cmd_mod_validate)
                 .subcommand(cmd_modbuilding_scale)
                 .subcommand(cmd_modbuilding_mirror)
+                .subcommand(cmd_modbuilding_gizmos)
+                .subcommand(cmd_modbuilding_thumbnail)
+                .subcommand(cmd_modbuilding_unused)
+                .subcommand(cmd_modbuilding_copy)
+                .subcommand(cmd_modbuilding_clone_stock)
+                .subcommand(cmd_modbuilding_new)
         };
 
         let cmd_modpack = {
@@ -239,11 +412,121 @@ lazy_static! {
                     .subcommand(cmd_ini_mirror_render)
             };
 
+            let cmd_ini_export = {
+                let mk_export = |name, file| SubCommand::with_name(name)
+                    .about(file)
+                    .arg(Arg::with_name("path").required(true))
+                    .arg(Arg::with_name("format").long("format").takes_value(true)
+                        .possible_values(&["json"]).default_value("json"));
+
+                SubCommand::with_name("export")
+                    .about("Print tokens of the specified file in a structured format")
+                    .subcommand(mk_export("building",     "Parse the specified building.ini, print its tokens"))
+                    .subcommand(mk_export("renderconfig", "Parse the specified renderconfig.ini, print its tokens"))
+                    .subcommand(mk_export("mtl",          "Parse the specified *.mtl, print its tokens"))
+            };
+
+            let cmd_ini_import = {
+                let mk_import = |name, file| SubCommand::with_name(name)
+                    .about(file)
+                    .arg(Arg::with_name("json-input").required(true))
+                    .arg(Arg::with_name("output").required(true));
+
+                SubCommand::with_name("import")
+                    .about("Write a file from tokens in JSON (as printed by 'ini export')")
+                    .subcommand(mk_import("building",     "Read tokens from the specified *.json, save them as a new building.ini"))
+                    .subcommand(mk_import("renderconfig", "Read tokens from the specified *.json, save them as a new renderconfig.ini"))
+                    .subcommand(mk_import("mtl",          "Read tokens from the specified *.json, save them as a new *.mtl"))
+            };
+
+            let cmd_ini_diff = {
+                let mk_diff = |name, file| SubCommand::with_name(name)
+                    .about(file)
+                    .arg(Arg::with_name("ini-old").required(true))
+                    .arg(Arg::with_name("ini-new").required(true))
+                    .arg(Arg::with_name("tolerance").long("tolerance").takes_value(true).default_value("0.0001")
+                        .help("Numbers closer than this are considered equal"));
+
+                SubCommand::with_name("diff")
+                    .about("Compare tokens of two files, ignoring their order and formatting")
+                    .subcommand(mk_diff("building",     "Compare two building.ini files"))
+                    .subcommand(mk_diff("renderconfig", "Compare two renderconfig.ini files"))
+            };
+
+            let cmd_ini_fmt = {
+                let cmd_ini_fmt_building = SubCommand::with_name("building")
+                    .about("Rewrite the specified building.ini in the canonical format")
+                    .arg(Arg::with_name("path").required(true))
+                    .arg(Arg::with_name("check").long("check")
+                        .help("Do not write the file, exit with code 1 if it needs formatting"))
+                    .arg(Arg::with_name("sort").long("sort")
+                        .help("Order tokens by groups: identity, economy, storage, stations, connections, visuals, costs"));
+
+                SubCommand::with_name("fmt")
+                    .about("Formatting *.ini files")
+                    .subcommand(cmd_ini_fmt_building)
+            };
+
             SubCommand::with_name("ini")
                 .about("Operations for individual text-based files")
                 .subcommand(cmd_ini_parse)
                 .subcommand(cmd_ini_scale)
                 .subcommand(cmd_ini_mirror)
+                .subcommand(cmd_ini_export)
+                .subcommand(cmd_ini_import)
+                .subcommand(cmd_ini_diff)
+                .subcommand(cmd_ini_fmt)
+        };
+
+        let cmd_dds = {
+            let cmd_dds_show = SubCommand::with_name("show")
+                .about("Parse the specified *.dds header, print it and check for problems")
+                .arg(Arg::with_name("dds-path").required(true));
+
+            let cmd_dds_topng = SubCommand::with_name("to-png")
+                .about("Convert the top-level image of the specified *.dds to *.png")
+                .arg(Arg::with_name("dds-input").required(true))
+                .arg(Arg::with_name("png-output").required(true));
+
+            let cmd_dds_frompng = SubCommand::with_name("from-png")
+                .about("Convert the specified *.png to *.dds, generating mips")
+                .arg(Arg::with_name("png-input").required(true))
+                .arg(Arg::with_name("dds-output").required(true))
+                .arg(Arg::with_name("format").long("format").takes_value(true)
+                    .possible_values(&["bc1", "bc3", "bc5", "rgba"]).default_value("bc3"))
+                .arg(Arg::with_name("no-mips").long("no-mips")
+                    .help("Do not generate mips (for textures used with $TEXTURE_NOMIP)"));
+
+            let cmd_dds_resize = SubCommand::with_name("resize")
+                .about("Halve the specified *.dds until both sides fit into max-size, save to a new file")
+                .arg(Arg::with_name("dds-input").required(true))
+                .arg(Arg::with_name("max-size").required(true))
+                .arg(Arg::with_name("dds-output").required(true));
+
+            SubCommand::with_name("dds")
+                .about("Operations for *.dds textures")
+                .subcommand(cmd_dds_show)
+                .subcommand(cmd_dds_topng)
+                .subcommand(cmd_dds_frompng)
+                .subcommand(cmd_dds_resize)
+        };
+
+        let cmd_tokens = {
+            let cmd_tokens_list = SubCommand::with_name("list")
+                .about("List the supported tokens with their parameters, and the allowed literals")
+                .arg(Arg::with_name("file").possible_values(&["building", "renderconfig", "mtl"])
+                    .help("Only the tokens of this file type"))
+                .arg(Arg::with_name("filter").long("filter").takes_value(true)
+                    .help("Only the tokens and literals containing this text (case-insensitive)"));
+
+            let cmd_tokens_explain = SubCommand::with_name("explain")
+                .about("Print the parameters, variants and an example of the specified token")
+                .arg(Arg::with_name("name").required(true));
+
+            SubCommand::with_name("tokens")
+                .about("Reference of the tokens in *.ini and *.mtl files")
+                .subcommand(cmd_tokens_list)
+                .subcommand(cmd_tokens_explain)
         };
 
         let m = App::new("wrsr-mt")
@@ -262,15 +545,52 @@ lazy_static! {
                     .long("workshop")
                     .default_value(r"C:\Program Files (x86)\Steam\steamapps\workshop\content\784150")
             )
+            .arg(Arg::with_name("allow").long("allow").takes_value(true).multiple(true).number_of_values(1)
+                .help("Disable the building.ini lint rule (see README for the list of rules)"))
+            .arg(Arg::with_name("warn").long("warn").takes_value(true).multiple(true).number_of_values(1)
+                .help("Report the building.ini lint rule as a warning"))
+            .arg(Arg::with_name("deny").long("deny").takes_value(true).multiple(true).number_of_values(1)
+                .help("Report the building.ini lint rule as an error (fails validation)"))
+            .arg(Arg::with_name("schema").long("schema").takes_value(true)
+                .help("Token schema file (JSON), declaring extra tokens unknown to the parser or redeclaring existing ones (see README)"))
+            .arg(Arg::with_name("encoding").long("encoding").takes_value(true)
+                .possible_values(&["auto", "utf-8", "cp1250", "cp1251", "cp1252"]).default_value("auto")
+                .help("Encoding of *.ini and *.mtl files which are not UTF-8 (auto: cp1250 or cp1251); utf-8 rejects such files"))
+            .arg(Arg::with_name("format").long("format").takes_value(true)
+                .possible_values(&["text", "json", "sarif"]).default_value("text")
+                .help("Output of 'mod-building validate', 'modpack validate' and 'modpack install' diagnostics"))
             .subcommand(cmd_nmf)
             .subcommand(cmd_modbuilding)
             .subcommand(cmd_ini)
             .subcommand(cmd_modpack)
+            .subcommand(cmd_dds)
+            .subcommand(cmd_tokens)
             .get_matches();
 
         let path_stock    = BasePathBuf::new(m.value_of("stock").unwrap()).unwrap();
         let path_workshop = BasePathBuf::new(m.value_of("workshop").unwrap()).unwrap();
 
+        let mut lints = LintConfig::default();
+        for (arg, level) in [("allow", lint::Level::Allow), ("warn", lint::Level::Warn), ("deny", lint::Level::Deny)] {
+            for rule in m.values_of(arg).into_iter().flatten() {
+                if let Err(e) = lints.set(rule, level) {
+                    eprintln!("Error: --{}: {}", arg, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        let schema = match Schema::load(m.value_of("schema").map(std::path::Path::new)) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Error: --schema: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+        let encoding = TextEncoding::from_name(m.value_of("encoding").unwrap());
+        let format = Format::from_name(m.value_of("format").unwrap()).unwrap();
+
         let command = { 
             use normpath::BasePathBuf;
             let run_dir = BasePathBuf::try_new(std::env::current_dir().unwrap()).unwrap();
@@ -319,13 +639,85 @@ lazy_static! {
                         ("renderconfig", Some(m)) => IniCommand::MirrorRender(mk_from_to(m, "ini-input", "ini-output")),
                         (cname, _)                => panic!("Unknown ini mirror subcommand '{}'" , cname)
                     },
+                    ("export", Some(m)) => match m.subcommand() {
+                        ("building", Some(m))     => IniCommand::ExportBuilding(mk_path(m, "path")),
+                        ("renderconfig", Some(m)) => IniCommand::ExportRender(mk_path(m, "path")),
+                        ("mtl", Some(m))          => IniCommand::ExportMtl(mk_path(m, "path")),
+                        (cname, _)                => panic!("Unknown ini export subcommand '{}'" , cname)
+                    },
+                    ("import", Some(m)) => match m.subcommand() {
+                        ("building", Some(m))     => IniCommand::ImportBuilding(mk_from_to(m, "json-input", "output")),
+                        ("renderconfig", Some(m)) => IniCommand::ImportRender(mk_from_to(m, "json-input", "output")),
+                        ("mtl", Some(m))          => IniCommand::ImportMtl(mk_from_to(m, "json-input", "output")),
+                        (cname, _)                => panic!("Unknown ini import subcommand '{}'" , cname)
+                    },
+                    ("fmt", Some(m)) => match m.subcommand() {
+                        ("building", Some(m)) => IniCommand::FmtBuilding(FmtCommand {
+                            path:  mk_path(m, "path"),
+                            check: m.is_present("check"),
+                            sort:  m.is_present("sort"),
+                        }),
+                        (cname, _) => panic!("Unknown ini fmt subcommand '{}'" , cname)
+                    },
+                    ("diff", Some(m)) => {
+                        let (cname, m) = m.subcommand();
+                        let m = m.unwrap_or_else(|| panic!("Unknown ini diff subcommand '{}'", cname));
+                        let tolerance = f64::from_str(m.value_of("tolerance").unwrap()).expect("Cannot parse tolerance as float");
+                        let diff = DiffCommand { old: mk_path(m, "ini-old"), new: mk_path(m, "ini-new"), tolerance };
+                        match cname {
+                            "building"     => IniCommand::DiffBuilding(diff),
+                            "renderconfig" => IniCommand::DiffRender(diff),
+                            _              => panic!("Unknown ini diff subcommand '{}'" , cname)
+                        }
+                    },
                     (cname, _) => panic!("Unknown ini subcommand '{}'" , cname)
                 }),
 
                 ("mod-building", Some(m)) => AppCommand::ModBuilding(match m.subcommand() {
-                    ("validate", Some(m)) => ModCommand::Validate(mk_path(m, "dir-input")),
+                    ("validate", Some(m)) => {
+                        let dir_input = mk_path(m, "dir-input");
+                        ModCommand::Validate(ValidateCommand {
+                            dir_input,
+                            fix_case: m.is_present("fix-case"),
+                            fix: m.is_present("fix"),
+                            dry_run: m.is_present("dry-run"),
+                        })
+                    },
                     ("scale", Some(m))    => ModCommand::Scale(mk_scale(m, "dir-input", "dir-output")),
                     ("mirror", Some(m))   => ModCommand::Mirror(mk_from_to(m, "dir-input", "dir-output")),
+                    ("apply-gizmos", Some(m)) => {
+                        let dir_input = mk_path(m, "dir-input");
+                        let gizmos = mk_path(m, "gizmos-file");
+                        ModCommand::ApplyGizmos(ApplyGizmosCommand { dir_input, gizmos })
+                    },
+                    ("thumbnail", Some(m)) => {
+                        let dir_input = mk_path(m, "dir-input");
+                        let size = usize::from_str(m.value_of("size").unwrap()).expect("Cannot parse size as integer");
+                        let angle = f32::from_str(m.value_of("angle").unwrap()).expect("Cannot parse angle as float");
+                        assert!(size > 0, "Size must be positive");
+                        ModCommand::Thumbnail(ThumbnailCommand { dir_input, size, angle })
+                    },
+                    ("copy", Some(m)) => {
+                        let dir_input = mk_path(m, "dir-input");
+                        let dir_output = mk_path(m, "dir-output");
+                        ModCommand::Copy(CopyCommand { dir_input, dir_output, deep: m.is_present("deep") })
+                    },
+                    ("clone-stock", Some(m)) => {
+                        let name = m.value_of("stock-building-name").unwrap().to_string();
+                        let dir_output = mk_path(m, "target-dir");
+                        ModCommand::CloneStock(CloneStockCommand { name, dir_output, copy: m.is_present("copy") })
+                    },
+                    ("new", Some(m)) => {
+                        let dir_output = mk_path(m, "dir-output");
+                        let model = mk_path(m, "model");
+                        let textures = m.values_of("textures").map_or_else(Vec::new, |v| v.map(|t| run_dir.join(t).into_path_buf()).collect());
+                        let building_type = BuildingType::from_str(&m.value_of("type").unwrap().to_uppercase()).unwrap();
+                        ModCommand::New(NewCommand { dir_output, model, textures, building_type })
+                    },
+                    ("unused", Some(m)) => {
+                        let dir_input = mk_path(m, "dir-input");
+                        ModCommand::Unused(UnusedCommand { dir_input, delete: m.is_present("delete") })
+                    },
                     (cname, _)            => panic!("Unknown mod subcommand '{}'" , cname)
                 }),
 
@@ -339,6 +731,39 @@ lazy_static! {
                     (cname, _) => panic!("Unknown nmf subcommand '{}'" , cname)
                 }),
 
+                ("dds", Some(m)) => AppCommand::Dds(match m.subcommand() {
+                    ("show",   Some(m)) => DdsCommand::Show(mk_path(m, "dds-path")),
+                    ("to-png", Some(m)) => DdsCommand::ToPng(mk_from_to(m, "dds-input", "png-output")),
+                    ("from-png", Some(m)) => {
+                        let FromToCommand { input, output } = mk_from_to(m, "png-input", "dds-output");
+                        let format = match m.value_of("format").unwrap() {
+                            "bc1" => dds::Format::Bc1,
+                            "bc3" => dds::Format::Bc3,
+                            "bc5" => dds::Format::Bc5,
+                            _     => dds::Format::Rgba8,
+                        };
+                        let mips = !m.is_present("no-mips");
+                        DdsCommand::FromPng(DdsFromPngCommand { input, output, format, mips })
+                    },
+                    ("resize", Some(m)) => {
+                        let FromToCommand { input, output } = mk_from_to(m, "dds-input", "dds-output");
+                        let max_size = usize::from_str(m.value_of("max-size").unwrap()).expect("Cannot parse max-size as integer");
+                        assert!(max_size > 0, "max-size must be positive");
+                        DdsCommand::Resize(DdsResizeCommand { input, max_size, output })
+                    },
+                    (cname, _) => panic!("Unknown dds subcommand '{}'" , cname)
+                }),
+
+                ("tokens", Some(m)) => AppCommand::Tokens(match m.subcommand() {
+                    ("list", Some(m)) => {
+                        let file = m.value_of("file").map(String::from);
+                        let filter = m.value_of("filter").map(String::from);
+                        TokensCommand::List(TokensListCommand { file, filter })
+                    },
+                    ("explain", Some(m)) => TokensCommand::Explain(m.value_of("name").unwrap().to_string()),
+                    (cname, _) => panic!("Unknown tokens subcommand '{}'", cname)
+                }),
+
                 _ => {
                     eprintln!("Error: missing arguments. Run with '--help' to see usage instructions");
                     std::process::exit(1);
@@ -349,6 +774,10 @@ lazy_static! {
         AppSettings {
             path_stock,
             path_workshop,
+            lints,
+            schema,
+            encoding,
+            format,
             command
         }
     };
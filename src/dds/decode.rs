@@ -0,0 +1,143 @@
+// Block-compressed formats: every 4x4 block of pixels is stored in 8 or 16 bytes.
+
+pub fn decode_blocks<F>(data: &[u8], width: usize, height: usize, block_len: usize, block_decoder: F) -> Vec<u8>
+where F: Fn(&[u8], &mut [[u8; 4]; 16])
+{
+    let mut pixels = vec![0u8; width * height * 4];
+    let blocks_x = width.div_ceil(4).max(1);
+    let mut block = [[0u8; 4]; 16];
+
+    for (i, src) in data.chunks_exact(block_len).enumerate() {
+        block_decoder(src, &mut block);
+
+        let bx = (i % blocks_x) * 4;
+        let by = (i / blocks_x) * 4;
+
+        for (j, px) in block.iter().enumerate() {
+            let x = bx + j % 4;
+            let y = by + j / 4;
+            if x < width && y < height {
+                let dst = (y * width + x) * 4;
+                pixels[dst .. dst + 4].copy_from_slice(px);
+            }
+        }
+    }
+
+    pixels
+}
+
+
+fn rgb565(c: u16) -> [u8; 3] {
+    let r = ((c >> 11) & 0x1f) as u32;
+    let g = ((c >> 5) & 0x3f) as u32;
+    let b = (c & 0x1f) as u32;
+    [((r * 255 + 15) / 31) as u8, ((g * 255 + 31) / 63) as u8, ((b * 255 + 15) / 31) as u8]
+}
+
+
+fn mix(a: u8, b: u8, wa: u32, wb: u32) -> u8 {
+    ((a as u32 * wa + b as u32 * wb) / (wa + wb)) as u8
+}
+
+
+pub(super) fn color_palette(c0: u16, c1: u16, force_4_colors: bool) -> [[u8; 4]; 4] {
+    let (p0, p1) = (rgb565(c0), rgb565(c1));
+
+    let mut palette = [[p0[0], p0[1], p0[2], 255], [p1[0], p1[1], p1[2], 255], [0; 4], [0; 4]];
+    if c0 > c1 || force_4_colors {
+        for k in 0 .. 3 {
+            palette[2][k] = mix(p0[k], p1[k], 2, 1);
+            palette[3][k] = mix(p0[k], p1[k], 1, 2);
+        }
+        palette[2][3] = 255;
+        palette[3][3] = 255;
+    } else {
+        for k in 0 .. 3 {
+            palette[2][k] = mix(p0[k], p1[k], 1, 1);
+        }
+        palette[2][3] = 255;
+        // palette[3] is transparent black
+    }
+
+    palette
+}
+
+
+fn color_block(src: &[u8], block: &mut [[u8; 4]; 16], force_4_colors: bool) {
+    let c0 = u16::from_le_bytes([src[0], src[1]]);
+    let c1 = u16::from_le_bytes([src[2], src[3]]);
+    let palette = color_palette(c0, c1, force_4_colors);
+
+    let indices = u32::from_le_bytes([src[4], src[5], src[6], src[7]]);
+    for (j, px) in block.iter_mut().enumerate() {
+        *px = palette[((indices >> (j * 2)) & 0x3) as usize];
+    }
+}
+
+
+pub(super) fn channel_palette(a0: u8, a1: u8) -> [u8; 8] {
+    let (a0, a1) = (a0 as u32, a1 as u32);
+
+    let mut palette = [0u8; 8];
+    palette[0] = a0 as u8;
+    palette[1] = a1 as u8;
+    if a0 > a1 {
+        for k in 1 ..= 6 {
+            palette[k + 1] = ((a0 * (7 - k as u32) + a1 * k as u32) / 7) as u8;
+        }
+    } else {
+        for k in 1 ..= 4 {
+            palette[k + 1] = ((a0 * (5 - k as u32) + a1 * k as u32) / 5) as u8;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+
+    palette
+}
+
+
+// BC3 alpha and BC5 channels use the same 8-byte interpolated block
+fn channel_block(src: &[u8], out: &mut [u8; 16]) {
+    let palette = channel_palette(src[0], src[1]);
+
+    let mut bits = 0u64;
+    for (k, b) in src[2 .. 8].iter().enumerate() {
+        bits |= (*b as u64) << (8 * k);
+    }
+
+    for (j, v) in out.iter_mut().enumerate() {
+        *v = palette[((bits >> (j * 3)) & 0x7) as usize];
+    }
+}
+
+
+pub fn bc1_block(src: &[u8], block: &mut [[u8; 4]; 16]) {
+    color_block(src, block, false);
+}
+
+
+pub fn bc3_block(src: &[u8], block: &mut [[u8; 4]; 16]) {
+    let mut alpha = [0u8; 16];
+    channel_block(&src[0 .. 8], &mut alpha);
+    color_block(&src[8 .. 16], block, true);
+    for (px, a) in block.iter_mut().zip(alpha.iter()) {
+        px[3] = *a;
+    }
+}
+
+
+// two-channel normal maps: blue is reconstructed from red and green
+pub fn bc5_block(src: &[u8], block: &mut [[u8; 4]; 16]) {
+    let mut red = [0u8; 16];
+    let mut green = [0u8; 16];
+    channel_block(&src[0 .. 8], &mut red);
+    channel_block(&src[8 .. 16], &mut green);
+
+    for (j, px) in block.iter_mut().enumerate() {
+        let x = red[j] as f32 / 127.5 - 1.0;
+        let y = green[j] as f32 / 127.5 - 1.0;
+        let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+        *px = [red[j], green[j], ((z + 1.0) * 127.5) as u8, 255];
+    }
+}
@@ -0,0 +1,139 @@
+// Simple range-fit block compression: endpoints are taken from the (slightly inset)
+// bounding box of block colors, every pixel gets the nearest palette entry.
+
+use super::decode::{color_palette, channel_palette};
+
+
+pub fn encode_blocks<F>(pixels: &[u8], width: usize, height: usize, block_len: usize, block_encoder: F) -> Vec<u8>
+where F: Fn(&[[u8; 4]; 16], &mut [u8])
+{
+    let blocks_x = width.div_ceil(4).max(1);
+    let blocks_y = height.div_ceil(4).max(1);
+    let mut result = vec![0u8; blocks_x * blocks_y * block_len];
+    let mut block = [[0u8; 4]; 16];
+
+    for (i, dst) in result.chunks_exact_mut(block_len).enumerate() {
+        let bx = (i % blocks_x) * 4;
+        let by = (i / blocks_x) * 4;
+
+        // pixels outside of the image repeat the edge
+        for (j, px) in block.iter_mut().enumerate() {
+            let x = (bx + j % 4).min(width - 1);
+            let y = (by + j / 4).min(height - 1);
+            let src = (y * width + x) * 4;
+            px.copy_from_slice(&pixels[src .. src + 4]);
+        }
+
+        block_encoder(&block, dst);
+    }
+
+    result
+}
+
+
+fn to_rgb565(c: [u8; 3]) -> u16 {
+    let r = (c[0] as u32 * 31 + 127) / 255;
+    let g = (c[1] as u32 * 63 + 127) / 255;
+    let b = (c[2] as u32 * 31 + 127) / 255;
+    ((r << 11) | (g << 5) | b) as u16
+}
+
+
+fn rgb_distance(a: &[u8; 4], b: &[u8; 4]) -> u32 {
+    (0 .. 3).map(|k| { let d = a[k] as i32 - b[k] as i32; (d * d) as u32 }).sum()
+}
+
+
+// three_color: BC1 mode with transparent pixels (index 3)
+fn color_block(block: &[[u8; 4]; 16], dst: &mut [u8], three_color: bool) {
+    let is_opaque = |px: &[u8; 4]| !three_color || px[3] >= 128;
+
+    let mut lo = [255u8; 3];
+    let mut hi = [0u8; 3];
+    for px in block.iter().filter(|px| is_opaque(px)) {
+        for k in 0 .. 3 {
+            lo[k] = lo[k].min(px[k]);
+            hi[k] = hi[k].max(px[k]);
+        }
+    }
+
+    if lo[0] > hi[0] {
+        // no opaque pixels
+        lo = [0; 3];
+        hi = [0; 3];
+    }
+
+    for k in 0 .. 3 {
+        let inset = (hi[k] - lo[k]) / 16;
+        lo[k] += inset;
+        hi[k] -= inset;
+    }
+
+    let (mut c0, mut c1) = (to_rgb565(hi), to_rgb565(lo));
+    if (c0 < c1) != three_color {
+        std::mem::swap(&mut c0, &mut c1);
+    }
+
+    let palette = color_palette(c0, c1, false);
+    let colors = if three_color || c0 == c1 { 3 } else { 4 };
+
+    let mut indices = 0u32;
+    for (j, px) in block.iter().enumerate() {
+        let idx = if is_opaque(px) {
+            (0 .. colors).min_by_key(|&i| rgb_distance(px, &palette[i])).unwrap()
+        } else {
+            3
+        };
+        indices |= (idx as u32) << (j * 2);
+    }
+
+    dst[0 .. 2].copy_from_slice(&c0.to_le_bytes());
+    dst[2 .. 4].copy_from_slice(&c1.to_le_bytes());
+    dst[4 .. 8].copy_from_slice(&indices.to_le_bytes());
+}
+
+
+fn channel_block(values: &[u8; 16], dst: &mut [u8]) {
+    let a0 = *values.iter().max().unwrap();
+    let a1 = *values.iter().min().unwrap();
+
+    // a0 > a1 selects the 8-value mode, a0 == a1 is a solid block (all indices 0)
+    let palette = channel_palette(a0, a1);
+
+    let mut bits = 0u64;
+    for (j, v) in values.iter().enumerate() {
+        let idx = (0 .. 8).min_by_key(|&i| (palette[i] as i32 - *v as i32).abs()).unwrap();
+        bits |= (idx as u64) << (j * 3);
+    }
+
+    dst[0] = a0;
+    dst[1] = a1;
+    dst[2 .. 8].copy_from_slice(&bits.to_le_bytes()[.. 6]);
+}
+
+
+fn channel(block: &[[u8; 4]; 16], k: usize) -> [u8; 16] {
+    let mut values = [0u8; 16];
+    for (v, px) in values.iter_mut().zip(block.iter()) {
+        *v = px[k];
+    }
+    values
+}
+
+
+pub fn bc1_block(block: &[[u8; 4]; 16], dst: &mut [u8]) {
+    let has_transparent = block.iter().any(|px| px[3] < 128);
+    color_block(block, dst, has_transparent);
+}
+
+
+pub fn bc3_block(block: &[[u8; 4]; 16], dst: &mut [u8]) {
+    channel_block(&channel(block, 3), &mut dst[0 .. 8]);
+    color_block(block, &mut dst[8 .. 16], false);
+}
+
+
+pub fn bc5_block(block: &[[u8; 4]; 16], dst: &mut [u8]) {
+    channel_block(&channel(block, 0), &mut dst[0 .. 8]);
+    channel_block(&channel(block, 1), &mut dst[8 .. 16]);
+}
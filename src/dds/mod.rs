@@ -0,0 +1,568 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::io::{self, Read};
+use std::convert::TryInto;
+
+use crate::ini::diagnostic::Severity;
+
+mod decode;
+mod encode;
+
+
+#[derive(Debug)]
+pub enum Error {
+    FileIO(io::Error),
+    WrongMagic,
+    HeaderEOF(usize),
+    DataEOF(usize, usize),
+    UnsupportedFormat(Format),
+    NotFlat,
+    PngDecode(png::DecodingError),
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Format {
+    Bc1,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc5,
+    Bc7,
+    Rgba8,
+    Bgra8,
+    Other(String),
+}
+
+
+pub struct Header {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub mip_count: usize,
+    pub format: Format,
+    pub is_cube: bool,
+    pub array_size: usize,
+    pub is_dx10: bool,
+    // amount of bytes after the header(s)
+    pub data_len: usize,
+}
+
+
+pub struct Dds {
+    pub header: Header,
+    data: Vec<u8>,
+}
+
+
+// decoded image, 4 bytes per pixel
+pub struct RgbaImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+
+const DDS_MAGIC:       &[u8; 4] = b"DDS ";
+const HEADER_LEN:      usize = 4 + 124;
+const HEADER_DX10_LEN: usize = 20;
+
+const DDSD_DEPTH:       u32 = 0x80_0000;
+
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC:      u32 = 0x4;
+const DDPF_RGB:         u32 = 0x40;
+
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+
+const DX10_MISC_TEXTURECUBE: u32 = 0x4;
+
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset .. offset + 4].try_into().unwrap())
+}
+
+
+// mip count of a complete chain, down to 1x1
+fn full_mip_count(width: usize, height: usize) -> usize {
+    let max_dim = width.max(height).max(1);
+    (usize::BITS - max_dim.leading_zeros()) as usize
+}
+
+
+impl Header {
+    // Reads only the header, data is checked against the file length
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Header, Error> {
+        let file = fs::File::open(path).map_err(Error::FileIO)?;
+        let file_len = file.metadata().map_err(Error::FileIO)?.len() as usize;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + HEADER_DX10_LEN);
+        file.take((HEADER_LEN + HEADER_DX10_LEN) as u64).read_to_end(&mut bytes).map_err(Error::FileIO)?;
+
+        let (mut header, header_len) = Self::from_bytes(&bytes)?;
+        header.data_len = file_len - header_len;
+        Ok(header)
+    }
+
+    // Returns the header and its length in bytes (including the DX10 extension)
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Header, usize), Error> {
+        if bytes.len() < HEADER_LEN {
+            return Err(Error::HeaderEOF(bytes.len()));
+        }
+
+        if &bytes[0 .. 4] != DDS_MAGIC {
+            return Err(Error::WrongMagic);
+        }
+
+        let flags     = read_u32(bytes, 8);
+        let height    = read_u32(bytes, 12) as usize;
+        let width     = read_u32(bytes, 16) as usize;
+        let depth     = if flags & DDSD_DEPTH != 0 { read_u32(bytes, 24) as usize } else { 1 };
+        let mip_count = read_u32(bytes, 28) as usize;
+
+        let pf_flags  = read_u32(bytes, 80);
+        let fourcc    = &bytes[84 .. 88];
+        let rgb_bits  = read_u32(bytes, 88);
+        let r_mask    = read_u32(bytes, 92);
+        let caps2     = read_u32(bytes, 112);
+
+        let mut header_len = HEADER_LEN;
+        let mut is_dx10 = false;
+        let mut is_cube = caps2 & DDSCAPS2_CUBEMAP != 0;
+        let mut array_size = 1;
+
+        let format = if pf_flags & DDPF_FOURCC != 0 {
+            match fourcc {
+                b"DXT1"           => Format::Bc1,
+                b"DXT2" | b"DXT3" => Format::Bc2,
+                b"DXT4" | b"DXT5" => Format::Bc3,
+                b"ATI1" | b"BC4U" => Format::Bc4,
+                b"ATI2" | b"BC5U" => Format::Bc5,
+                b"DX10" => {
+                    if bytes.len() < HEADER_LEN + HEADER_DX10_LEN {
+                        return Err(Error::HeaderEOF(bytes.len()));
+                    }
+
+                    let dxgi_format = read_u32(bytes, HEADER_LEN);
+                    let misc_flag   = read_u32(bytes, HEADER_LEN + 8);
+
+                    header_len += HEADER_DX10_LEN;
+                    is_dx10 = true;
+                    is_cube |= misc_flag & DX10_MISC_TEXTURECUBE != 0;
+                    array_size = read_u32(bytes, HEADER_LEN + 12) as usize;
+
+                    Format::from_dxgi(dxgi_format)
+                },
+                _ => Format::Other(format!("FourCC '{}'", String::from_utf8_lossy(fourcc)))
+            }
+        } else if pf_flags & DDPF_RGB != 0 && pf_flags & DDPF_ALPHAPIXELS != 0 && rgb_bits == 32 && r_mask == 0x0000_00ff {
+            Format::Rgba8
+        } else if pf_flags & DDPF_RGB != 0 && pf_flags & DDPF_ALPHAPIXELS != 0 && rgb_bits == 32 && r_mask == 0x00ff_0000 {
+            Format::Bgra8
+        } else {
+            Format::Other(format!("pixel format flags {:#x}, {} bits per pixel, red mask {:#010x}", pf_flags, rgb_bits, r_mask))
+        };
+
+        let header = Header {
+            width,
+            height,
+            depth: depth.max(1),
+            mip_count: mip_count.max(1),
+            format,
+            is_cube,
+            array_size: array_size.max(1),
+            is_dx10,
+            data_len: bytes.len() - header_len,
+        };
+
+        Ok((header, header_len))
+    }
+
+    pub fn full_mip_count(&self) -> usize {
+        full_mip_count(self.width, self.height)
+    }
+
+    fn top_mip_len(&self) -> usize {
+        self.format.mip_len(self.width, self.height).unwrap_or(0)
+    }
+
+    // None for unknown formats
+    pub fn expected_data_len(&self) -> Option<usize> {
+        let mut face_len = 0;
+        for i in 0 .. self.mip_count {
+            let w = (self.width >> i).max(1);
+            let h = (self.height >> i).max(1);
+            let d = (self.depth >> i).max(1);
+            face_len += self.format.mip_len(w, h)? * d;
+        }
+
+        let faces = if self.is_cube { 6 } else { 1 };
+        Some(face_len * faces * self.array_size)
+    }
+
+    // need_mips: texture is referenced with $TEXTURE (not $TEXTURE_NOMIP).
+    // The game still loads textures with a non-power-of-two size or missing mips, these are warnings.
+    pub fn validate(&self, need_mips: bool) -> Vec<(Severity, String)> {
+        let mut issues = Vec::with_capacity(0);
+
+        if !self.format.is_supported() {
+            issues.push((Severity::Error, format!("unsupported format: {}", self.format)));
+        }
+
+        if !self.width.is_power_of_two() || !self.height.is_power_of_two() {
+            issues.push((Severity::Warning, format!("size {}x{} is not a power of two", self.width, self.height)));
+        }
+
+        if need_mips && self.mip_count < self.full_mip_count() {
+            issues.push((Severity::Warning, format!("has {} of {} mips, but is used with $TEXTURE (generate mips, or use $TEXTURE_NOMIP)",
+                                                    self.mip_count, self.full_mip_count())));
+        }
+
+        if let Some(need) = self.expected_data_len() {
+            if self.data_len < need {
+                issues.push((Severity::Error, format!("file is truncated: image data needs {} bytes, has {}", need, self.data_len)));
+            }
+        }
+
+        issues
+    }
+}
+
+
+impl Dds {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Dds, Error> {
+        let bytes = fs::read(path).map_err(Error::FileIO)?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Dds, Error> {
+        let (header, header_len) = Header::from_bytes(bytes)?;
+
+        if !header.format.is_supported() {
+            return Err(Error::UnsupportedFormat(header.format));
+        }
+
+        let need = header.top_mip_len();
+        if header.data_len < need {
+            return Err(Error::DataEOF(need, header.data_len));
+        }
+
+        Ok(Dds { header, data: bytes[header_len ..].to_vec() })
+    }
+
+    // Encodes the image (and its mips, down to 1x1) into a supported format.
+    pub fn from_rgba(image: &RgbaImage, format: Format, with_mips: bool) -> Dds {
+        let mut data = Vec::with_capacity(format.mip_len(image.width, image.height).unwrap() * 2);
+        let mut mip_count = 1;
+        encode_mip(image, &format, &mut data);
+
+        if with_mips {
+            let mut mip = image.half();
+            while mip_count < full_mip_count(image.width, image.height) {
+                encode_mip(&mip, &format, &mut data);
+                mip_count += 1;
+                mip = mip.half();
+            }
+        }
+
+        let header = Header {
+            width: image.width,
+            height: image.height,
+            depth: 1,
+            mip_count,
+            format,
+            is_cube: false,
+            array_size: 1,
+            is_dx10: false,
+            data_len: data.len(),
+        };
+
+        Dds { header, data }
+    }
+
+    // decodes the top-level mip
+    pub fn decode_rgba(&self) -> RgbaImage {
+        let Header { width, height, .. } = self.header;
+        let data = &self.data[.. self.header.top_mip_len()];
+        let pixels = match self.header.format {
+            Format::Bc1   => decode::decode_blocks(data, width, height, 8,  decode::bc1_block),
+            Format::Bc3   => decode::decode_blocks(data, width, height, 16, decode::bc3_block),
+            Format::Bc5   => decode::decode_blocks(data, width, height, 16, decode::bc5_block),
+            Format::Rgba8 => data.to_vec(),
+            Format::Bgra8 => data.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0], p[3]]).collect(),
+            _ => unreachable!("Dds is created only for supported formats")
+        };
+
+        RgbaImage { width, height, pixels }
+    }
+
+    // Halves the texture until both sides fit into max_size. Returns None if it already fits.
+    // When the full mip chain is present, the top mips are just dropped, otherwise the image is re-encoded.
+    pub fn shrink_to(&self, max_size: usize) -> Result<Option<Dds>, Error> {
+        let h = &self.header;
+        let mut steps = 0;
+        while (h.width.max(h.height) >> steps) > max_size.max(1) {
+            steps += 1;
+        }
+
+        if steps == 0 {
+            return Ok(None);
+        }
+
+        if h.is_cube || h.array_size > 1 || h.depth > 1 {
+            return Err(Error::NotFlat);
+        }
+
+        let has_data = h.expected_data_len().map(|need| self.data.len() >= need).unwrap_or(false);
+        if steps < h.mip_count && has_data {
+            let skip = (0 .. steps).map(|i| h.format.mip_len((h.width >> i).max(1), (h.height >> i).max(1)).unwrap()).sum::<usize>();
+            let mut header = Header {
+                width: (h.width >> steps).max(1),
+                height: (h.height >> steps).max(1),
+                mip_count: h.mip_count - steps,
+                format: h.format.clone(),
+                data_len: 0,
+                .. *h
+            };
+            let len = header.expected_data_len().unwrap();
+            header.data_len = len;
+
+            Ok(Some(Dds { header, data: self.data[skip .. skip + len].to_vec() }))
+        } else {
+            let mut image = self.decode_rgba();
+            for _ in 0 .. steps {
+                image = image.half();
+            }
+
+            Ok(Some(Dds::from_rgba(&image, h.format.clone(), h.mip_count > 1)))
+        }
+    }
+
+    // Always writes the legacy header (no DX10 extension)
+    pub fn write_to<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        const DDSD_REQUIRED:    u32 = 0x1 | 0x2 | 0x4 | 0x1000;
+        const DDSD_PITCH:       u32 = 0x8;
+        const DDSD_MIPMAPCOUNT: u32 = 0x2_0000;
+        const DDSD_LINEARSIZE:  u32 = 0x8_0000;
+        const DDSCAPS_COMPLEX:  u32 = 0x8;
+        const DDSCAPS_TEXTURE:  u32 = 0x1000;
+        const DDSCAPS_MIPMAP:   u32 = 0x40_0000;
+
+        let h = &self.header;
+        let has_mips = h.mip_count > 1;
+
+        let mut bytes = [0u8; HEADER_LEN];
+        let mut put = |offset: usize, v: u32| bytes[offset .. offset + 4].copy_from_slice(&v.to_le_bytes());
+
+        let (size_flag, pitch) = match h.format {
+            Format::Rgba8 | Format::Bgra8 => (DDSD_PITCH, h.width * 4),
+            _                             => (DDSD_LINEARSIZE, h.top_mip_len()),
+        };
+
+        put(4, 124);
+        put(8, DDSD_REQUIRED | size_flag | if has_mips { DDSD_MIPMAPCOUNT } else { 0 });
+        put(12, h.height as u32);
+        put(16, h.width as u32);
+        put(20, pitch as u32);
+        put(28, if has_mips { h.mip_count as u32 } else { 0 });
+
+        put(76, 32);
+        let fourcc = match h.format {
+            Format::Bc1 => Some(b"DXT1"),
+            Format::Bc3 => Some(b"DXT5"),
+            Format::Bc5 => Some(b"ATI2"),
+            _ => None
+        };
+
+        match fourcc {
+            Some(fourcc) => {
+                put(80, DDPF_FOURCC);
+                put(84, u32::from_le_bytes(*fourcc));
+            },
+            None => {
+                let (r, b) = if h.format == Format::Rgba8 { (0x0000_00ff, 0x00ff_0000) } else { (0x00ff_0000, 0x0000_00ff) };
+                put(80, DDPF_RGB | DDPF_ALPHAPIXELS);
+                put(88, 32);
+                put(92, r);
+                put(96, 0x0000_ff00);
+                put(100, b);
+                put(104, 0xff00_0000);
+            }
+        }
+
+        put(108, DDSCAPS_TEXTURE | if has_mips { DDSCAPS_COMPLEX | DDSCAPS_MIPMAP } else { 0 });
+        bytes[0 .. 4].copy_from_slice(DDS_MAGIC);
+
+        writer.write_all(&bytes)?;
+        writer.write_all(&self.data)
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(fs::File::create(path)?);
+        self.write_to(&mut writer)?;
+        io::Write::flush(&mut writer)
+    }
+}
+
+
+fn encode_mip(image: &RgbaImage, format: &Format, data: &mut Vec<u8>) {
+    let RgbaImage { width, height, ref pixels } = *image;
+    match format {
+        Format::Bc1   => data.append(&mut encode::encode_blocks(pixels, width, height, 8,  encode::bc1_block)),
+        Format::Bc3   => data.append(&mut encode::encode_blocks(pixels, width, height, 16, encode::bc3_block)),
+        Format::Bc5   => data.append(&mut encode::encode_blocks(pixels, width, height, 16, encode::bc5_block)),
+        Format::Rgba8 => data.extend_from_slice(pixels),
+        Format::Bgra8 => data.extend(pixels.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0], p[3]])),
+        _ => panic!("Cannot encode DDS as {}", format)
+    }
+}
+
+
+impl RgbaImage {
+    pub fn from_png<P: AsRef<Path>>(path: P) -> Result<RgbaImage, Error> {
+        let file = fs::File::open(path).map_err(Error::FileIO)?;
+        // default transformations expand palettes and strip 16-bit channels
+        let (info, mut reader) = png::Decoder::new(file).read_info().map_err(Error::PngDecode)?;
+        let mut buf = vec![0u8; info.buffer_size()];
+        reader.next_frame(&mut buf).map_err(Error::PngDecode)?;
+
+        let (width, height) = (info.width as usize, info.height as usize);
+        let pixels = match info.color_type {
+            png::ColorType::RGBA           => buf,
+            png::ColorType::RGB            => buf.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+            png::ColorType::Grayscale      => buf.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+            png::ColorType::Indexed        => unreachable!("png decoder expands indexed colors"),
+        };
+
+        Ok(RgbaImage { width, height, pixels })
+    }
+
+    pub fn write_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = io::BufWriter::new(fs::File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let to_io = |e: png::EncodingError| io::Error::other(e);
+        let mut writer = encoder.write_header().map_err(to_io)?;
+        writer.write_image_data(&self.pixels).map_err(to_io)
+    }
+
+    // 2x2 box filter (next mip level)
+    pub fn half(&self) -> RgbaImage {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut pixels = vec![0u8; width * height * 4];
+
+        for y in 0 .. height {
+            for x in 0 .. width {
+                let mut acc = [0u32; 4];
+                for (sx, sy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let src_x = (x * 2 + sx).min(self.width - 1);
+                    let src_y = (y * 2 + sy).min(self.height - 1);
+                    let s = (src_y * self.width + src_x) * 4;
+                    for (a, v) in acc.iter_mut().zip(&self.pixels[s .. s + 4]) {
+                        *a += *v as u32;
+                    }
+                }
+
+                let dst = (y * width + x) * 4;
+                for (p, a) in pixels[dst .. dst + 4].iter_mut().zip(acc.iter()) {
+                    *p = ((a + 2) / 4) as u8;
+                }
+            }
+        }
+
+        RgbaImage { width, height, pixels }
+    }
+}
+
+
+impl Format {
+    fn from_dxgi(dxgi_format: u32) -> Format {
+        match dxgi_format {
+            28 | 29   => Format::Rgba8,
+            70 ..= 72 => Format::Bc1,
+            73 ..= 75 => Format::Bc2,
+            76 ..= 78 => Format::Bc3,
+            79 ..= 81 => Format::Bc4,
+            82 ..= 84 => Format::Bc5,
+            87 | 91   => Format::Bgra8,
+            97 ..= 99 => Format::Bc7,
+            x         => Format::Other(format!("DXGI format {}", x))
+        }
+    }
+
+    // formats used by the game (and decoded by this tool)
+    pub fn is_supported(&self) -> bool {
+        matches!(self, Format::Bc1 | Format::Bc3 | Format::Bc5 | Format::Rgba8 | Format::Bgra8)
+    }
+
+    pub fn mip_len(&self, width: usize, height: usize) -> Option<usize> {
+        let blocks = |x: usize| x.div_ceil(4).max(1);
+        match self {
+            Format::Bc1 | Format::Bc4     => Some(blocks(width) * blocks(height) * 8),
+            Format::Bc2 | Format::Bc3 |
+            Format::Bc5 | Format::Bc7     => Some(blocks(width) * blocks(height) * 16),
+            Format::Rgba8 | Format::Bgra8 => Some(width * height * 4),
+            Format::Other(_)              => None,
+        }
+    }
+}
+
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Error::FileIO(e)              => write!(f, "Could not read file: {}", e),
+            Error::WrongMagic             => write!(f, "Not a DDS file"),
+            Error::HeaderEOF(len)         => write!(f, "File is too short for a DDS header ({} bytes)", len),
+            Error::DataEOF(need, have)    => write!(f, "Image data is truncated: need {} bytes, have {}", need, have),
+            Error::UnsupportedFormat(fmt) => write!(f, "Unsupported format: {}", fmt),
+            Error::NotFlat                => write!(f, "Cube maps, arrays and volume textures are not supported"),
+            Error::PngDecode(e)           => write!(f, "Could not decode png: {}", e),
+        }
+    }
+}
+
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let s = match self {
+            Format::Bc1      => "BC1 (DXT1)",
+            Format::Bc2      => "BC2 (DXT3)",
+            Format::Bc3      => "BC3 (DXT5)",
+            Format::Bc4      => "BC4 (ATI1)",
+            Format::Bc5      => "BC5 (ATI2)",
+            Format::Bc7      => "BC7",
+            Format::Rgba8    => "R8G8B8A8",
+            Format::Bgra8    => "B8G8R8A8",
+            Format::Other(s) => s,
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+
+        writeln!(f, "Size:       {} x {}", self.width, self.height)?;
+        if self.depth > 1 {
+            writeln!(f, "Depth:      {}", self.depth)?;
+        }
+        writeln!(f, "Format:     {}", self.format)?;
+        writeln!(f, "DX10:       {}", yes_no(self.is_dx10))?;
+        writeln!(f, "Mips:       {} (full chain: {})", self.mip_count, self.full_mip_count())?;
+        writeln!(f, "Cube:       {}", yes_no(self.is_cube))?;
+        writeln!(f, "Array size: {}", self.array_size)?;
+        match self.expected_data_len() {
+            Some(need) => write!(f, "Data:       {} bytes (expected {})", self.data_len, need),
+            None       => write!(f, "Data:       {} bytes", self.data_len),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+
+
+// Encoding of a game text file (*.ini, *.mtl). Files are decoded for parsing
+// and written back in the encoding they were read in.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TextEncoding {
+    Utf8,
+    Utf8Bom,
+    Cp1250,
+    Cp1251,
+    Cp1252,
+}
+
+
+impl TextEncoding {
+    const BOM: &'static [u8] = b"\xEF\xBB\xBF";
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "utf-8"     => Some(Self::Utf8),
+            "utf-8-bom" => Some(Self::Utf8Bom),
+            "cp1250"    => Some(Self::Cp1250),
+            "cp1251"    => Some(Self::Cp1251),
+            "cp1252"    => Some(Self::Cp1252),
+            _           => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Utf8    => "utf-8",
+            Self::Utf8Bom => "utf-8-bom",
+            Self::Cp1250  => "cp1250",
+            Self::Cp1251  => "cp1251",
+            Self::Cp1252  => "cp1252",
+        }
+    }
+
+    fn codepage(&self) -> &'static encoding_rs::Encoding {
+        match self {
+            Self::Utf8 | Self::Utf8Bom => encoding_rs::UTF_8,
+            Self::Cp1250 => encoding_rs::WINDOWS_1250,
+            Self::Cp1251 => encoding_rs::WINDOWS_1251,
+            Self::Cp1252 => encoding_rs::WINDOWS_1252,
+        }
+    }
+
+    // Text in this encoding. Fails if the text has characters the codepage does not have.
+    pub fn encode<'a>(&self, text: &'a str) -> io::Result<Cow<'a, [u8]>> {
+        match self {
+            Self::Utf8 => Ok(Cow::Borrowed(text.as_bytes())),
+            Self::Utf8Bom => {
+                let mut bytes = Vec::with_capacity(Self::BOM.len() + text.len());
+                bytes.extend_from_slice(Self::BOM);
+                bytes.extend_from_slice(text.as_bytes());
+                Ok(Cow::Owned(bytes))
+            },
+            cp => {
+                let (bytes, _, unmappable) = cp.codepage().encode(text);
+                if unmappable {
+                    Err(io::Error::new(io::ErrorKind::InvalidData, format!("the text has characters which cannot be written in {}", cp)))
+                } else {
+                    Ok(bytes)
+                }
+            }
+        }
+    }
+}
+
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+
+// Decodes the file contents into buf. UTF-8 (with or without BOM) is recognized by itself.
+// Other files are read as `legacy`, or, when it is None, as cp1250/cp1251, whichever looks right.
+// Some(Utf8) rejects files which are not UTF-8.
+pub fn decode(bytes: &[u8], legacy: Option<TextEncoding>, buf: &mut String) -> io::Result<TextEncoding> {
+    buf.clear();
+
+    if let Some(text) = bytes.strip_prefix(TextEncoding::BOM) {
+        let text = std::str::from_utf8(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        buf.push_str(text);
+        return Ok(TextEncoding::Utf8Bom);
+    }
+
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        buf.push_str(text);
+        return Ok(TextEncoding::Utf8);
+    }
+
+    let enc = match legacy {
+        Some(TextEncoding::Utf8) | Some(TextEncoding::Utf8Bom) => {
+            let e = std::str::from_utf8(bytes).unwrap_err();
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} (use --encoding to read files in other encodings)", e)));
+        },
+        Some(enc) => enc,
+        None => detect_codepage(bytes)
+    };
+
+    let (text, _) = enc.codepage().decode_without_bom_handling(bytes);
+    buf.push_str(&text);
+    Ok(enc)
+}
+
+
+// Cyrillic words are made of non-ASCII bytes only, while in Central European text
+// the non-ASCII letters are mixed with ASCII ones.
+fn detect_codepage(bytes: &[u8]) -> TextEncoding {
+    let (mut cyrillic, mut latin) = (0, 0);
+    for w in bytes.windows(2) {
+        match (w[0] >= 0x80, w[1] >= 0x80) {
+            (true, true) => cyrillic += 1,
+            (true, false) if w[1].is_ascii_alphabetic() => latin += 1,
+            (false, true) if w[0].is_ascii_alphabetic() => latin += 1,
+            _ => { }
+        }
+    }
+
+    if cyrillic > latin {
+        TextEncoding::Cp1251
+    } else {
+        TextEncoding::Cp1250
+    }
+}
@@ -0,0 +1,317 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::read_to_string_buf;
+use crate::ini::common::Point3f;
+
+
+// Gizmo naming scheme (object names in the *.obj file, node names in the *.gltf / *.glb file):
+//
+//   <KEYWORD>[.<NNN>]
+//
+// where KEYWORD is the full building.ini token name without '$', including its tag
+// (CONNECTION_ROAD, VEHICLE_STATION, AIRPLANE_STATION_40M, CONNECTIONS_SPACE, ...),
+// and NNN is the zero-based occurrence of this token in the building.ini
+// (no suffix = first occurrence). This matches how Blender names duplicated objects.
+//
+// Point tokens take the object's vertices in order (1 vertex for a marker, 2 for a segment).
+// Rect tokens take the XZ bounding box of all the object's vertices.
+//
+// In glTF, a node with a mesh gives the vertices of its POSITION attributes (in world coordinates),
+// a node without a mesh and children (an empty) gives its world position as the only vertex.
+
+
+pub struct Gizmo {
+    pub name: String,
+    pub keyword: String,
+    pub index: usize,
+    pub points: Vec<Point3f>,
+}
+
+
+pub fn read_gizmos(path: &Path, buf: &mut String) -> Result<Vec<Gizmo>, String> {
+    match path.extension().and_then(|x| x.to_str()).map(|x| x.to_lowercase()).as_deref() {
+        Some("obj") => {
+            read_to_string_buf(path, buf).map_err(|e| format!("Cannot read gizmos file: {}", e))?;
+            parse_obj(buf)
+        },
+        Some("gltf") | Some("glb") => {
+            let bytes = fs::read(path).map_err(|e| format!("Cannot read gizmos file: {}", e))?;
+            parse_gltf(&bytes, path.parent().unwrap_or_else(|| Path::new("")))
+        },
+        _ => Err(format!("Unsupported gizmos file format: {}. Wavefront *.obj and glTF (*.gltf, *.glb) are supported", path.display()))
+    }
+}
+
+
+fn parse_obj(src: &str) -> Result<Vec<Gizmo>, String> {
+    let mut result: Vec<Gizmo> = Vec::with_capacity(32);
+
+    for (i, line) in src.lines().enumerate() {
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("o") => {
+                let name = parts.next().ok_or_else(|| format!("Line {}: object without a name", i + 1))?;
+                let (keyword, index) = split_name(name);
+                result.push(Gizmo { name: name.to_string(), keyword: keyword.to_string(), index, points: Vec::with_capacity(2) });
+            },
+            Some("v") => {
+                let mut coord = || -> Result<f32, String> {
+                    let s = parts.next().ok_or_else(|| format!("Line {}: not enough vertex coordinates", i + 1))?;
+                    f32::from_str(s).map_err(|e| format!("Line {}: cannot parse vertex coordinate '{}': {}", i + 1, s, e))
+                };
+
+                let (x, y, z) = (coord()?, coord()?, coord()?);
+                let gizmo = result.last_mut().ok_or_else(|| format!("Line {}: vertex does not belong to any object", i + 1))?;
+                gizmo.points.push(Point3f { x, y, z });
+            },
+            _ => { }
+        }
+    }
+
+    Ok(result)
+}
+
+
+//------------------------------------------------------------------
+// glTF 2.0
+
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+const GL_FLOAT: u64 = 5126;
+
+
+// column-major 4x4, as in glTF
+type Matrix = [f64; 16];
+
+const IDENTITY: Matrix = [1., 0., 0., 0.,  0., 1., 0., 0.,  0., 0., 1., 0.,  0., 0., 0., 1.];
+
+
+// bytes: *.gltf (JSON) or *.glb (binary container); root: directory of external buffers
+fn parse_gltf(bytes: &[u8], root: &Path) -> Result<Vec<Gizmo>, String> {
+    let (json, bin) = if bytes.starts_with(GLB_MAGIC) { split_glb(bytes)? } else { (bytes, None) };
+    let doc: Value = serde_json::from_slice(json).map_err(|e| format!("Invalid glTF JSON: {}", e))?;
+
+    let buffers = doc["buffers"].as_array().map(Vec::as_slice).unwrap_or(&[]).iter().enumerate().map(|(i, b)| {
+        match b["uri"].as_str() {
+            Some(uri) => read_buffer_uri(uri, root).map_err(|e| format!("Buffer #{}: {}", i, e)),
+            None => bin.map(<[u8]>::to_vec).ok_or_else(|| format!("Buffer #{} has no uri and there is no binary chunk", i))
+        }
+    }).collect::<Result<Vec<_>, _>>()?;
+
+    let nodes = doc["nodes"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+    let children = |n: &Value| -> Vec<usize> {
+        n["children"].as_array().map(|xs| xs.iter().filter_map(Value::as_u64).map(|x| x as usize).collect()).unwrap_or_default()
+    };
+
+    // nodes of the default scene, or the nodes which are not children of others
+    let scene = doc["scenes"].get(doc["scene"].as_u64().unwrap_or(0) as usize);
+    let mut backlog: Vec<(usize, Matrix)> = match scene.and_then(|s| s["nodes"].as_array()) {
+        Some(roots) => roots.iter().filter_map(Value::as_u64).map(|i| (i as usize, IDENTITY)).collect(),
+        None => (0 .. nodes.len()).filter(|i| nodes.iter().all(|n| !children(n).contains(i))).map(|i| (i, IDENTITY)).collect()
+    };
+    backlog.reverse();
+
+    let mut result = Vec::with_capacity(nodes.len());
+    let mut visited = vec![false; nodes.len()];
+    while let Some((idx, parent)) = backlog.pop() {
+        let node = nodes.get(idx).ok_or_else(|| format!("Node #{} does not exist", idx))?;
+        if std::mem::replace(&mut visited[idx], true) {
+            return Err(format!("Node #{} is referenced more than once", idx));
+        }
+
+        let world = mul(&parent, &local_matrix(node)?);
+        let kids = children(node);
+        for c in kids.iter().rev() {
+            backlog.push((*c, world));
+        }
+
+        let name = match node["name"].as_str() {
+            Some(name) if node.get("camera").is_none() => name,
+            _ => continue
+        };
+
+        let points = match node["mesh"].as_u64() {
+            Some(mesh) => {
+                let mesh = &doc["meshes"][mesh as usize];
+                let mut points = Vec::with_capacity(2);
+                for prim in mesh["primitives"].as_array().map(Vec::as_slice).unwrap_or(&[]) {
+                    let acc = prim["attributes"]["POSITION"].as_u64().ok_or_else(|| format!("Node '{}': mesh primitive without POSITION", name))?;
+                    let positions = read_vec3(&doc, &buffers, acc as usize).map_err(|e| format!("Node '{}': {}", name, e))?;
+                    points.extend(positions.into_iter().map(|p| transform(&world, p)));
+                }
+                points
+            },
+            // grouping nodes are not gizmos
+            None if !kids.is_empty() => continue,
+            None => vec![transform(&world, [0., 0., 0.])],
+        };
+
+        let (keyword, index) = split_name(name);
+        result.push(Gizmo { name: name.to_string(), keyword: keyword.to_string(), index, points });
+    }
+
+    Ok(result)
+}
+
+
+// (JSON chunk, BIN chunk)
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), String> {
+    let u32_at = |pos: usize| -> Result<u32, String> {
+        bytes.get(pos .. pos + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).ok_or_else(|| "GLB file is truncated".to_string())
+    };
+
+    let mut json = None;
+    let mut bin = None;
+    let mut pos = 12;
+    while pos < bytes.len() {
+        let len = u32_at(pos)? as usize;
+        let kind = u32_at(pos + 4)?;
+        let data = bytes.get(pos + 8 .. pos + 8 + len).ok_or_else(|| "GLB file is truncated".to_string())?;
+        match kind {
+            GLB_CHUNK_JSON => json = json.or(Some(data)),
+            GLB_CHUNK_BIN  => bin = bin.or(Some(data)),
+            _ => { }
+        }
+        pos += 8 + len;
+    }
+
+    Ok((json.ok_or_else(|| "GLB file has no JSON chunk".to_string())?, bin))
+}
+
+
+fn read_buffer_uri(uri: &str, root: &Path) -> Result<Vec<u8>, String> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, b64) = data.split_once(";base64,").ok_or_else(|| "only base64 data uris are supported".to_string())?;
+        return decode_base64(b64);
+    }
+
+    let path = root.join(uri.replace("%20", " "));
+    fs::read(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))
+}
+
+
+fn decode_base64(src: &str) -> Result<Vec<u8>, String> {
+    let mut result = Vec::with_capacity(src.len() * 3 / 4);
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in src.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        let v = match c {
+            b'A' ..= b'Z' => c - b'A',
+            b'a' ..= b'z' => c - b'a' + 26,
+            b'0' ..= b'9' => c - b'0' + 52,
+            b'+' | b'-'   => 62,
+            b'/' | b'_'   => 63,
+            _ => return Err(format!("invalid base64 character '{}'", c as char))
+        };
+
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((acc >> bits) as u8);
+        }
+    }
+
+    Ok(result)
+}
+
+
+// Float VEC3 accessor (sparse accessors are not supported)
+fn read_vec3(doc: &Value, buffers: &[Vec<u8>], acc: usize) -> Result<Vec<[f64; 3]>, String> {
+    let acc = &doc["accessors"][acc];
+    if acc["componentType"].as_u64() != Some(GL_FLOAT) || acc["type"].as_str() != Some("VEC3") || acc.get("sparse").is_some() {
+        return Err("POSITION must be a float VEC3 accessor without sparse storage".to_string());
+    }
+
+    let count = acc["count"].as_u64().unwrap_or(0) as usize;
+    let view = match acc["bufferView"].as_u64() {
+        Some(v) => &doc["bufferViews"][v as usize],
+        // no buffer view: all zeros
+        None => return Ok(vec![[0.; 3]; count]),
+    };
+
+    let buffer = buffers.get(view["buffer"].as_u64().unwrap_or(0) as usize).ok_or_else(|| "buffer does not exist".to_string())?;
+    let start = view["byteOffset"].as_u64().unwrap_or(0) as usize + acc["byteOffset"].as_u64().unwrap_or(0) as usize;
+    let stride = view["byteStride"].as_u64().unwrap_or(12) as usize;
+
+    (0 .. count).map(|i| {
+        let at = start + i * stride;
+        let b = buffer.get(at .. at + 12).ok_or_else(|| "accessor is out of the buffer bounds".to_string())?;
+        let f = |k: usize| f32::from_le_bytes([b[k], b[k + 1], b[k + 2], b[k + 3]]) as f64;
+        Ok([f(0), f(4), f(8)])
+    }).collect()
+}
+
+
+// matrix, or translation * rotation * scale
+fn local_matrix(node: &Value) -> Result<Matrix, String> {
+    let floats = |key: &str, len: usize| -> Result<Option<Vec<f64>>, String> {
+        match node.get(key) {
+            None => Ok(None),
+            Some(Value::Array(xs)) if xs.len() == len && xs.iter().all(Value::is_number) => Ok(Some(xs.iter().filter_map(Value::as_f64).collect())),
+            Some(_) => Err(format!("Node '{}': \"{}\" must be an array of {} numbers", node["name"].as_str().unwrap_or("?"), key, len))
+        }
+    };
+
+    if let Some(m) = floats("matrix", 16)? {
+        let mut result = IDENTITY;
+        result.copy_from_slice(&m);
+        return Ok(result);
+    }
+
+    let t = floats("translation", 3)?.unwrap_or_else(|| vec![0., 0., 0.]);
+    let r = floats("rotation", 4)?.unwrap_or_else(|| vec![0., 0., 0., 1.]);
+    let s = floats("scale", 3)?.unwrap_or_else(|| vec![1., 1., 1.]);
+
+    let (x, y, z, w) = (r[0], r[1], r[2], r[3]);
+    let rot = [
+        1. - 2. * (y * y + z * z), 2. * (x * y + z * w),      2. * (x * z - y * w),      0.,
+        2. * (x * y - z * w),      1. - 2. * (x * x + z * z), 2. * (y * z + x * w),      0.,
+        2. * (x * z + y * w),      2. * (y * z - x * w),      1. - 2. * (x * x + y * y), 0.,
+        0.,                        0.,                        0.,                        1.,
+    ];
+
+    let mut result = rot;
+    for col in 0 .. 3 {
+        for row in 0 .. 3 {
+            result[col * 4 + row] *= s[col];
+        }
+    }
+    result[12] = t[0];
+    result[13] = t[1];
+    result[14] = t[2];
+    Ok(result)
+}
+
+
+fn mul(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut result = [0.; 16];
+    for col in 0 .. 4 {
+        for row in 0 .. 4 {
+            result[col * 4 + row] = (0 .. 4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    result
+}
+
+
+fn transform(m: &Matrix, p: [f64; 3]) -> Point3f {
+    let at = |row: usize| m[row] * p[0] + m[4 + row] * p[1] + m[8 + row] * p[2] + m[12 + row];
+    Point3f { x: at(0) as f32, y: at(1) as f32, z: at(2) as f32 }
+}
+
+
+fn split_name(name: &str) -> (&str, usize) {
+    if let Some(pos) = name.rfind('.') {
+        if let Ok(index) = usize::from_str(&name[pos + 1 ..]) {
+            return (&name[.. pos], index);
+        }
+    }
+
+    (name, 0)
+}
@@ -64,8 +64,10 @@ impl Token<'_> {
                                                                Self::CONNECTIONS_ROAD_DEAD_SQUARE,    r.x1, r.z1, r.x2, r.z2, prec = PREC),
             Self::ConnectionsAirportDeadSquare(r)    => write!(wr, "{}\r\n{:.prec$} {:.prec$}\r\n{:.prec$} {:.prec$}",
                                                                Self::CONNECTIONS_AIRPORT_DEAD_SQUARE, r.x1, r.z1, r.x2, r.z2, prec = PREC),
+            Self::ConnectionsPedestrianDeadSquare(r) => write!(wr, "{}\r\n{:.prec$} {:.prec$}\r\n{:.prec$} {:.prec$}",
+                                                               Self::CONNECTIONS_PEDESTRIAN_DEAD_SQUARE, r.x1, r.z1, r.x2, r.z2, prec = PREC),
             Self::ConnectionsWaterDeadSquare((x, r)) => write!(wr, "{}\r\n{:.prec$}\r\n{:.prec$} {:.prec$}\r\n{:.prec$} {:.prec$}",
-                                                               Self::CONNECTIONS_ROAD_DEAD_SQUARE, x, r.x1, r.z1, r.x2, r.z2, prec = PREC),
+                                                               Self::CONNECTIONS_WATER_DEAD_SQUARE, x, r.x1, r.z1, r.x2, r.z2, prec = PREC),
 
             Self::Particle((t, p, a, s))           => write!(wr, "{} {} {:.prec$} {:.prec$} {:.prec$} {:.prec$} {:.prec$}", 
                                                              Self::PARTICLE, t, p.x, p.y, p.z, a, s, prec = PREC),
@@ -78,8 +80,8 @@ impl Token<'_> {
                 write!(wr, "{} {}\nposition {:.prec$} {:.prec$} {:.prec$}\n\
                                   rotation {:.prec$}\n\
                                   scale {:.prec$} {:.prec$} {:.prec$}\n\
-                                  numstep_x {:.prec$} {}\n\
-                                  numstep_t {:.prec$} {}", 
+                                  numstepx {:.prec$} {}\n\
+                                  numstept {:.prec$} {}", 
                        Self::RESOURCE_VISUALIZATION, storage_id, p.x, p.y, p.z, rotation, s.x, s.y, s.z, x1, x2, z1, z2, prec = PREC),
             Self::ResourceIncreasePoint((i, p))        => write_x_pts!(Self::RESOURCE_INCREASE_POINT, i, p),
             Self::ResourceIncreaseConvPoint((i, a, b)) => write_x_pts!(Self::RESOURCE_INCREASE_CONV_POINT, i, a, b),
@@ -88,6 +90,8 @@ impl Token<'_> {
 
             Self::CostWorkVehicleStation((a, b))       => write_pts!(Self::COST_WORK_VEHICLE_STATION, a, b),
 
+            Self::Schema(t)                            => t.serialize(wr, true),
+
             t => write!(wr, "{}", t)
         }
 
@@ -106,6 +110,7 @@ impl Display for Token<'_> {
             Self::HeatDisable                      => write!(f, "{}",    Self::HEATING_DISABLE),
             Self::CivilBuilding                    => write!(f, "{}",    Self::CIVIL_BUILDING),
             Self::MonumentTrespass                 => write!(f, "{}",    Self::MONUMENT_TRESPASS),
+            Self::MonumentEleConsumptionAdd(x)     => write!(f, "{} {}", Self::MONUMENT_ELE_CONSUMPTION_ADD, x),
             Self::QualityOfLiving(x)               => write!(f, "{} {}", Self::QUALITY_OF_LIVING, x),
 
             Self::WorkersNeeded(x)                 => write!(f, "{} {}",    Self::WORKERS_NEEDED, x),
@@ -144,6 +149,7 @@ impl Display for Token<'_> {
             Self::StorageExportSpecial((t, x, r))  => write!(f, "{} {} {} {}", Self::STORAGE_EXPORT_SPECIAL, t, x, r),
             Self::StorageImportSpecial((t, x, r))  => write!(f, "{} {} {} {}", Self::STORAGE_IMPORT_SPECIAL, t, x, r),
             Self::StorageDemandBasic((t, x))       => write!(f, "{} {} {}",    Self::STORAGE_DEMAND_BASIC, t, x),
+            Self::StorageDemandMedium((t, x))      => write!(f, "{} {} {}",    Self::STORAGE_DEMAND_MEDIUM, t, x),
             Self::StorageDemandMediumAdvanced((t, x))
                                                    => write!(f, "{} {} {}",    Self::STORAGE_DEMAND_MEDIUMADVANCED, t, x),
             Self::StorageDemandAdvanced((t, x))    => write!(f, "{} {} {}",    Self::STORAGE_DEMAND_ADVANCED, t, x),
@@ -157,6 +163,7 @@ impl Display for Token<'_> {
 
             Self::RoadNotFlip                      => write!(f, "{}",       Self::ROAD_VEHICLE_NOT_FLIP),
             Self::RoadElectric                     => write!(f, "{}",       Self::ROAD_VEHICLE_ELECTRIC),
+            Self::RoadForkliftPass                 => write!(f, "{}",       Self::ROAD_VEHICLE_FORKLIFT_PASS),
             Self::VehicleCannotSelect              => write!(f, "{}",       Self::VEHICLE_CANNOT_SELECT),
             Self::LongTrains                       => write!(f, "{}",       Self::LONG_TRAINS),
 
@@ -187,6 +194,7 @@ impl Display for Token<'_> {
             Self::ConnectionsSpace(r)              => write!(f, "{} {}",      Self::CONNECTIONS_SPACE, r),
             Self::ConnectionsRoadDeadSquare(r)     => write!(f, "{} {}",      Self::CONNECTIONS_ROAD_DEAD_SQUARE, r),
             Self::ConnectionsAirportDeadSquare(r)  => write!(f, "{} {}",      Self::CONNECTIONS_AIRPORT_DEAD_SQUARE, r),
+            Self::ConnectionsPedestrianDeadSquare(r) => write!(f, "{} {}",    Self::CONNECTIONS_PEDESTRIAN_DEAD_SQUARE, r),
             Self::ConnectionsWaterDeadSquare((x, r)) => write!(f, "{} {} {}", Self::CONNECTIONS_WATER_DEAD_SQUARE, x, r),
 
             Self::AttractionType((t, x))           => write!(f, "{}{} {}", Self::ATTRACTION_TYPE, t, x),
@@ -223,9 +231,11 @@ impl Display for Token<'_> {
             Self::ResourceFillingConvPoint((a, b)) => write!(f, "{} {} {}",    Self::RESOURCE_FILLING_CONV_POINT, a, b),
 
             Self::WorkingSfx(s)                    => write!(f, "{} {}",       Self::WORKING_SFX, s),
+            Self::WorkingSfxDistance(x)            => write!(f, "{} {}",       Self::WORKING_SFX_DISTANCE, x),
             Self::AnimationFps(x)                  => write!(f, "{} {}",       Self::ANIMATION_FPS, x),
-            Self::AnimationMesh((s, t))            => write!(f, "{} \"{}\" \"{}\"", Self::ANIMATION_MESH, s, t),
-            Self::UndergroundMesh((s, t))          => write!(f, "{} \"{}\" \"{}\"", Self::UNDERGROUND_MESH, s, t),
+            Self::AnimationMesh((s, t))            => write!(f, "{} {} {}", Self::ANIMATION_MESH, s, t),
+            Self::AnimationMeshWorkshop((s, t))    => write!(f, "{} {} {}", Self::ANIMATION_MESH_WORKSHOP, s, t),
+            Self::UndergroundMesh((s, t))          => write!(f, "{} {} {}", Self::UNDERGROUND_MESH, s, t),
 
             Self::CostWork((t, x))                 => write!(f, "{} {} {}", Self::COST_WORK, t, x),
             Self::CostWorkBuildingNode(n)          => write!(f, "{} {}",    Self::COST_WORK_BUILDING_NODE, n),
@@ -236,6 +246,8 @@ impl Display for Token<'_> {
             Self::CostResourceAuto((t, x))         => write!(f, "{} {} {}", Self::COST_RESOURCE_AUTO, t, x),
             Self::CostWorkVehicleStation((a, b))   => write!(f, "{} {} {}", Self::COST_WORK_VEHICLE_STATION, a, b),
             Self::CostWorkVehicleStationNode(p)    => write!(f, "{} {}",    Self::COST_WORK_VEHICLE_STATION_NODE, p),
+
+            Self::Schema(t)                        => write!(f, "{}", t),
         }
     }
 }
@@ -340,6 +352,7 @@ impl Display for super::Connection2PType {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         let s = match self {
             Self::AirRoad           => Self::CONN_AIRROAD,
+            Self::Airplane          => Self::CONN_AIRPLANE,
             Self::Pedestrian        => Self::CONN_PED,
             Self::PedestrianNotPick => Self::CONN_PED_NOTPICK,
             Self::Road              => Self::CONN_ROAD,
@@ -347,6 +360,7 @@ impl Display for super::Connection2PType {
             Self::RoadBorder        => Self::CONN_ROAD_BORDER,
             Self::RoadIn            => Self::CONN_ROAD_IN,
             Self::RoadOut           => Self::CONN_ROAD_OUT,
+            Self::RoadHeight        => Self::CONN_ROAD_HEIGHT,
             Self::Rail              => Self::CONN_RAIL,
             Self::RailAllowpass     => Self::CONN_RAIL_ALLOWPASS,
             Self::RailBorder        => Self::CONN_RAIL_BORDER,
@@ -379,6 +393,7 @@ impl Display for super::Connection1PType {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         let s = match self {
             Self::RoadDead       => Self::ROAD_DEAD,
+            Self::RailDead       => Self::RAIL_DEAD,
             Self::PedestrianDead => Self::PEDESTRIAN_DEAD,
             Self::WaterDead      => Self::WATER_DEAD,
             Self::AirportDead    => Self::AIRPORT_DEAD,
@@ -0,0 +1,467 @@
+use std::slice::{self, Iter};
+
+use serde_json::{Value, Map};
+
+use super::{BuildingType,
+            BuildingSubtype,
+            StorageCargoType,
+            ParticleType,
+            ConstructionPhase,
+            ConstructionAutoCost,
+            ResourceType,
+            ResourceVisualization,
+            Token,
+
+            Connection2PType,
+            Connection1PType,
+            AirplaneStationType,
+            AttractionType,
+            ResourceSourceType,
+           };
+
+use crate::ini::common::{ParseError,
+                         Point3f,
+                         Rect,
+                         QuotedStringParam,
+                         IdStringParam,
+                         CostKeywordParam,
+                        };
+
+use crate::ini::json::{JsonParam, JsonLiteral, JsonToken};
+use crate::ini::schema;
+use crate::cfg::APP_SETTINGS;
+
+
+// Tagged tokens ($TYPE_FACTORY, $CONNECTION_ROAD, ...) keep the tag in the keyword, as in the file
+impl<'a> JsonToken<'a> for Token<'a> {
+    fn to_json(&self) -> (String, Vec<Value>) {
+        let mut params = Vec::new();
+        macro_rules! json {
+            ($kw:expr, $($p:expr),+) => {{
+                $( $p.write_json(&mut params); )+
+                $kw.to_string()
+            }};
+            ($kw:expr) => {
+                $kw.to_string()
+            };
+        }
+
+        let keyword = match self {
+            Self::BuildingType(t)                 => format!("{}{}", Self::BUILDING_TYPE, t),
+            Self::BuildingSubtype(t)              => format!("{}{}", Self::BUILDING_SUBTYPE, t),
+            Self::ResourceSource(t)               => format!("{}{}", Self::RESOURCE_SOURCE, t),
+            Self::AirplaneStation((t, a, b))      => json!(format!("{}{}", Self::AIRPLANE_STATION, t), a, b),
+            Self::AttractionType((t, x))          => json!(format!("{}{}", Self::ATTRACTION_TYPE, t), x),
+            Self::Connection2Points((t, a, b))    => json!(format!("{}{}", Self::CONNECTION, t), a, b),
+            Self::Connection1Point((t, p))        => json!(format!("{}{}", Self::CONNECTION, t), p),
+            Self::ConnectionRailDeadend           => format!("{}{}", Self::CONNECTION, Self::CONNECTION_RAIL_DEADEND),
+
+            Self::NameStr(p)                      => json!(Self::NAME_STR, p),
+            Self::Name(p)                         => json!(Self::NAME, p),
+
+            Self::HeatEnable                      => json!(Self::HEATING_ENABLE),
+            Self::HeatDisable                     => json!(Self::HEATING_DISABLE),
+            Self::CivilBuilding                   => json!(Self::CIVIL_BUILDING),
+            Self::MonumentTrespass                => json!(Self::MONUMENT_TRESPASS),
+            Self::MonumentEleConsumptionAdd(p)    => json!(Self::MONUMENT_ELE_CONSUMPTION_ADD, p),
+            Self::QualityOfLiving(p)              => json!(Self::QUALITY_OF_LIVING, p),
+
+            Self::WorkersNeeded(p)                => json!(Self::WORKERS_NEEDED, p),
+            Self::ProfessorsNeeded(p)             => json!(Self::PROFESSORS_NEEDED, p),
+            Self::CitizenAbleServe(p)             => json!(Self::CITIZEN_ABLE_SERVE, p),
+            Self::Consumption(p)                  => json!(Self::CONSUMPTION, p),
+            Self::ConsumptionPerSec(p)            => json!(Self::CONSUMPTION_PER_SEC, p),
+            Self::Production(p)                   => json!(Self::PRODUCTION, p),
+            Self::ProductionSun(p)                => json!(Self::PRODUCTION_SUN, p),
+            Self::ProductionWind(p)               => json!(Self::PRODUCTION_WIND, p),
+            Self::SeasonalTempMin(p)              => json!(Self::SEASONAL_TEMP_MIN, p),
+            Self::SeasonalTempMax(p)              => json!(Self::SEASONAL_TEMP_MAX, p),
+
+            Self::EleConsumWorkerFactorBase(p)    => json!(Self::ELE_CONSUM_WORKER_FACTOR_BASE, p),
+            Self::EleConsumWorkerFactorNight(p)   => json!(Self::ELE_CONSUM_WORKER_FACTOR_NIGHT, p),
+            Self::EleConsumServeFactorBase(p)     => json!(Self::ELE_CONSUM_SERVE_FACTOR_BASE, p),
+            Self::EleConsumServeFactorNight(p)    => json!(Self::ELE_CONSUM_SERVE_FACTOR_NIGHT, p),
+            Self::EleConsumCargoLoadFactor(p)     => json!(Self::ELE_CONSUM_CARGO_LOAD_FACTOR, p),
+            Self::EleConsumCargoUnloadFactor(p)   => json!(Self::ELE_CONSUM_CARGO_UNLOAD_FACTOR, p),
+
+            Self::NoEleWorkFactorBase(p)          => json!(Self::NO_ELE_WORK_FACTOR_BASE, p),
+            Self::NoEleWorkFactorNight(p)         => json!(Self::NO_ELE_WORK_FACTOR_NIGHT, p),
+            Self::NoHeatWorkFactor(p)             => json!(Self::NO_HEAT_WORK_FACTOR, p),
+
+            Self::EngineSpeed(p)                  => json!(Self::ENGINE_SPEED, p),
+            Self::CablewayHeavy                   => json!(Self::CABLEWAY_HEAVY),
+            Self::CablewayLight                   => json!(Self::CABLEWAY_LIGHT),
+
+            Self::Storage(p)                      => json!(Self::STORAGE, p),
+            Self::StorageSpecial(p)               => json!(Self::STORAGE_SPECIAL, p),
+            Self::StorageFuel(p)                  => json!(Self::STORAGE_FUEL, p),
+            Self::StorageExport(p)                => json!(Self::STORAGE_EXPORT, p),
+            Self::StorageImport(p)                => json!(Self::STORAGE_IMPORT, p),
+            Self::StorageImportCarplant(p)        => json!(Self::STORAGE_IMPORT_CARPLANT, p),
+            Self::StorageExportSpecial(p)         => json!(Self::STORAGE_EXPORT_SPECIAL, p),
+            Self::StorageImportSpecial(p)         => json!(Self::STORAGE_IMPORT_SPECIAL, p),
+            Self::StorageDemandBasic(p)           => json!(Self::STORAGE_DEMAND_BASIC, p),
+            Self::StorageDemandMedium(p)          => json!(Self::STORAGE_DEMAND_MEDIUM, p),
+            Self::StorageDemandMediumAdvanced(p)  => json!(Self::STORAGE_DEMAND_MEDIUMADVANCED, p),
+            Self::StorageDemandAdvanced(p)        => json!(Self::STORAGE_DEMAND_ADVANCED, p),
+            Self::StorageDemandHotel(p)           => json!(Self::STORAGE_DEMAND_HOTEL, p),
+            Self::StoragePackFrom(p)              => json!(Self::STORAGE_PACK_FROM, p),
+            Self::StorageUnpackTo(p)              => json!(Self::STORAGE_UNPACK_TO, p),
+            Self::StorageLivingAuto(p)            => json!(Self::STORAGE_LIVING_AUTO, p),
+
+            Self::VehicleLoadingFactor(p)         => json!(Self::VEHICLE_LOADING_FACTOR, p),
+            Self::VehicleUnloadingFactor(p)       => json!(Self::VEHICLE_UNLOADING_FACTOR, p),
+
+            Self::RoadNotFlip                     => json!(Self::ROAD_VEHICLE_NOT_FLIP),
+            Self::RoadElectric                    => json!(Self::ROAD_VEHICLE_ELECTRIC),
+            Self::RoadForkliftPass                => json!(Self::ROAD_VEHICLE_FORKLIFT_PASS),
+            Self::VehicleCannotSelect             => json!(Self::VEHICLE_CANNOT_SELECT),
+            Self::LongTrains                      => json!(Self::LONG_TRAINS),
+
+            Self::WorkingVehiclesNeeded(p)        => json!(Self::WORKING_VEHICLES_NEEDED, p),
+            Self::VehicleStation(p)               => json!(Self::VEHICLE_STATION, p),
+            Self::VehicleStationNotBlock          => json!(Self::VEHICLE_STATION_NOT_BLOCK),
+            Self::VehicleStationDetourPoint(p)    => json!(Self::VEHICLE_STATION_DETOUR_POINT, p),
+            Self::VehicleStationDetourPid(p)      => json!(Self::VEHICLE_STATION_DETOUR_PID, p),
+
+            Self::VehicleParking(p)               => json!(Self::VEHICLE_PARKING, p),
+            Self::VehicleParkingDetourPoint(p)    => json!(Self::VEHICLE_PARKING_DETOUR_POINT, p),
+            Self::VehicleParkingDetourPid(p)      => json!(Self::VEHICLE_PARKING_DETOUR_PID, p),
+            Self::VehicleParkingPersonal(p)       => json!(Self::VEHICLE_PARKING_PERSONAL, p),
+
+            Self::HeliportStation(p)              => json!(Self::HELIPORT_STATION, p),
+            Self::ShipStation(p)                  => json!(Self::SHIP_STATION, p),
+            Self::HeliportArea(p)                 => json!(Self::HELIPORT_AREA, p),
+            Self::HarborTerrainFrom(p)            => json!(Self::HARBOR_OVER_TERRAIN_FROM, p),
+            Self::HarborWaterFrom(p)              => json!(Self::HARBOR_OVER_WATER_FROM, p),
+            Self::HarborExtendWhenBuilding(p)     => json!(Self::HARBOR_EXTEND_WHEN_BULDING, p),
+
+            Self::ConnectionsSpace(p)             => json!(Self::CONNECTIONS_SPACE, p),
+            Self::ConnectionsRoadDeadSquare(p)    => json!(Self::CONNECTIONS_ROAD_DEAD_SQUARE, p),
+            Self::ConnectionsAirportDeadSquare(p) => json!(Self::CONNECTIONS_AIRPORT_DEAD_SQUARE, p),
+            Self::ConnectionsPedestrianDeadSquare(p)=> json!(Self::CONNECTIONS_PEDESTRIAN_DEAD_SQUARE, p),
+            Self::ConnectionsWaterDeadSquare(p)   => json!(Self::CONNECTIONS_WATER_DEAD_SQUARE, p),
+            Self::OffsetConnection(p)             => json!(Self::OFFSET_CONNECTION_XYZW, p),
+
+            Self::AttractionRememberUsage         => json!(Self::ATTRACTION_REMEMBER_USAGE),
+            Self::AttractiveScoreBase(p)          => json!(Self::ATTRACTIVE_SCORE_BASE, p),
+            Self::AttractiveScoreAlcohol(p)       => json!(Self::ATTRACTIVE_SCORE_ALCOHOL, p),
+            Self::AttractiveScoreCulture(p)       => json!(Self::ATTRACTIVE_SCORE_CULTURE, p),
+            Self::AttractiveScoreReligion(p)      => json!(Self::ATTRACTIVE_SCORE_RELIGION, p),
+            Self::AttractiveScoreSport(p)         => json!(Self::ATTRACTIVE_SCORE_SPORT, p),
+            Self::AttractiveFactorNature(p)       => json!(Self::ATTRACTIVE_FACTOR_NATURE, p),
+            Self::AttractiveFactorNatureAdd(p)    => json!(Self::ATTRACTIVE_FACTOR_NATURE_ADD, p),
+            Self::AttractiveFactorPollution(p)    => json!(Self::ATTRACTIVE_FACTOR_POLLUTION, p),
+            Self::AttractiveFactorPollutionAdd(p) => json!(Self::ATTRACTIVE_FACTOR_POLLUTION_ADD, p),
+            Self::AttractiveFactorSight(p)        => json!(Self::ATTRACTIVE_FACTOR_SIGHT, p),
+            Self::AttractiveFactorSightAdd(p)     => json!(Self::ATTRACTIVE_FACTOR_SIGHT_ADD, p),
+            Self::AttractiveFactorWater(p)        => json!(Self::ATTRACTIVE_FACTOR_WATER, p),
+            Self::AttractiveFactorWaterAdd(p)     => json!(Self::ATTRACTIVE_FACTOR_WATER_ADD, p),
+
+            Self::PollutionHigh                   => json!(Self::POLLUTION_HIGH),
+            Self::PollutionMedium                 => json!(Self::POLLUTION_MEDIUM),
+            Self::PollutionSmall                  => json!(Self::POLLUTION_SMALL),
+
+            Self::Particle(p)                     => json!(Self::PARTICLE, p),
+            Self::ParticleReactor(p)              => json!(Self::PARTICLE_REACTOR, p),
+            Self::ParticleSnowRemove(p)           => json!(Self::PARTICLE_SNOW_REMOVE, p),
+
+            Self::TextCaption(p)                  => json!(Self::TEXT_CAPTION, p),
+            Self::WorkerRenderingArea(p)          => json!(Self::WORKER_RENDERING_AREA, p),
+            Self::ResourceVisualization(p)        => json!(Self::RESOURCE_VISUALIZATION, p),
+            Self::ResourceIncreasePoint(p)        => json!(Self::RESOURCE_INCREASE_POINT, p),
+            Self::ResourceIncreaseConvPoint(p)    => json!(Self::RESOURCE_INCREASE_CONV_POINT, p),
+            Self::ResourceFillingPoint(p)         => json!(Self::RESOURCE_FILLING_POINT, p),
+            Self::ResourceFillingConvPoint(p)     => json!(Self::RESOURCE_FILLING_CONV_POINT, p),
+            Self::WorkingSfx(p)                   => json!(Self::WORKING_SFX, p),
+            Self::WorkingSfxDistance(p)           => json!(Self::WORKING_SFX_DISTANCE, p),
+            Self::AnimationFps(p)                 => json!(Self::ANIMATION_FPS, p),
+            Self::AnimationMesh(p)                => json!(Self::ANIMATION_MESH, p),
+            Self::AnimationMeshWorkshop(p)        => json!(Self::ANIMATION_MESH_WORKSHOP, p),
+            Self::UndergroundMesh(p)              => json!(Self::UNDERGROUND_MESH, p),
+
+            Self::CostWork(p)                     => json!(Self::COST_WORK, p),
+            Self::CostWorkBuildingNode(p)         => json!(Self::COST_WORK_BUILDING_NODE, p),
+            Self::CostWorkBuildingKeyword(p)      => json!(Self::COST_WORK_BUILDING_KEYWORD, p),
+            Self::CostWorkBuildingAll             => json!(Self::COST_WORK_BUILDING_ALL),
+
+            Self::CostResource(p)                 => json!(Self::COST_RESOURCE, p),
+            Self::CostResourceAuto(p)             => json!(Self::COST_RESOURCE_AUTO, p),
+
+            Self::CostWorkVehicleStation(p)       => json!(Self::COST_WORK_VEHICLE_STATION, p),
+            Self::CostWorkVehicleStationNode(p)   => json!(Self::COST_WORK_VEHICLE_STATION_NODE, p),
+
+            Self::Schema(t)                       => json!(t.keyword, t),
+        };
+
+        (keyword, params)
+    }
+
+
+    fn from_json(keyword: &'a str, params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        schema::from_json_declared(&APP_SETTINGS.schema.building, keyword, params, Self::builtin_from_json, Self::Schema)
+    }
+}
+
+
+impl<'a> Token<'a> {
+    fn builtin_from_json(keyword: &str, params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        macro_rules! read {
+            ($id:ident, $t:ty) => {
+                <$t>::read_json(params).map(Self::$id)
+            };
+            ($id:ident) => {
+                Ok(Self::$id)
+            };
+        }
+
+        match keyword {
+            Self::NAME_STR                         => read!(NameStr,                      QuotedStringParam),
+            Self::NAME                             => read!(Name,                         u32),
+
+            Self::HEATING_ENABLE                   => read!(HeatEnable),
+            Self::HEATING_DISABLE                  => read!(HeatDisable),
+            Self::CIVIL_BUILDING                   => read!(CivilBuilding),
+            Self::MONUMENT_TRESPASS                => read!(MonumentTrespass),
+            Self::MONUMENT_ELE_CONSUMPTION_ADD     => read!(MonumentEleConsumptionAdd,    f32),
+            Self::QUALITY_OF_LIVING                => read!(QualityOfLiving,              f32),
+
+            Self::WORKERS_NEEDED                   => read!(WorkersNeeded,                u32),
+            Self::PROFESSORS_NEEDED                => read!(ProfessorsNeeded,             u32),
+            Self::CITIZEN_ABLE_SERVE               => read!(CitizenAbleServe,             u32),
+            Self::CONSUMPTION                      => read!(Consumption,                  (ResourceType, f32)),
+            Self::CONSUMPTION_PER_SEC              => read!(ConsumptionPerSec,            (ResourceType, f32)),
+            Self::PRODUCTION                       => read!(Production,                   (ResourceType, f32)),
+            Self::PRODUCTION_SUN                   => read!(ProductionSun,                f32),
+            Self::PRODUCTION_WIND                  => read!(ProductionWind,               f32),
+            Self::SEASONAL_TEMP_MIN                => read!(SeasonalTempMin,              f32),
+            Self::SEASONAL_TEMP_MAX                => read!(SeasonalTempMax,              f32),
+
+            Self::ELE_CONSUM_WORKER_FACTOR_BASE    => read!(EleConsumWorkerFactorBase,    f32),
+            Self::ELE_CONSUM_WORKER_FACTOR_NIGHT   => read!(EleConsumWorkerFactorNight,   f32),
+            Self::ELE_CONSUM_SERVE_FACTOR_BASE     => read!(EleConsumServeFactorBase,     f32),
+            Self::ELE_CONSUM_SERVE_FACTOR_NIGHT    => read!(EleConsumServeFactorNight,    f32),
+            Self::ELE_CONSUM_CARGO_LOAD_FACTOR     => read!(EleConsumCargoLoadFactor,     f32),
+            Self::ELE_CONSUM_CARGO_UNLOAD_FACTOR   => read!(EleConsumCargoUnloadFactor,   f32),
+
+            Self::NO_ELE_WORK_FACTOR_BASE          => read!(NoEleWorkFactorBase,          f32),
+            Self::NO_ELE_WORK_FACTOR_NIGHT         => read!(NoEleWorkFactorNight,         f32),
+            Self::NO_HEAT_WORK_FACTOR              => read!(NoHeatWorkFactor,             f32),
+
+            Self::ENGINE_SPEED                     => read!(EngineSpeed,                  f32),
+            Self::CABLEWAY_HEAVY                   => read!(CablewayHeavy),
+            Self::CABLEWAY_LIGHT                   => read!(CablewayLight),
+
+            Self::STORAGE                          => read!(Storage,                      (StorageCargoType, f32)),
+            Self::STORAGE_SPECIAL                  => read!(StorageSpecial,               (StorageCargoType, f32, ResourceType)),
+            Self::STORAGE_FUEL                     => read!(StorageFuel,                  (StorageCargoType, f32)),
+            Self::STORAGE_EXPORT                   => read!(StorageExport,                (StorageCargoType, f32)),
+            Self::STORAGE_IMPORT                   => read!(StorageImport,                (StorageCargoType, f32)),
+            Self::STORAGE_IMPORT_CARPLANT          => read!(StorageImportCarplant,        (StorageCargoType, f32)),
+            Self::STORAGE_EXPORT_SPECIAL           => read!(StorageExportSpecial,         (StorageCargoType, f32, ResourceType)),
+            Self::STORAGE_IMPORT_SPECIAL           => read!(StorageImportSpecial,         (StorageCargoType, f32, ResourceType)),
+            Self::STORAGE_DEMAND_BASIC             => read!(StorageDemandBasic,           (StorageCargoType, f32)),
+            Self::STORAGE_DEMAND_MEDIUM            => read!(StorageDemandMedium,          (StorageCargoType, f32)),
+            Self::STORAGE_DEMAND_MEDIUMADVANCED    => read!(StorageDemandMediumAdvanced,  (StorageCargoType, f32)),
+            Self::STORAGE_DEMAND_ADVANCED          => read!(StorageDemandAdvanced,        (StorageCargoType, f32)),
+            Self::STORAGE_DEMAND_HOTEL             => read!(StorageDemandHotel,           (StorageCargoType, f32)),
+            Self::STORAGE_PACK_FROM                => read!(StoragePackFrom,              u32),
+            Self::STORAGE_UNPACK_TO                => read!(StorageUnpackTo,              u32),
+            Self::STORAGE_LIVING_AUTO              => read!(StorageLivingAuto,            IdStringParam),
+
+            Self::VEHICLE_LOADING_FACTOR           => read!(VehicleLoadingFactor,         f32),
+            Self::VEHICLE_UNLOADING_FACTOR         => read!(VehicleUnloadingFactor,       f32),
+
+            Self::ROAD_VEHICLE_NOT_FLIP            => read!(RoadNotFlip),
+            Self::ROAD_VEHICLE_ELECTRIC            => read!(RoadElectric),
+            Self::ROAD_VEHICLE_FORKLIFT_PASS       => read!(RoadForkliftPass),
+            Self::VEHICLE_CANNOT_SELECT            => read!(VehicleCannotSelect),
+            Self::LONG_TRAINS                      => read!(LongTrains),
+
+            Self::WORKING_VEHICLES_NEEDED          => read!(WorkingVehiclesNeeded,        u32),
+            Self::VEHICLE_STATION                  => read!(VehicleStation,               (Point3f, Point3f)),
+            Self::VEHICLE_STATION_NOT_BLOCK        => read!(VehicleStationNotBlock),
+            Self::VEHICLE_STATION_DETOUR_POINT     => read!(VehicleStationDetourPoint,    Point3f),
+            Self::VEHICLE_STATION_DETOUR_PID       => read!(VehicleStationDetourPid,      (u32, Point3f)),
+
+            Self::VEHICLE_PARKING                  => read!(VehicleParking,               (Point3f, Point3f)),
+            Self::VEHICLE_PARKING_DETOUR_POINT     => read!(VehicleParkingDetourPoint,    Point3f),
+            Self::VEHICLE_PARKING_DETOUR_PID       => read!(VehicleParkingDetourPid,      (u32, Point3f)),
+            Self::VEHICLE_PARKING_PERSONAL         => read!(VehicleParkingPersonal,       (Point3f, Point3f)),
+
+            Self::HELIPORT_STATION                 => read!(HeliportStation,              (Point3f, Point3f)),
+            Self::SHIP_STATION                     => read!(ShipStation,                  (Point3f, Point3f)),
+            Self::HELIPORT_AREA                    => read!(HeliportArea,                 f32),
+            Self::HARBOR_OVER_TERRAIN_FROM         => read!(HarborTerrainFrom,            f32),
+            Self::HARBOR_OVER_WATER_FROM           => read!(HarborWaterFrom,              f32),
+            Self::HARBOR_EXTEND_WHEN_BULDING       => read!(HarborExtendWhenBuilding,     f32),
+
+            Self::CONNECTIONS_SPACE                => read!(ConnectionsSpace,             Rect),
+            Self::CONNECTIONS_ROAD_DEAD_SQUARE     => read!(ConnectionsRoadDeadSquare,    Rect),
+            Self::CONNECTIONS_AIRPORT_DEAD_SQUARE  => read!(ConnectionsAirportDeadSquare, Rect),
+            Self::CONNECTIONS_PEDESTRIAN_DEAD_SQUARE=> read!(ConnectionsPedestrianDeadSquare,Rect),
+            Self::CONNECTIONS_WATER_DEAD_SQUARE    => read!(ConnectionsWaterDeadSquare,   (f32, Rect)),
+            Self::OFFSET_CONNECTION_XYZW           => read!(OffsetConnection,             (u32, Point3f)),
+
+            Self::ATTRACTION_REMEMBER_USAGE        => read!(AttractionRememberUsage),
+            Self::ATTRACTIVE_SCORE_BASE            => read!(AttractiveScoreBase,          f32),
+            Self::ATTRACTIVE_SCORE_ALCOHOL         => read!(AttractiveScoreAlcohol,       f32),
+            Self::ATTRACTIVE_SCORE_CULTURE         => read!(AttractiveScoreCulture,       f32),
+            Self::ATTRACTIVE_SCORE_RELIGION        => read!(AttractiveScoreReligion,      f32),
+            Self::ATTRACTIVE_SCORE_SPORT           => read!(AttractiveScoreSport,         f32),
+            Self::ATTRACTIVE_FACTOR_NATURE         => read!(AttractiveFactorNature,       f32),
+            Self::ATTRACTIVE_FACTOR_NATURE_ADD     => read!(AttractiveFactorNatureAdd,    f32),
+            Self::ATTRACTIVE_FACTOR_POLLUTION      => read!(AttractiveFactorPollution,    f32),
+            Self::ATTRACTIVE_FACTOR_POLLUTION_ADD  => read!(AttractiveFactorPollutionAdd, f32),
+            Self::ATTRACTIVE_FACTOR_SIGHT          => read!(AttractiveFactorSight,        f32),
+            Self::ATTRACTIVE_FACTOR_SIGHT_ADD      => read!(AttractiveFactorSightAdd,     f32),
+            Self::ATTRACTIVE_FACTOR_WATER          => read!(AttractiveFactorWater,        f32),
+            Self::ATTRACTIVE_FACTOR_WATER_ADD      => read!(AttractiveFactorWaterAdd,     f32),
+
+            Self::POLLUTION_HIGH                   => read!(PollutionHigh),
+            Self::POLLUTION_MEDIUM                 => read!(PollutionMedium),
+            Self::POLLUTION_SMALL                  => read!(PollutionSmall),
+
+            Self::PARTICLE                         => read!(Particle,                     (ParticleType, Point3f, f32, f32)),
+            Self::PARTICLE_REACTOR                 => read!(ParticleReactor,              Point3f),
+            Self::PARTICLE_SNOW_REMOVE             => read!(ParticleSnowRemove,           (Point3f, u32, f32)),
+
+            Self::TEXT_CAPTION                     => read!(TextCaption,                  (Point3f, Point3f)),
+            Self::WORKER_RENDERING_AREA            => read!(WorkerRenderingArea,          (Point3f, Point3f)),
+            Self::RESOURCE_VISUALIZATION           => read!(ResourceVisualization,        ResourceVisualization),
+            Self::RESOURCE_INCREASE_POINT          => read!(ResourceIncreasePoint,        (u32, Point3f)),
+            Self::RESOURCE_INCREASE_CONV_POINT     => read!(ResourceIncreaseConvPoint,    (u32, Point3f, Point3f)),
+            Self::RESOURCE_FILLING_POINT           => read!(ResourceFillingPoint,         Point3f),
+            Self::RESOURCE_FILLING_CONV_POINT      => read!(ResourceFillingConvPoint,     (Point3f, Point3f)),
+            Self::WORKING_SFX                      => read!(WorkingSfx,                   IdStringParam),
+            Self::WORKING_SFX_DISTANCE             => read!(WorkingSfxDistance,           f32),
+            Self::ANIMATION_FPS                    => read!(AnimationFps,                 f32),
+            Self::ANIMATION_MESH                   => read!(AnimationMesh,                (IdStringParam, IdStringParam)),
+            Self::ANIMATION_MESH_WORKSHOP          => read!(AnimationMeshWorkshop,        (IdStringParam, IdStringParam)),
+            Self::UNDERGROUND_MESH                 => read!(UndergroundMesh,              (IdStringParam, IdStringParam)),
+
+            Self::COST_WORK                        => read!(CostWork,                     (ConstructionPhase, f32)),
+            Self::COST_WORK_BUILDING_NODE          => read!(CostWorkBuildingNode,         IdStringParam),
+            Self::COST_WORK_BUILDING_KEYWORD       => read!(CostWorkBuildingKeyword,      CostKeywordParam),
+            Self::COST_WORK_BUILDING_ALL           => read!(CostWorkBuildingAll),
+
+            Self::COST_RESOURCE                    => read!(CostResource,                 (ResourceType, f32)),
+            Self::COST_RESOURCE_AUTO               => read!(CostResourceAuto,             (ConstructionAutoCost, f32)),
+
+            Self::COST_WORK_VEHICLE_STATION        => read!(CostWorkVehicleStation,       (Point3f, Point3f)),
+            Self::COST_WORK_VEHICLE_STATION_NODE   => read!(CostWorkVehicleStationNode,   IdStringParam),
+
+
+            _ => Self::tagged_from_json(keyword, params)
+        }
+    }
+
+    fn tagged_from_json(keyword: &str, params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        if let Some(tag) = keyword.strip_prefix(Self::BUILDING_TYPE) {
+            BuildingType::from_name(tag).map(Self::BuildingType)
+        } else if let Some(tag) = keyword.strip_prefix(Self::BUILDING_SUBTYPE) {
+            BuildingSubtype::from_name(tag).map(Self::BuildingSubtype)
+        } else if let Some(tag) = keyword.strip_prefix(Self::RESOURCE_SOURCE) {
+            ResourceSourceType::from_name(tag).map(Self::ResourceSource)
+        } else if let Some(tag) = keyword.strip_prefix(Self::AIRPLANE_STATION) {
+            let t = AirplaneStationType::from_name(tag)?;
+            <(Point3f, Point3f)>::read_json(params).map(|(a, b)| Self::AirplaneStation((t, a, b)))
+        } else if let Some(tag) = keyword.strip_prefix(Self::ATTRACTION_TYPE) {
+            let t = AttractionType::from_name(tag)?;
+            u32::read_json(params).map(|x| Self::AttractionType((t, x)))
+        } else if let Some(tag) = keyword.strip_prefix(Self::CONNECTION) {
+            if let Some(t) = Connection2PType::from_str(tag) {
+                <(Point3f, Point3f)>::read_json(params).map(|(a, b)| Self::Connection2Points((t, a, b)))
+            } else if let Some(t) = Connection1PType::from_str(tag) {
+                Point3f::read_json(params).map(|p| Self::Connection1Point((t, p)))
+            } else if tag == Self::CONNECTION_RAIL_DEADEND {
+                Ok(Self::ConnectionRailDeadend)
+            } else {
+                let names: Vec<&'static str> = Connection2PType::NAMES.iter()
+                    .chain(Connection1PType::NAMES.iter())
+                    .chain(std::iter::once(&Self::CONNECTION_RAIL_DEADEND))
+                    .copied()
+                    .collect();
+                Err(ParseError::unknown("connection type", tag, &names).to_string())
+            }
+        } else {
+            Err(ParseError::unknown("token type", keyword, Self::NAMES).to_string())
+        }
+    }
+}
+
+
+macro_rules! json_literals {
+    ($($t:ty: $kind:expr),* $(,)?) => {
+        $(
+            impl JsonLiteral for $t {
+                fn from_name(name: &str) -> Result<Self, String> {
+                    <$t>::from_str(name).ok_or_else(|| ParseError::unknown($kind, name, <$t>::NAMES).to_string())
+                }
+            }
+        )*
+    };
+}
+
+json_literals! {
+    BuildingType:         "building type",
+    BuildingSubtype:      "building subtype",
+    Connection2PType:     "2-point connection type",
+    Connection1PType:     "1-point connection type",
+    StorageCargoType:     "storage cargo type",
+    ParticleType:         "particle type",
+    ConstructionPhase:    "construction phase",
+    ConstructionAutoCost: "construction auto cost",
+    ResourceType:         "resource type",
+    AirplaneStationType:  "airplane station type",
+    AttractionType:       "attraction type",
+    ResourceSourceType:   "resource-source type",
+}
+
+
+// {"storage": 0, "position": [x, y, z], "rotation": r, "scale": [x, y, z], "numstep_x": [step, n], "numstep_z": [step, n]}
+impl<'a> JsonParam<'a> for ResourceVisualization {
+    fn write_json(&self, params: &mut Vec<Value>) {
+        fn value<'a, T: JsonParam<'a>>(p: &T) -> Value {
+            let mut vs = Vec::with_capacity(2);
+            p.write_json(&mut vs);
+            if vs.len() == 1 { vs.pop().unwrap() } else { Value::Array(vs) }
+        }
+
+        let mut obj = Map::with_capacity(6);
+        obj.insert("storage".to_string(),   value(&self.storage_id));
+        obj.insert("position".to_string(),  value(&self.position));
+        obj.insert("rotation".to_string(),  value(&self.rotation));
+        obj.insert("scale".to_string(),     value(&self.scale));
+        obj.insert("numstep_x".to_string(), value(&self.numstep_x));
+        obj.insert("numstep_z".to_string(), value(&self.numstep_z));
+        params.push(Value::Object(obj));
+    }
+
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        let v = params.next().ok_or_else(|| "Not enough parameters".to_string())?;
+        let obj = v.as_object().ok_or_else(|| format!("Expected an object, found {}", v))?;
+
+        // numsteps are [step, count] arrays, other fields are single values
+        fn field<'a, T: JsonParam<'a>>(obj: &'a Map<String, Value>, name: &str, is_pair: bool) -> Result<T, String> {
+            let v = obj.get(name).ok_or_else(|| format!("Missing \"{}\"", name))?;
+            let mut iter = match v {
+                Value::Array(vs) if is_pair => vs.iter(),
+                _ => slice::from_ref(v).iter()
+            };
+
+            T::read_json(&mut iter)
+             .and_then(|x| if iter.len() == 0 { Ok(x) } else { Err("Too many values".to_string()) })
+             .map_err(|e| format!("\"{}\": {}", name, e))
+        }
+
+        Ok(ResourceVisualization {
+            storage_id: field(obj, "storage",   false)?,
+            position:   field(obj, "position",  false)?,
+            rotation:   field(obj, "rotation",  false)?,
+            scale:      field(obj, "scale",     false)?,
+            numstep_x:  field(obj, "numstep_x", true)?,
+            numstep_z:  field(obj, "numstep_z", true)?,
+        })
+    }
+}
@@ -0,0 +1,457 @@
+// Semantic checks of a parsed building.ini: things that parse fine, but make no sense to the game.
+//
+// Every rule has an id and a default level. Levels can be changed per rule with LintConfig
+// (from the command line: --allow, --warn, --deny).
+
+use std::fmt::{Display, Formatter, Error};
+use std::mem::{discriminant, Discriminant};
+use std::ops::Range;
+
+use crate::ini::BuildingIni;
+use crate::ini::common::{ParseError, Point3f, Rect};
+use crate::nmf::object_full::RawBBox;
+
+use super::{Token, BuildingType, Connection2PType, ResourceType, StorageCargoType};
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+
+// What the rules look at: tokens with their byte ranges in the source and
+// the bounding box of the main model (geometry rules are skipped without it)
+pub struct Building<'a, 't> {
+    tokens: Vec<(Range<usize>, &'a Token<'t>)>,
+    bbox: Option<&'a RawBBox>,
+}
+
+type Found = Vec<(Range<usize>, String)>;
+
+
+pub struct Rule {
+    pub id: &'static str,
+    pub level: Level,
+    pub description: &'static str,
+    check: fn(&Building, &mut Found),
+}
+
+
+pub struct Lint {
+    pub rule: &'static str,
+    pub level: Level,
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+
+#[derive(Default)]
+pub struct LintConfig {
+    overrides: Vec<(&'static str, Level)>,
+}
+
+
+pub static RULES: &[Rule] = &[
+    Rule { id: "missing-type",           level: Level::Deny, check: missing_type,
+           description: "building.ini has no $TYPE_ token" },
+    Rule { id: "duplicate-token",        level: Level::Warn, check: duplicate_token,
+           description: "A token that is expected once (or once per resource) is repeated" },
+    Rule { id: "workers-not-allowed",    level: Level::Warn, check: workers_not_allowed,
+           description: "$WORKERS_NEEDED is set for a building type that has no workers" },
+    Rule { id: "storage-unreachable",    level: Level::Warn, check: storage_unreachable,
+           description: "Storages are defined, but there is no vehicle station, conveyor or pipe connection to serve them" },
+    Rule { id: "production-no-storage",  level: Level::Warn, check: production_no_storage,
+           description: "$PRODUCTION of a resource which no storage of the building can hold" },
+    Rule { id: "consumption-no-storage", level: Level::Warn, check: consumption_no_storage,
+           description: "$CONSUMPTION of a resource which no storage of the building can hold" },
+    Rule { id: "cost-work-no-resources", level: Level::Warn, check: cost_work_no_resources,
+           description: "Construction phases are defined without any $COST_RESOURCE" },
+    Rule { id: "point-outside-model",    level: Level::Warn, check: point_outside_model,
+           description: "A connection, station or resource filling point is far outside the model footprint or above its roof" },
+    Rule { id: "space-not-covering",     level: Level::Warn, check: space_not_covering,
+           description: "$CONNECTIONS_SPACE does not cover the model footprint" },
+    Rule { id: "station-length",         level: Level::Warn, check: station_length,
+           description: "A station segment has zero or absurd length" },
+];
+
+
+impl LintConfig {
+    pub fn set(&mut self, rule_id: &str, level: Level) -> Result<(), ParseError> {
+        match RULES.iter().find(|r| r.id == rule_id) {
+            Some(rule) => {
+                self.overrides.retain(|(id, _)| *id != rule.id);
+                self.overrides.push((rule.id, level));
+                Ok(())
+            },
+            None => {
+                let ids: Vec<_> = RULES.iter().map(|r| r.id).collect();
+                Err(ParseError::unknown("lint rule", rule_id, &ids))
+            }
+        }
+    }
+
+    pub fn level(&self, rule: &Rule) -> Level {
+        self.overrides.iter()
+                      .find(|(id, _)| *id == rule.id)
+                      .map(|(_, lvl)| *lvl)
+                      .unwrap_or(rule.level)
+    }
+}
+
+
+// Runs all enabled rules. Lints are ordered by rule, then by position in the file.
+pub fn check(ini: &BuildingIni, bbox: Option<&RawBBox>, config: &LintConfig) -> Vec<Lint> {
+    let building = Building { tokens: ini.tokens_with_spans().collect(), bbox };
+    let mut result = Vec::with_capacity(0);
+    let mut found = Vec::with_capacity(0);
+
+    for rule in RULES.iter() {
+        let level = config.level(rule);
+        if level == Level::Allow {
+            continue;
+        }
+
+        (rule.check)(&building, &mut found);
+        result.extend(found.drain(..).map(|(span, message)| Lint { rule: rule.id, level, span, message }));
+    }
+
+    result
+}
+
+
+impl Display for Level {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Level::Allow => write!(f, "allow"),
+            Level::Warn  => write!(f, "warning"),
+            Level::Deny  => write!(f, "error"),
+        }
+    }
+}
+
+
+//---------------------------------------------------------------
+// rules
+
+fn missing_type(b: &Building, found: &mut Found) {
+    if !b.tokens.iter().any(|(_, t)| matches!(t, Token::BuildingType(_))) {
+        found.push((0 .. 0, format!("Building type is not set (${}...)", Token::BUILDING_TYPE)));
+    }
+}
+
+
+// Tokens expected once in a file: Some((kind, None)). Tokens with a resource parameter are expected
+// once per resource: Some((kind, Some(resource))). The "once" flags of the embedded schema follow this.
+pub(crate) fn duplicate_key<'t>(t: &Token<'t>) -> Option<(Discriminant<Token<'t>>, Option<ResourceType>)> {
+    let res = match t {
+        Token::Consumption((r, _))
+        | Token::ConsumptionPerSec((r, _))
+        | Token::Production((r, _))
+        | Token::CostResource((r, _)) => Some(r.clone()),
+
+        Token::NameStr(_) | Token::Name(_) | Token::BuildingType(_) | Token::BuildingSubtype(_)
+        | Token::HeatEnable | Token::HeatDisable | Token::CivilBuilding | Token::MonumentTrespass
+        | Token::QualityOfLiving(_) | Token::WorkersNeeded(_) | Token::ProfessorsNeeded(_) | Token::CitizenAbleServe(_)
+        | Token::ProductionSun(_) | Token::ProductionWind(_) | Token::SeasonalTempMin(_) | Token::SeasonalTempMax(_)
+        | Token::EleConsumWorkerFactorNight(_) | Token::EleConsumWorkerFactorBase(_)
+        | Token::EleConsumServeFactorNight(_) | Token::EleConsumServeFactorBase(_)
+        | Token::EleConsumCargoLoadFactor(_) | Token::EleConsumCargoUnloadFactor(_)
+        | Token::NoEleWorkFactorBase(_) | Token::NoEleWorkFactorNight(_) | Token::NoHeatWorkFactor(_)
+        | Token::EngineSpeed(_) | Token::VehicleLoadingFactor(_) | Token::VehicleUnloadingFactor(_)
+        | Token::RoadNotFlip | Token::RoadElectric | Token::VehicleCannotSelect | Token::LongTrains
+        | Token::WorkingVehiclesNeeded(_) | Token::HeliportArea(_)
+        | Token::HarborTerrainFrom(_) | Token::HarborWaterFrom(_) | Token::HarborExtendWhenBuilding(_)
+        | Token::AttractionRememberUsage
+        | Token::AttractiveScoreBase(_) | Token::AttractiveScoreAlcohol(_) | Token::AttractiveScoreCulture(_)
+        | Token::AttractiveScoreReligion(_) | Token::AttractiveScoreSport(_)
+        | Token::AttractiveFactorNature(_) | Token::AttractiveFactorNatureAdd(_)
+        | Token::AttractiveFactorPollution(_) | Token::AttractiveFactorPollutionAdd(_)
+        | Token::AttractiveFactorSight(_) | Token::AttractiveFactorSightAdd(_)
+        | Token::AttractiveFactorWater(_) | Token::AttractiveFactorWaterAdd(_)
+        | Token::PollutionHigh | Token::PollutionMedium | Token::PollutionSmall
+        | Token::WorkingSfx(_) | Token::WorkingSfxDistance(_) | Token::AnimationFps(_) => None,
+
+        _ => return None
+    };
+
+    Some((discriminant(t), res))
+}
+
+
+fn duplicate_token(b: &Building, found: &mut Found) {
+    let mut seen = Vec::with_capacity(16);
+    let mut seen_declared = Vec::with_capacity(0);
+    for (span, t) in b.tokens.iter() {
+        // tokens from the schema file are expected once when declared so
+        if let Token::Schema(st) = t {
+            if st.def.once {
+                if seen_declared.contains(&st.keyword) {
+                    found.push((span.clone(), format!("Duplicate token ${}", st.keyword)));
+                } else {
+                    seen_declared.push(st.keyword);
+                }
+            }
+        } else if let Some(k) = duplicate_key(t) {
+            if seen.contains(&k) {
+                found.push((span.clone(), format!("Duplicate token ${}", keyword(t))));
+            } else {
+                seen.push(k);
+            }
+        }
+    }
+}
+
+
+fn workers_not_allowed(b: &Building, found: &mut Found) {
+    let b_type = b.tokens.iter().find_map(|(_, t)| match t {
+        Token::BuildingType(bt) => Some(bt),
+        _ => None
+    });
+
+    let no_workers = matches!(b_type, Some(BuildingType::Living)
+                                    | Some(BuildingType::Parking)
+                                    | Some(BuildingType::PedestrianBridge)
+                                    | Some(BuildingType::Substation)
+                                    | Some(BuildingType::Transformator)
+                                    | Some(BuildingType::RailTrafo)
+                                    | Some(BuildingType::HeatingSwitch)
+                                    | Some(BuildingType::Monument));
+    if !no_workers {
+        return;
+    }
+
+    for (span, t) in b.tokens.iter() {
+        if let Token::WorkersNeeded(n) = t {
+            if *n > 0 {
+                found.push((span.clone(), format!("${} is set, but buildings of type {} have no workers", Token::WORKERS_NEEDED, b_type.unwrap())));
+            }
+        }
+    }
+}
+
+
+fn storage_unreachable(b: &Building, found: &mut Found) {
+    let first_storage = b.tokens.iter().find(|(_, t)| storage_of(t).is_some());
+
+    let has_access = b.tokens.iter().any(|(_, t)| match t {
+        Token::VehicleStation(_) | Token::AirplaneStation(_) | Token::HeliportStation(_) | Token::ShipStation(_) => true,
+        Token::Connection2Points((c, _, _)) => matches!(c,
+            Connection2PType::ConveyorIn | Connection2PType::ConveyorOut
+            | Connection2PType::BulkIn   | Connection2PType::BulkOut
+            | Connection2PType::PipeIn   | Connection2PType::PipeOut
+            | Connection2PType::Cableway | Connection2PType::Factory),
+        _ => false
+    });
+
+    if let (Some((span, t)), false) = (first_storage, has_access) {
+        found.push((span.clone(), format!("${} has no vehicle station, conveyor or pipe connection to be served from", keyword(t))));
+    }
+}
+
+
+fn production_no_storage(b: &Building, found: &mut Found) {
+    let outputs: Vec<_> = b.tokens.iter().filter_map(|(_, t)| match t {
+        Token::StorageImport(_) | Token::StorageImportSpecial(_) | Token::StorageImportCarplant(_) | Token::StorageFuel(_) => None,
+        Token::StorageDemandBasic(_) | Token::StorageDemandMedium(_) | Token::StorageDemandMediumAdvanced(_)
+        | Token::StorageDemandAdvanced(_) | Token::StorageDemandHotel(_) => None,
+        t => storage_of(t)
+    }).collect();
+
+    for (span, t) in b.tokens.iter() {
+        if let Token::Production((res, _)) = t {
+            if !can_store(&outputs, res) {
+                found.push((span.clone(), format!("Building produces '{}', but has no output storage for it", res)));
+            }
+        }
+    }
+}
+
+
+fn consumption_no_storage(b: &Building, found: &mut Found) {
+    let inputs: Vec<_> = b.tokens.iter().filter_map(|(_, t)| match t {
+        Token::StorageExport(_) | Token::StorageExportSpecial(_) => None,
+        t => storage_of(t)
+    }).collect();
+
+    for (span, t) in b.tokens.iter() {
+        if let Token::Consumption((res, _)) | Token::ConsumptionPerSec((res, _)) = t {
+            if !can_store(&inputs, res) {
+                found.push((span.clone(), format!("Building consumes '{}', but has no input storage for it", res)));
+            }
+        }
+    }
+}
+
+
+fn cost_work_no_resources(b: &Building, found: &mut Found) {
+    let first_phase = b.tokens.iter().find(|(_, t)| matches!(t, Token::CostWork(_)));
+    let has_resources = b.tokens.iter().any(|(_, t)| matches!(t, Token::CostResource(_) | Token::CostResourceAuto(_)));
+
+    if let (Some((span, _)), false) = (first_phase, has_resources) {
+        found.push((span.clone(), format!("Construction phases are set, but there is no ${} or ${}", Token::COST_RESOURCE, Token::COST_RESOURCE_AUTO)));
+    }
+}
+
+
+// Geometry rules. Tolerances are in meters.
+
+// Connections usually sit on the edge of the building or a bit outside of it
+const MAX_DIST_FROM_FOOTPRINT: f32 = 10.0;
+const MAX_HEIGHT_ABOVE_ROOF:   f32 = 2.0;
+const SPACE_TOLERANCE:         f32 = 0.5;
+const MIN_STATION_LENGTH:      f32 = 0.5;
+const MAX_STATION_LENGTH:      f32 = 1000.0;
+
+
+fn point_outside_model(b: &Building, found: &mut Found) {
+    let bbox = match b.bbox {
+        Some(bbox) => bbox,
+        None => return
+    };
+
+    for (span, t) in b.tokens.iter() {
+        let points: &[&Point3f] = match t {
+            Token::Connection2Points((_, p1, p2))
+            | Token::VehicleStation((p1, p2))
+            | Token::ResourceFillingConvPoint((p1, p2)) => &[p1, p2],
+            Token::Connection1Point((_, p))
+            | Token::ResourceFillingPoint(p) => &[p],
+            _ => continue
+        };
+
+        for p in points.iter() {
+            let dx = (bbox.v_min.x - p.x).max(p.x - bbox.v_max.x).max(0.0);
+            let dz = (bbox.v_min.z - p.z).max(p.z - bbox.v_max.z).max(0.0);
+            let dist = (dx * dx + dz * dz).sqrt();
+
+            if dist > MAX_DIST_FROM_FOOTPRINT {
+                found.push((span.clone(), format!("${} point ({}, {}, {}) is {:.1} m outside of the model footprint", keyword(t), p.x, p.y, p.z, dist)));
+            } else if p.y > bbox.v_max.y + MAX_HEIGHT_ABOVE_ROOF {
+                found.push((span.clone(), format!("${} point ({}, {}, {}) is {:.1} m above the top of the model", keyword(t), p.x, p.y, p.z, p.y - bbox.v_max.y)));
+            }
+        }
+    }
+}
+
+
+fn space_not_covering(b: &Building, found: &mut Found) {
+    let bbox = match b.bbox {
+        Some(bbox) => bbox,
+        None => return
+    };
+
+    for (span, t) in b.tokens.iter() {
+        if let Token::ConnectionsSpace(Rect { x1, z1, x2, z2 }) = t {
+            let covers = x1.min(*x2) <= bbox.v_min.x + SPACE_TOLERANCE
+                      && x1.max(*x2) >= bbox.v_max.x - SPACE_TOLERANCE
+                      && z1.min(*z2) <= bbox.v_min.z + SPACE_TOLERANCE
+                      && z1.max(*z2) >= bbox.v_max.z - SPACE_TOLERANCE;
+            if !covers {
+                found.push((span.clone(), format!("${} does not cover the model footprint (x: {} .. {}, z: {} .. {})", 
+                                                  keyword(t), bbox.v_min.x, bbox.v_max.x, bbox.v_min.z, bbox.v_max.z)));
+            }
+        }
+    }
+}
+
+
+fn station_length(b: &Building, found: &mut Found) {
+    for (span, t) in b.tokens.iter() {
+        let (p1, p2) = match t {
+            Token::VehicleStation((p1, p2))
+            | Token::AirplaneStation((_, p1, p2))
+            | Token::HeliportStation((p1, p2))
+            | Token::ShipStation((p1, p2)) => (p1, p2),
+            _ => continue
+        };
+
+        let len = ((p2.x - p1.x).powi(2) + (p2.y - p1.y).powi(2) + (p2.z - p1.z).powi(2)).sqrt();
+        if len < MIN_STATION_LENGTH {
+            found.push((span.clone(), format!("${} has zero length", keyword(t))));
+        } else if len > MAX_STATION_LENGTH {
+            found.push((span.clone(), format!("${} is {:.0} m long", keyword(t), len)));
+        }
+    }
+}
+
+
+//---------------------------------------------------------------
+
+// Token name as written in the file
+fn keyword(t: &Token) -> String {
+    let s = t.to_string();
+    s.split_whitespace().next().unwrap_or_default().to_string()
+}
+
+
+// Cargo type of a storage token; special storages hold only the specified resource
+fn storage_of<'t>(t: &'t Token) -> Option<(&'t StorageCargoType, Option<&'t ResourceType>)> {
+    match t {
+        Token::Storage((c, _))
+        | Token::StorageFuel((c, _))
+        | Token::StorageExport((c, _))
+        | Token::StorageImport((c, _))
+        | Token::StorageImportCarplant((c, _))
+        | Token::StorageDemandBasic((c, _))
+        | Token::StorageDemandMedium((c, _))
+        | Token::StorageDemandMediumAdvanced((c, _))
+        | Token::StorageDemandAdvanced((c, _))
+        | Token::StorageDemandHotel((c, _)) => Some((c, None)),
+
+        Token::StorageSpecial((c, _, r))
+        | Token::StorageExportSpecial((c, _, r))
+        | Token::StorageImportSpecial((c, _, r)) => Some((c, Some(r))),
+
+        _ => None
+    }
+}
+
+
+fn can_store(storages: &[(&StorageCargoType, Option<&ResourceType>)], res: &ResourceType) -> bool {
+    let cargos = cargo_types(res);
+    if cargos.is_empty() {
+        // electricity, heat: delivered by networks
+        return true;
+    }
+
+    storages.iter().any(|(c, r)| match r {
+        Some(r) => *r == res,
+        None    => *c == &StorageCargoType::General || cargos.contains(*c)
+    })
+}
+
+
+// Cargo types a resource can be transported in. Deliberately permissive: a false "no storage" warning
+// is worse than a missed one.
+fn cargo_types(res: &ResourceType) -> &'static [StorageCargoType] {
+    use ResourceType as R;
+    use StorageCargoType as C;
+
+    match res {
+        R::Electricity | R::Heat => &[],
+        R::Workers => &[C::Passanger],
+
+        R::Alcohol | R::Chemicals | R::Clothes | R::Crops | R::ElectroComponents
+        | R::Electronics | R::Fabric | R::Plastic => &[C::Covered],
+        R::Food        => &[C::Covered, C::Cooler],
+        R::Meat        => &[C::Cooler],
+        R::Alumina | R::Cement => &[C::Cement, C::Covered],
+        R::MechComponents => &[C::Covered, C::Open],
+
+        R::Aluminium | R::Boards | R::Bricks | R::PrefabPanels | R::Steel | R::Wood => &[C::Open],
+        R::Iron        => &[C::Gravel, C::Open],
+        R::Bauxite | R::RawBauxite | R::Coal | R::RawCoal | R::Gravel | R::RawGravel | R::RawIron | R::Uranium => &[C::Gravel],
+        R::Asphalt     => &[C::Gravel, C::Concrete],
+        R::Concrete    => &[C::Concrete],
+        R::Oil | R::Fuel | R::Bitumen => &[C::Oil],
+
+        R::Livestock   => &[C::Livestock],
+        R::Vehicles    => &[C::Vehicles],
+        R::Yellowcake  => &[C::Covered, C::Nuclear1],
+        R::UF6 | R::NuclearFuel => &[C::Nuclear1],
+        R::NuclearWaste => &[C::Nuclear2],
+    }
+}
@@ -1,9 +1,13 @@
 mod display;
 mod parse;
+mod json;
+pub mod lint;
 
 use crate::ini::common::{Point3f, Rect, QuotedStringParam, IdStringParam, CostKeywordParam};
+use crate::ini::schema::SchemaToken;
 
 pub use parse::{parse_tokens, parse_tokens_strict};
+pub(crate) use parse::LITERALS;
 
 //#[derive(Clone)]
 pub enum Token<'a> {
@@ -18,6 +22,7 @@ pub enum Token<'a> {
 
     CivilBuilding,
     MonumentTrespass,
+    MonumentEleConsumptionAdd(f32),
     QualityOfLiving(f32),
 
     WorkersNeeded(u32),
@@ -56,6 +61,7 @@ pub enum Token<'a> {
     StorageExportSpecial((StorageCargoType, f32, ResourceType)),
     StorageImportSpecial((StorageCargoType, f32, ResourceType)),
     StorageDemandBasic((StorageCargoType, f32)),
+    StorageDemandMedium((StorageCargoType, f32)),
     StorageDemandMediumAdvanced((StorageCargoType, f32)),
     StorageDemandAdvanced((StorageCargoType, f32)),
     StorageDemandHotel((StorageCargoType, f32)),
@@ -68,6 +74,7 @@ pub enum Token<'a> {
 
     RoadNotFlip,
     RoadElectric,
+    RoadForkliftPass,
     VehicleCannotSelect,
     LongTrains,
 
@@ -98,6 +105,7 @@ pub enum Token<'a> {
     ConnectionsSpace(Rect),
     ConnectionsRoadDeadSquare(Rect),
     ConnectionsAirportDeadSquare(Rect),
+    ConnectionsPedestrianDeadSquare(Rect),
     ConnectionsWaterDeadSquare((f32, Rect)),
 
     AttractionType((AttractionType, u32)),
@@ -131,8 +139,10 @@ pub enum Token<'a> {
     ResourceFillingPoint(Point3f),
     ResourceFillingConvPoint((Point3f, Point3f)),
     WorkingSfx(IdStringParam<'a>),
+    WorkingSfxDistance(f32),
     AnimationFps(f32),
     AnimationMesh((IdStringParam<'a>, IdStringParam<'a>)),
+    AnimationMeshWorkshop((IdStringParam<'a>, IdStringParam<'a>)),
     UndergroundMesh((IdStringParam<'a>, IdStringParam<'a>)),
 
     CostWork((ConstructionPhase, f32)),
@@ -145,6 +155,9 @@ pub enum Token<'a> {
 
     CostWorkVehicleStation((Point3f, Point3f)),
     CostWorkVehicleStationNode(IdStringParam<'a>),
+
+    // unknown to the parser, declared in the token schema
+    Schema(SchemaToken<'a>),
 }
 
 
@@ -158,6 +171,7 @@ impl<'a> Token<'a> {
     const HEATING_DISABLE:                &'static str = "HEATING_DISABLE";
     const CIVIL_BUILDING:                 &'static str = "CIVIL_BUILDING";
     const MONUMENT_TRESPASS:              &'static str = "MONUMENT_ENABLE_TRESPASSING";
+    const MONUMENT_ELE_CONSUMPTION_ADD:   &'static str = "MONUMENT_ELETRIC_CONSUMPTION_ADD";
     const QUALITY_OF_LIVING:              &'static str = "QUALITY_OF_LIVING";
 
     const WORKERS_NEEDED:                 &'static str = "WORKERS_NEEDED";
@@ -197,6 +211,7 @@ impl<'a> Token<'a> {
     const STORAGE_EXPORT_SPECIAL:         &'static str = "STORAGE_EXPORT_SPECIAL";
     const STORAGE_IMPORT_SPECIAL:         &'static str = "STORAGE_IMPORT_SPECIAL";
     const STORAGE_DEMAND_BASIC:           &'static str = "STORAGE_DEMAND_BASIC";
+    const STORAGE_DEMAND_MEDIUM:          &'static str = "STORAGE_DEMAND_MEDIUM";
     const STORAGE_DEMAND_MEDIUMADVANCED:  &'static str = "STORAGE_DEMAND_MEDIUMADVANCED";
     const STORAGE_DEMAND_ADVANCED:        &'static str = "STORAGE_DEMAND_ADVANCED";
     const STORAGE_DEMAND_HOTEL:           &'static str = "STORAGE_DEMAND_HOTEL";
@@ -211,6 +226,7 @@ impl<'a> Token<'a> {
 
     const ROAD_VEHICLE_NOT_FLIP:          &'static str = "ROADVEHICLE_NOTFLIP";
     const ROAD_VEHICLE_ELECTRIC:          &'static str = "ROADVEHICLE_ELETRIC";
+    const ROAD_VEHICLE_FORKLIFT_PASS:     &'static str = "ROADVEHICLE_FORKLIFT_PASS";
 
     const WORKING_VEHICLES_NEEDED:        &'static str = "WORKING_VEHICLES_NEEDED";
     const VEHICLE_STATION:                &'static str = "VEHICLE_STATION";
@@ -238,6 +254,7 @@ impl<'a> Token<'a> {
     const CONNECTIONS_SPACE:               &'static str = "CONNECTIONS_SPACE";
     const CONNECTIONS_ROAD_DEAD_SQUARE:    &'static str = "CONNECTIONS_ROAD_DEAD_SQUARE";
     const CONNECTIONS_AIRPORT_DEAD_SQUARE: &'static str = "CONNECTIONS_AIRPORT_DEAD_SQUARE";
+    const CONNECTIONS_PEDESTRIAN_DEAD_SQUARE: &'static str = "CONNECTIONS_PEDESTRIAN_DEAD_SQUARE";
     const CONNECTIONS_WATER_DEAD_SQUARE:   &'static str = "CONNECTIONS_WATER_DEAD_SQUARE";
 
     const ATTRACTION_TYPE:                 &'static str = "ATTRACTIVE_TYPE_";
@@ -271,8 +288,10 @@ impl<'a> Token<'a> {
     const RESOURCE_FILLING_POINT:         &'static str = "RESOURCE_FILLING_POINT";
     const RESOURCE_FILLING_CONV_POINT:    &'static str = "RESOURCE_FILLING_CONVEYOR_POINT";
     const WORKING_SFX:                    &'static str = "WORKING_SFX";
+    const WORKING_SFX_DISTANCE:           &'static str = "WORKING_SFX_DISTANCE";
     const ANIMATION_FPS:                  &'static str = "ANIMATION_SPEED_FPS";
     const ANIMATION_MESH:                 &'static str = "ANIMATION_MESH";
+    const ANIMATION_MESH_WORKSHOP:        &'static str = "ANIMATION_MESH_WORKSHOP";
     const UNDERGROUND_MESH:               &'static str = "UNDERGROUND_MESH";
 
 
@@ -288,7 +307,121 @@ impl<'a> Token<'a> {
 }
 
 
+// Canonical order of token groups in a building.ini
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TokenGroup {
+    Identity,
+    Economy,
+    Storage,
+    Stations,
+    Connections,
+    Visuals,
+    Costs,
+}
+
+
+impl TokenGroup {
+    // as named in the token schema
+    pub const NAMES: &'static [&'static str] = &["identity", "economy", "storage", "stations", "connections", "visuals", "costs"];
+    const ALL: [TokenGroup; 7] = [Self::Identity, Self::Economy, Self::Storage, Self::Stations, Self::Connections, Self::Visuals, Self::Costs];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES.iter().position(|n| *n == name).map(|i| Self::ALL[i])
+    }
+}
+
+
+impl std::fmt::Display for TokenGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", Self::NAMES[*self as usize])
+    }
+}
+
+
+impl Token<'_> {
+    pub fn group(&self) -> TokenGroup {
+        use TokenGroup as G;
+
+        match self {
+            Self::NameStr(_)                      | Self::Name(_)                      |
+            Self::BuildingType(_)                 | Self::BuildingSubtype(_)           |
+            Self::HeatEnable                      | Self::HeatDisable                  |
+            Self::CivilBuilding                   | Self::MonumentTrespass             => G::Identity,
+
+            Self::MonumentEleConsumptionAdd(_)    | Self::QualityOfLiving(_)           |
+            Self::WorkersNeeded(_)                | Self::ProfessorsNeeded(_)          |
+            Self::CitizenAbleServe(_)             | Self::Consumption(_)               |
+            Self::ConsumptionPerSec(_)            | Self::Production(_)                |
+            Self::ProductionSun(_)                | Self::ProductionWind(_)            |
+            Self::SeasonalTempMin(_)              | Self::SeasonalTempMax(_)           |
+            Self::EleConsumWorkerFactorNight(_)   | Self::EleConsumWorkerFactorBase(_) |
+            Self::EleConsumServeFactorNight(_)    | Self::EleConsumServeFactorBase(_)  |
+            Self::EleConsumCargoLoadFactor(_)     | Self::EleConsumCargoUnloadFactor(_) |
+            Self::NoEleWorkFactorBase(_)          | Self::NoEleWorkFactorNight(_)      |
+            Self::NoHeatWorkFactor(_)             | Self::EngineSpeed(_)               |
+            Self::CablewayHeavy                   | Self::CablewayLight                |
+            Self::ResourceSource(_)               | Self::AttractionType(_)            |
+            Self::AttractionRememberUsage         | Self::AttractiveScoreBase(_)       |
+            Self::AttractiveScoreAlcohol(_)       | Self::AttractiveScoreCulture(_)    |
+            Self::AttractiveScoreReligion(_)      | Self::AttractiveScoreSport(_)      |
+            Self::AttractiveFactorNature(_)       | Self::AttractiveFactorNatureAdd(_) |
+            Self::AttractiveFactorPollution(_)    | Self::AttractiveFactorPollutionAdd(_) |
+            Self::AttractiveFactorSight(_)        | Self::AttractiveFactorSightAdd(_)  |
+            Self::AttractiveFactorWater(_)        | Self::AttractiveFactorWaterAdd(_)  |
+            Self::PollutionHigh                   | Self::PollutionMedium              |
+            Self::PollutionSmall                                                       => G::Economy,
+
+            Self::Storage(_)                      | Self::StorageSpecial(_)            |
+            Self::StorageFuel(_)                  | Self::StorageExport(_)             |
+            Self::StorageImport(_)                | Self::StorageImportCarplant(_)     |
+            Self::StorageExportSpecial(_)         | Self::StorageImportSpecial(_)      |
+            Self::StorageDemandBasic(_)           | Self::StorageDemandMedium(_)       |
+            Self::StorageDemandMediumAdvanced(_)  | Self::StorageDemandAdvanced(_)     |
+            Self::StorageDemandHotel(_)           | Self::StoragePackFrom(_)           |
+            Self::StorageUnpackTo(_)              | Self::StorageLivingAuto(_)         |
+            Self::VehicleLoadingFactor(_)         | Self::VehicleUnloadingFactor(_)    => G::Storage,
+
+            Self::RoadNotFlip                     | Self::RoadElectric                 |
+            Self::RoadForkliftPass                | Self::VehicleCannotSelect          |
+            Self::LongTrains                      | Self::WorkingVehiclesNeeded(_)     |
+            Self::VehicleStation(_)               | Self::VehicleStationNotBlock       |
+            Self::VehicleStationDetourPoint(_)    | Self::VehicleStationDetourPid(_)   |
+            Self::VehicleParking(_)               | Self::VehicleParkingDetourPoint(_) |
+            Self::VehicleParkingDetourPid(_)      | Self::VehicleParkingPersonal(_)    |
+            Self::AirplaneStation(_)              | Self::HeliportStation(_)           |
+            Self::ShipStation(_)                  | Self::HeliportArea(_)              |
+            Self::HarborTerrainFrom(_)            | Self::HarborWaterFrom(_)           |
+            Self::HarborExtendWhenBuilding(_)                                          => G::Stations,
+
+            Self::Connection2Points(_)            | Self::Connection1Point(_)          |
+            Self::OffsetConnection(_)             | Self::ConnectionRailDeadend        |
+            Self::ConnectionsSpace(_)             | Self::ConnectionsRoadDeadSquare(_) |
+            Self::ConnectionsAirportDeadSquare(_) | Self::ConnectionsPedestrianDeadSquare(_) |
+            Self::ConnectionsWaterDeadSquare(_)                                        => G::Connections,
+
+            Self::Particle(_)                     | Self::ParticleReactor(_)           |
+            Self::ParticleSnowRemove(_)           | Self::TextCaption(_)               |
+            Self::WorkerRenderingArea(_)          | Self::ResourceVisualization(_)     |
+            Self::ResourceIncreasePoint(_)        | Self::ResourceIncreaseConvPoint(_) |
+            Self::ResourceFillingPoint(_)         | Self::ResourceFillingConvPoint(_)  |
+            Self::WorkingSfx(_)                   | Self::WorkingSfxDistance(_)        |
+            Self::AnimationFps(_)                 | Self::AnimationMesh(_)             |
+            Self::AnimationMeshWorkshop(_)        | Self::UndergroundMesh(_)           => G::Visuals,
+
+            Self::CostWork(_)                     | Self::CostWorkBuildingNode(_)      |
+            Self::CostWorkBuildingKeyword(_)      | Self::CostWorkBuildingAll          |
+            Self::CostResource(_)                 | Self::CostResourceAuto(_)          |
+            Self::CostWorkVehicleStation(_)       | Self::CostWorkVehicleStationNode(_) => G::Costs,
+
+            Self::Schema(t) => t.def.group.unwrap_or(G::Economy),
+        }
+    }
+}
+
+
 impl<'t> super::IniToken for Token<'t> {
+    const PREFIX: &'static str = "$";
+
     fn serialize<W: std::io::Write>(&self, wr: W) -> Result<(), std::io::Error> {
         self.serialize_token(wr)
     }
@@ -464,7 +597,7 @@ impl BuildingSubtype {
 }
 
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum StorageCargoType {
     Passanger,
     Cement,
@@ -609,7 +742,7 @@ impl ConstructionAutoCost {
 }
 
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum ResourceType {
     Alcohol,
     Alumina,
@@ -705,6 +838,7 @@ impl ResourceType {
 #[derive(Clone, Copy)]
 pub enum Connection2PType {
     AirRoad,
+    Airplane,
     Pedestrian,
     PedestrianNotPick,
     Road,
@@ -712,6 +846,7 @@ pub enum Connection2PType {
     RoadBorder,
     RoadIn,
     RoadOut,
+    RoadHeight,
     Rail,
     RailAllowpass,
     RailBorder,
@@ -737,6 +872,7 @@ pub enum Connection2PType {
 
 impl Connection2PType {
     const CONN_AIRROAD:        &'static str = "AIRROAD";
+    const CONN_AIRPLANE:       &'static str = "AIRPLANE";
     const CONN_PED:            &'static str = "PEDESTRIAN";
     const CONN_PED_NOTPICK:    &'static str = "PEDESTRIAN_NOTPICK";
     const CONN_ROAD:           &'static str = "ROAD";
@@ -744,6 +880,7 @@ impl Connection2PType {
     const CONN_ROAD_BORDER:    &'static str = "ROAD_BORDER";
     const CONN_ROAD_IN:        &'static str = "ROAD_INPUT";
     const CONN_ROAD_OUT:       &'static str = "ROAD_OUTPUT";
+    const CONN_ROAD_HEIGHT:    &'static str = "ROAD_HEIGHT";
     const CONN_RAIL:           &'static str = "RAIL";
     const CONN_RAIL_ALLOWPASS: &'static str = "RAIL_ALLOWPASS";
     const CONN_RAIL_BORDER:    &'static str = "RAIL_BORDER";
@@ -771,6 +908,7 @@ impl Connection2PType {
 #[derive(Clone, Copy)] 
 pub enum Connection1PType {
     RoadDead,
+    RailDead,
     PedestrianDead,
     WaterDead,
     AirportDead,
@@ -780,6 +918,7 @@ pub enum Connection1PType {
 
 impl Connection1PType {
     const ROAD_DEAD:       &'static str = "ROAD_DEAD";
+    const RAIL_DEAD:       &'static str = "RAIL_DEAD";
     const PEDESTRIAN_DEAD: &'static str = "PEDESTRIAN_DEAD";
     const WATER_DEAD:      &'static str = "WATER_DEAD";
     const AIRPORT_DEAD:    &'static str = "AIRPORT_DEAD";
@@ -29,16 +29,84 @@ use crate::ini::common::{ParseSlice,
                          CostKeywordParam,
                          RX_REMAINDER, 
                          chop_param, 
-                         parse_param,
+                         parse_literal,
                          parse_tokens_with,
                          parse_tokens_strict_with,
                         };
-
+use crate::ini::schema;
+use crate::cfg::APP_SETTINGS;
+
+
+// Literal parameter types, as they are named in the token schema: (name, kind, values)
+pub(crate) const LITERALS: &[(&str, &str, &[&str])] = &[
+    ("building-type",          "building type",           BuildingType::NAMES),
+    ("building-subtype",       "building subtype",        BuildingSubtype::NAMES),
+    ("resource-source",        "resource-source type",    ResourceSourceType::NAMES),
+    ("airplane-station",       "airplane station type",   AirplaneStationType::NAMES),
+    ("attraction",             "attraction type",         AttractionType::NAMES),
+    ("connection-2p",          "2-point connection type", Connection2PType::NAMES),
+    ("connection-1p",          "1-point connection type", Connection1PType::NAMES),
+    ("storage-cargo",          "storage cargo type",      StorageCargoType::NAMES),
+    ("particle",               "particle type",           ParticleType::NAMES),
+    ("construction-phase",     "construction phase",      ConstructionPhase::NAMES),
+    ("construction-auto-cost", "construction auto cost",  ConstructionAutoCost::NAMES),
+    ("resource",               "resource type",           ResourceType::NAMES),
+];
 
 
 impl<'a> Token<'a> {
 
+    pub(super) const NAMES: &'static [&'static str] = &[
+        Self::NAME_STR, Self::NAME, Self::BUILDING_TYPE, Self::BUILDING_SUBTYPE, Self::HEATING_ENABLE,
+        Self::HEATING_DISABLE, Self::CIVIL_BUILDING, Self::MONUMENT_TRESPASS,
+        Self::MONUMENT_ELE_CONSUMPTION_ADD, Self::QUALITY_OF_LIVING, Self::WORKERS_NEEDED,
+        Self::PROFESSORS_NEEDED, Self::CITIZEN_ABLE_SERVE, Self::CONSUMPTION,
+        Self::CONSUMPTION_PER_SEC, Self::PRODUCTION, Self::PRODUCTION_SUN, Self::PRODUCTION_WIND,
+        Self::SEASONAL_TEMP_MIN, Self::SEASONAL_TEMP_MAX, Self::ELE_CONSUM_WORKER_FACTOR_BASE,
+        Self::ELE_CONSUM_WORKER_FACTOR_NIGHT, Self::ELE_CONSUM_SERVE_FACTOR_BASE,
+        Self::ELE_CONSUM_SERVE_FACTOR_NIGHT, Self::ELE_CONSUM_CARGO_LOAD_FACTOR,
+        Self::ELE_CONSUM_CARGO_UNLOAD_FACTOR, Self::NO_ELE_WORK_FACTOR_BASE,
+        Self::NO_ELE_WORK_FACTOR_NIGHT, Self::NO_HEAT_WORK_FACTOR, Self::ENGINE_SPEED,
+        Self::CABLEWAY_HEAVY, Self::CABLEWAY_LIGHT, Self::RESOURCE_SOURCE, Self::STORAGE,
+        Self::STORAGE_SPECIAL, Self::STORAGE_FUEL, Self::STORAGE_EXPORT, Self::STORAGE_IMPORT,
+        Self::STORAGE_IMPORT_CARPLANT, Self::STORAGE_EXPORT_SPECIAL, Self::STORAGE_IMPORT_SPECIAL,
+        Self::STORAGE_DEMAND_BASIC, Self::STORAGE_DEMAND_MEDIUM, Self::STORAGE_DEMAND_MEDIUMADVANCED,
+        Self::STORAGE_DEMAND_ADVANCED, Self::STORAGE_DEMAND_HOTEL, Self::STORAGE_PACK_FROM,
+        Self::STORAGE_UNPACK_TO, Self::STORAGE_LIVING_AUTO, Self::VEHICLE_LOADING_FACTOR,
+        Self::VEHICLE_UNLOADING_FACTOR, Self::ROAD_VEHICLE_NOT_FLIP, Self::ROAD_VEHICLE_ELECTRIC,
+        Self::ROAD_VEHICLE_FORKLIFT_PASS, Self::VEHICLE_CANNOT_SELECT, Self::LONG_TRAINS,
+        Self::WORKING_VEHICLES_NEEDED, Self::VEHICLE_STATION, Self::VEHICLE_STATION_NOT_BLOCK,
+        Self::VEHICLE_STATION_DETOUR_POINT, Self::VEHICLE_STATION_DETOUR_PID, Self::VEHICLE_PARKING,
+        Self::VEHICLE_PARKING_DETOUR_POINT, Self::VEHICLE_PARKING_DETOUR_PID,
+        Self::VEHICLE_PARKING_PERSONAL, Self::AIRPLANE_STATION, Self::HELIPORT_STATION,
+        Self::SHIP_STATION, Self::HELIPORT_AREA, Self::HARBOR_OVER_TERRAIN_FROM,
+        Self::HARBOR_OVER_WATER_FROM, Self::HARBOR_EXTEND_WHEN_BULDING, Self::CONNECTION,
+        Self::CONNECTIONS_SPACE, Self::CONNECTIONS_ROAD_DEAD_SQUARE,
+        Self::CONNECTIONS_AIRPORT_DEAD_SQUARE, Self::CONNECTIONS_PEDESTRIAN_DEAD_SQUARE,
+        Self::CONNECTIONS_WATER_DEAD_SQUARE, Self::OFFSET_CONNECTION_XYZW, Self::ATTRACTION_TYPE,
+        Self::ATTRACTION_REMEMBER_USAGE, Self::ATTRACTIVE_SCORE_BASE, Self::ATTRACTIVE_SCORE_ALCOHOL,
+        Self::ATTRACTIVE_SCORE_CULTURE, Self::ATTRACTIVE_SCORE_RELIGION, Self::ATTRACTIVE_SCORE_SPORT,
+        Self::ATTRACTIVE_FACTOR_NATURE, Self::ATTRACTIVE_FACTOR_NATURE_ADD,
+        Self::ATTRACTIVE_FACTOR_POLLUTION, Self::ATTRACTIVE_FACTOR_POLLUTION_ADD,
+        Self::ATTRACTIVE_FACTOR_SIGHT, Self::ATTRACTIVE_FACTOR_SIGHT_ADD,
+        Self::ATTRACTIVE_FACTOR_WATER, Self::ATTRACTIVE_FACTOR_WATER_ADD, Self::POLLUTION_HIGH,
+        Self::POLLUTION_MEDIUM, Self::POLLUTION_SMALL, Self::PARTICLE, Self::PARTICLE_REACTOR,
+        Self::PARTICLE_SNOW_REMOVE, Self::TEXT_CAPTION, Self::WORKER_RENDERING_AREA,
+        Self::RESOURCE_VISUALIZATION, Self::RESOURCE_INCREASE_POINT,
+        Self::RESOURCE_INCREASE_CONV_POINT, Self::RESOURCE_FILLING_POINT,
+        Self::RESOURCE_FILLING_CONV_POINT, Self::WORKING_SFX, Self::WORKING_SFX_DISTANCE,
+        Self::ANIMATION_FPS, Self::ANIMATION_MESH, Self::ANIMATION_MESH_WORKSHOP,
+        Self::UNDERGROUND_MESH, Self::COST_WORK, Self::COST_WORK_BUILDING_NODE,
+        Self::COST_WORK_BUILDING_KEYWORD, Self::COST_WORK_BUILDING_ALL, Self::COST_RESOURCE,
+        Self::COST_RESOURCE_AUTO, Self::COST_WORK_VEHICLE_STATION,
+        Self::COST_WORK_VEHICLE_STATION_NODE
+    ];
+
     fn parse(src: &'a str) -> ParseResult<Self> {
+        schema::parse_declared(Self::parse_builtin(src), &APP_SETTINGS.schema.building, src, Self::Schema)
+    }
+
+    pub(crate) fn parse_builtin(src: &'a str) -> ParseResult<Self> {
         lazy_static! {
             static ref RX_TYPE: Regex = Regex::new(concatcp!(
                 r"(?s)^(", 
@@ -51,7 +119,7 @@ impl<'a> Token<'a> {
                 r"[A-Z_]+)($|\s*(.*))")).unwrap();
         }
     
-        let (t_type, rest) = chop_param(Some(src), &RX_TYPE).map_err(|e| format!("Cannot parse token type: {}", e))?;
+        let (t_type, rest) = chop_param(Some(src), &RX_TYPE).map_err(|e| e.context("Cannot parse token type"))?;
         macro_rules! parse {
             ($id:ident, $t:ty) => {
                 <$t>::parse(rest).map(|(p, rest)| (Self::$id(p), rest))
@@ -72,6 +140,7 @@ impl<'a> Token<'a> {
             Self::HEATING_DISABLE                => parse!(HeatDisable),
             Self::CIVIL_BUILDING                 => parse!(CivilBuilding),
             Self::MONUMENT_TRESPASS              => parse!(MonumentTrespass),
+            Self::MONUMENT_ELE_CONSUMPTION_ADD   => parse!(MonumentEleConsumptionAdd,   f32),
             Self::QUALITY_OF_LIVING              => parse!(QualityOfLiving,             f32),
 
             Self::WORKERS_NEEDED                 => parse!(WorkersNeeded,               u32),
@@ -110,6 +179,7 @@ impl<'a> Token<'a> {
             Self::STORAGE_EXPORT_SPECIAL         => parse!(StorageExportSpecial,        (StorageCargoType, f32, ResourceType)),
             Self::STORAGE_IMPORT_SPECIAL         => parse!(StorageImportSpecial,        (StorageCargoType, f32, ResourceType)),
             Self::STORAGE_DEMAND_BASIC           => parse!(StorageDemandBasic,          (StorageCargoType, f32)),
+            Self::STORAGE_DEMAND_MEDIUM          => parse!(StorageDemandMedium,         (StorageCargoType, f32)),
             Self::STORAGE_DEMAND_MEDIUMADVANCED  => parse!(StorageDemandMediumAdvanced, (StorageCargoType, f32)),
             Self::STORAGE_DEMAND_ADVANCED        => parse!(StorageDemandAdvanced,       (StorageCargoType, f32)),
             Self::STORAGE_DEMAND_HOTEL           => parse!(StorageDemandHotel,          (StorageCargoType, f32)),
@@ -122,6 +192,7 @@ impl<'a> Token<'a> {
             
             Self::ROAD_VEHICLE_NOT_FLIP          => parse!(RoadNotFlip),
             Self::ROAD_VEHICLE_ELECTRIC          => parse!(RoadElectric),
+            Self::ROAD_VEHICLE_FORKLIFT_PASS     => parse!(RoadForkliftPass),
             Self::VEHICLE_CANNOT_SELECT          => parse!(VehicleCannotSelect),
             Self::LONG_TRAINS                    => parse!(LongTrains),
 
@@ -149,6 +220,7 @@ impl<'a> Token<'a> {
             Self::CONNECTIONS_SPACE                => parse!(ConnectionsSpace,             Rect),
             Self::CONNECTIONS_ROAD_DEAD_SQUARE     => parse!(ConnectionsRoadDeadSquare,    Rect),
             Self::CONNECTIONS_AIRPORT_DEAD_SQUARE  => parse!(ConnectionsAirportDeadSquare, Rect),
+            Self::CONNECTIONS_PEDESTRIAN_DEAD_SQUARE => parse!(ConnectionsPedestrianDeadSquare, Rect),
             Self::CONNECTIONS_WATER_DEAD_SQUARE    => parse!(ConnectionsWaterDeadSquare,   (f32, Rect)),
             Self::OFFSET_CONNECTION_XYZW           => parse!(OffsetConnection,             (u32, Point3f)),
 
@@ -184,8 +256,10 @@ impl<'a> Token<'a> {
             Self::RESOURCE_FILLING_POINT           => parse!(ResourceFillingPoint,        Point3f),
             Self::RESOURCE_FILLING_CONV_POINT      => parse!(ResourceFillingConvPoint,    (Point3f, Point3f)),
             Self::WORKING_SFX                      => parse!(WorkingSfx,                  IdStringParam),
+            Self::WORKING_SFX_DISTANCE             => parse!(WorkingSfxDistance,          f32),
             Self::ANIMATION_FPS                    => parse!(AnimationFps,                f32),
             Self::ANIMATION_MESH                   => parse!(AnimationMesh,               (IdStringParam, IdStringParam)),
+            Self::ANIMATION_MESH_WORKSHOP          => parse!(AnimationMeshWorkshop,       (IdStringParam, IdStringParam)),
             Self::UNDERGROUND_MESH                 => parse!(UndergroundMesh,             (IdStringParam, IdStringParam)),
 
             Self::COST_WORK                        => parse!(CostWork,                    (ConstructionPhase, f32)),
@@ -199,7 +273,7 @@ impl<'a> Token<'a> {
             Self::COST_WORK_VEHICLE_STATION        => parse!(CostWorkVehicleStation,      (Point3f, Point3f)),
             Self::COST_WORK_VEHICLE_STATION_NODE   => parse!(CostWorkVehicleStationNode,  IdStringParam),
 
-            _ => Err(format!("Unknown token type: \"${}\"", t_type))
+            _ => Err(ParseError::unknown("token type", t_type, Self::NAMES))
         }
     }
 
@@ -207,9 +281,14 @@ impl<'a> Token<'a> {
     fn parse_connection(src: Option<&'a str>) -> ParseResult<Token<'a>> {
         lazy_static! {
             static ref RX_TYPE: Regex = Regex::new(r"(?s)^([A-Z_]+)(\s*(.*))").unwrap();
+            static ref NAMES: Vec<&'static str> = Connection2PType::NAMES.iter()
+                .chain(Connection1PType::NAMES.iter())
+                .chain(std::iter::once(&Token::CONNECTION_RAIL_DEADEND))
+                .copied()
+                .collect();
         }
 
-        let (con_type, rest) = chop_param(src, &RX_TYPE).map_err(|e| format!("Cannot parse connection type: {}", e))?;
+        let (con_type, rest) = chop_param(src, &RX_TYPE).map_err(|e| e.context("Cannot parse connection type"))?;
 
         if let Some(tag) = Connection2PType::from_str(con_type) {
             <(Point3f, Point3f)>::parse(rest).map(|((p1, p2), rest)| (Self::Connection2Points((tag, p1, p2)), rest))
@@ -218,7 +297,7 @@ impl<'a> Token<'a> {
         } else { 
             match con_type {
                 Self::CONNECTION_RAIL_DEADEND => Ok((Self::ConnectionRailDeadend, rest)),
-                _ => Err(format!("Unknown connection type: {}", con_type))
+                _ => Err(ParseError::unknown("connection type", con_type, &NAMES))
             }
         }
     }
@@ -226,7 +305,27 @@ impl<'a> Token<'a> {
 
 
 impl BuildingType {
-    fn from_str(src: &str) -> Option<Self> {
+    pub const NAMES: &'static [&'static str] = &[
+        Self::TYPE_AIRPLANE_GATE, Self::TYPE_AIRPLANE_PARKING, Self::TYPE_AIRPLANE_TOWER,
+        Self::TYPE_ATTRACTION, Self::TYPE_BROADCAST, Self::TYPE_CAR_DEALER, Self::TYPE_CARGO_STATION,
+        Self::TYPE_CHURCH, Self::TYPE_CITYHALL, Self::TYPE_CONSTRUCTION_OFFICE,
+        Self::TYPE_CONSTRUCTION_OFFICE_RAIL, Self::TYPE_CONTAINER_FACILITY, Self::TYPE_COOLING_TOWER,
+        Self::TYPE_CUSTOMHOUSE, Self::TYPE_DISTRIBUTION_OFFICE, Self::TYPE_ELETRIC_EXPORT,
+        Self::TYPE_ELETRIC_IMPORT, Self::TYPE_ENGINE, Self::TYPE_FACTORY, Self::TYPE_FARM,
+        Self::TYPE_FIELD, Self::TYPE_FIRESTATION, Self::TYPE_FORKLIFT_GARAGE,
+        Self::TYPE_GARBAGE_OFFICE, Self::TYPE_GAS_STATION, Self::TYPE_HEATING_ENDSTATION,
+        Self::TYPE_HEATING_PLANT, Self::TYPE_HEATING_SWITCH, Self::TYPE_HOSPITAL, Self::TYPE_HOTEL,
+        Self::TYPE_KINDERGARTEN, Self::TYPE_KINO, Self::TYPE_LIVING, Self::TYPE_MINE_BAUXITE,
+        Self::TYPE_MINE_COAL, Self::TYPE_MINE_GRAVEL, Self::TYPE_MINE_IRON, Self::TYPE_MINE_OIL,
+        Self::TYPE_MINE_URANIUM, Self::TYPE_MINE_WOOD, Self::TYPE_MONUMENT, Self::TYPE_PARKING,
+        Self::TYPE_PEDESTRIAN_BRIDGE, Self::TYPE_POLICE_STATION, Self::TYPE_PASSANGER_STATION,
+        Self::TYPE_POLLUTION_METER, Self::TYPE_POWERPLANT, Self::TYPE_PRODUCTION_LINE, Self::TYPE_PUB,
+        Self::TYPE_RAIL_TRAFO, Self::TYPE_RAILDEPO, Self::TYPE_ROADDEPO, Self::TYPE_SCHOOL,
+        Self::TYPE_SHIP_DOCK, Self::TYPE_SHOP, Self::TYPE_SPORT, Self::TYPE_STORAGE,
+        Self::TYPE_SUBSTATION, Self::TYPE_TRANSFORMATOR, Self::TYPE_UNIVERSITY
+    ];
+
+    pub fn from_str(src: &str) -> Option<Self> {
         match src {
             Self::TYPE_AIRPLANE_GATE            => Some(Self::AirplaneGate),
             Self::TYPE_AIRPLANE_PARKING         => Some(Self::AirplaneParking),
@@ -299,13 +398,20 @@ impl ParseSlice<'_> for BuildingType {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([A-Z_]+)", RX_REMAINDER)).unwrap();
         }
 
-        parse_param(src, &RX, |s| Self::from_str(s).ok_or(format!("Unknown building type '{}'", s)))
+        parse_literal(src, &RX, "building type", Self::NAMES, Self::from_str)
     }
 }
 
 
 impl BuildingSubtype {
-    fn from_str(src: &str) -> Option<Self> {
+    pub(super) const NAMES: &'static [&'static str] = &[
+        Self::SUBTYPE_AIRCUSTOM, Self::SUBTYPE_AIRPLANE, Self::SUBTYPE_CABLEWAY, Self::SUBTYPE_HOSTEL,
+        Self::SUBTYPE_MEDICAL, Self::SUBTYPE_RADIO, Self::SUBTYPE_RAIL, Self::SUBTYPE_RESTAURANT,
+        Self::SUBTYPE_ROAD, Self::SUBTYPE_SHIP, Self::SUBTYPE_SOVIET, Self::SUBTYPE_SPACE_FOR_VEHICLES,
+        Self::SUBTYPE_TECHNICAL, Self::SUBTYPE_TELEVISION, Self::SUBTYPE_TROLLEYBUS
+    ];
+
+    pub(super) fn from_str(src: &str) -> Option<Self> {
         match src {
             Self::SUBTYPE_AIRCUSTOM          => Some(Self::Aircustom),
             Self::SUBTYPE_AIRPLANE           => Some(Self::Airplane),
@@ -334,15 +440,28 @@ impl ParseSlice<'_> for BuildingSubtype {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([A-Z_]+)", RX_REMAINDER)).unwrap();
         }
 
-        parse_param(src, &RX, |s| Self::from_str(s).ok_or(format!("Unknown building subtype '{}'", s)))
+        parse_literal(src, &RX, "building subtype", Self::NAMES, Self::from_str)
     }
 }
 
 
 impl Connection2PType {
-    fn from_str(src: &str) -> Option<Self> {
+    pub(super) const NAMES: &'static [&'static str] = &[
+        Self::CONN_AIRROAD, Self::CONN_AIRPLANE, Self::CONN_PED, Self::CONN_PED_NOTPICK,
+        Self::CONN_ROAD, Self::CONN_ROAD_ALLOWPASS, Self::CONN_ROAD_BORDER, Self::CONN_ROAD_IN,
+        Self::CONN_ROAD_OUT, Self::CONN_ROAD_HEIGHT, Self::CONN_RAIL, Self::CONN_RAIL_ALLOWPASS,
+        Self::CONN_RAIL_BORDER, Self::CONN_RAIL_HEIGHT, Self::CONN_HEATING_BIG,
+        Self::CONN_HEATING_SMALL, Self::CONN_STEAM_IN, Self::CONN_STEAM_OUT, Self::CONN_PIPE_IN,
+        Self::CONN_PIPE_OUT, Self::CONN_BULK_IN, Self::CONN_BULK_OUT, Self::CONN_CABLEWAY,
+        Self::CONN_FACTORY, Self::CONN_CONVEYOR_IN, Self::CONN_CONVEYOR_OUT, Self::CONN_ELECTRIC_H_IN,
+        Self::CONN_ELECTRIC_H_OUT, Self::CONN_ELECTRIC_L_IN, Self::CONN_ELECTRIC_L_OUT,
+        Self::CONN_FENCE
+    ];
+
+    pub(super) fn from_str(src: &str) -> Option<Self> {
         match src {
             Self::CONN_AIRROAD        => Some(Self::AirRoad),
+            Self::CONN_AIRPLANE       => Some(Self::Airplane),
             Self::CONN_PED            => Some(Self::Pedestrian),
             Self::CONN_PED_NOTPICK    => Some(Self::PedestrianNotPick),
             Self::CONN_ROAD           => Some(Self::Road),
@@ -350,6 +469,7 @@ impl Connection2PType {
             Self::CONN_ROAD_BORDER    => Some(Self::RoadBorder),
             Self::CONN_ROAD_IN        => Some(Self::RoadIn),
             Self::CONN_ROAD_OUT       => Some(Self::RoadOut),
+            Self::CONN_ROAD_HEIGHT    => Some(Self::RoadHeight),
             Self::CONN_RAIL           => Some(Self::Rail),
             Self::CONN_RAIL_ALLOWPASS => Some(Self::RailAllowpass),
             Self::CONN_RAIL_BORDER    => Some(Self::RailBorder),
@@ -383,15 +503,21 @@ impl ParseSlice<'_> for Connection2PType {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([A-Z_]+)", RX_REMAINDER)).unwrap();
         }
 
-        parse_param(src, &RX, |s| Self::from_str(s).ok_or(format!("Unknown 2-point connection type '{}'", s)))
+        parse_literal(src, &RX, "2-point connection type", Self::NAMES, Self::from_str)
     }
 }
 
 
 impl Connection1PType {
-    fn from_str(src: &str) -> Option<Self> {
+    pub(super) const NAMES: &'static [&'static str] = &[
+        Self::ROAD_DEAD, Self::RAIL_DEAD, Self::PEDESTRIAN_DEAD, Self::WATER_DEAD, Self::AIRPORT_DEAD,
+        Self::ADVANCED_POINT
+    ];
+
+    pub(super) fn from_str(src: &str) -> Option<Self> {
         match src {
             Self::ROAD_DEAD       => Some(Self::RoadDead),
+            Self::RAIL_DEAD       => Some(Self::RailDead),
             Self::PEDESTRIAN_DEAD => Some(Self::PedestrianDead),
             Self::WATER_DEAD      => Some(Self::WaterDead),
             Self::AIRPORT_DEAD    => Some(Self::AirportDead),
@@ -407,13 +533,19 @@ impl ParseSlice<'_> for Connection1PType {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([A-Z_]+)", RX_REMAINDER)).unwrap();
         }
 
-        parse_param(src, &RX, |s| Connection1PType::from_str(s).ok_or(format!("Unknown 1-point connection type '{}'", s)))
+        parse_literal(src, &RX, "1-point connection type", Self::NAMES, Self::from_str)
     }
 }
 
 
 impl StorageCargoType {
-    fn from_str(src: &str) -> Option<Self> {
+    pub(super) const NAMES: &'static [&'static str] = &[
+        Self::PASSANGER, Self::CEMENT, Self::COVERED, Self::GRAVEL, Self::OIL, Self::OPEN,
+        Self::COOLER, Self::CONCRETE, Self::LIVESTOCK, Self::GENERAL, Self::VEHICLES, Self::NUCLEAR1,
+        Self::NUCLEAR2
+    ];
+
+    pub(super) fn from_str(src: &str) -> Option<Self> {
         match src {
             Self::PASSANGER => Some(Self::Passanger),
             Self::CEMENT    => Some(Self::Cement),
@@ -439,13 +571,20 @@ impl ParseSlice<'_> for StorageCargoType {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([0-9A-Z_]+)", RX_REMAINDER)).unwrap();
         }
 
-        parse_param(src, &RX, |s| StorageCargoType::from_str(s).ok_or(format!("Unknown storage cargo type '{}'", s)))
+        parse_literal(src, &RX, "storage cargo type", Self::NAMES, Self::from_str)
     }
 }
 
 
 impl ParticleType {
-    fn from_str(src: &str) -> Option<Self> {
+    pub(super) const NAMES: &'static [&'static str] = &[
+        Self::RESIDENTIAL_HEATING, Self::FACTORY_BIG_BLACK, Self::FACTORY_MEDIUM_BLACK,
+        Self::FACTORY_SMALL_BLACK, Self::FACTORY_BIG_GRAY, Self::FACTORY_MEDIUM_GRAY,
+        Self::FACTORY_SMALL_GRAY, Self::FACTORY_BIG_WHITE, Self::FACTORY_MEDIUM_WHITE,
+        Self::FACTORY_SMALL_WHITE, Self::FOUNTAIN_1, Self::FOUNTAIN_2, Self::FOUNTAIN_3
+    ];
+
+    pub(super) fn from_str(src: &str) -> Option<Self> {
         match src {
             Self::RESIDENTIAL_HEATING  => Some(Self::ResidentialHeating),
             Self::FACTORY_BIG_BLACK    => Some(Self::BigBlack),
@@ -471,13 +610,20 @@ impl ParseSlice<'_> for ParticleType {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([0-9a-z_]+)", RX_REMAINDER)).unwrap();
         }
 
-        parse_param(src, &RX, |s| ParticleType::from_str(s).ok_or(format!("Unknown particle type '{}'", s)))
+        parse_literal(src, &RX, "particle type", Self::NAMES, Self::from_str)
     }
 }
 
 
 impl ConstructionPhase {
-    fn from_str(src: &str) -> Option<Self> {
+    pub(super) const NAMES: &'static [&'static str] = &[
+        Self::ASPHALT_LAYING, Self::ASPHALT_ROLLING, Self::BOARDS_LAYING, Self::BRICKS_LAYING,
+        Self::BRIDGE_BUILDING, Self::GRAVEL_LAYING, Self::GROUNDWORKS, Self::INTERIOR_WORKS,
+        Self::PANELS_LAYING, Self::RAILWAY_LAYING, Self::ROOFTOP_BUILDING, Self::SKELETON_CASTING,
+        Self::STEEL_LAYING, Self::TUNNELING, Self::WIRE_LAYING
+    ];
+
+    pub(super) fn from_str(src: &str) -> Option<Self> {
         match src {
             Self::ASPHALT_LAYING   => Some(Self::AsphaltLaying),
             Self::ASPHALT_ROLLING  => Some(Self::AsphaltRolling),
@@ -505,14 +651,20 @@ impl ParseSlice<'_> for ConstructionPhase {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([A-Z_]+)", RX_REMAINDER)).unwrap();
         }
 
-        parse_param(src, &RX, |s| ConstructionPhase::from_str(s).ok_or(format!("Unknown construction phase '{}'", s)))
+        parse_literal(src, &RX, "construction phase", Self::NAMES, Self::from_str)
     }
 }
 
 
 
 impl ConstructionAutoCost {
-    fn from_str(src: &str) -> Option<Self> {
+    pub(super) const NAMES: &'static [&'static str] = &[
+        Self::GROUND, Self::GROUND_ASPHALT, Self::WALL_CONCRETE, Self::WALL_PANELS, Self::WALL_BRICK,
+        Self::WALL_STEEL, Self::WALL_WOOD, Self::TECH_STEEL, Self::ELECTRO_STEEL,
+        Self::TECH_ELECTRO_STEEL, Self::ROOF_WOOD_BRICK, Self::ROOF_STEEL, Self::ROOF_WOOD_STEEL
+    ];
+
+    pub(super) fn from_str(src: &str) -> Option<Self> {
         match src {
             Self::GROUND             => Some(Self::Ground),
             Self::GROUND_ASPHALT     => Some(Self::GroundAsphalt),
@@ -538,14 +690,25 @@ impl ParseSlice<'_> for ConstructionAutoCost {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([a-z_]+)", RX_REMAINDER)).unwrap();
         }
 
-        parse_param(src, &RX, |s| ConstructionAutoCost::from_str(s).ok_or(format!("Unknown construction auto cost '{}'", s)))
+        parse_literal(src, &RX, "construction auto cost", Self::NAMES, Self::from_str)
     }
 }
 
 
 
 impl ResourceType {
-    fn from_str(src: &str) -> Option<Self> {
+    pub(super) const NAMES: &'static [&'static str] = &[
+        Self::ALCOHOL, Self::ALUMINA, Self::ALUMINIUM, Self::ASPHALT, Self::BAUXITE, Self::BITUMEN,
+        Self::BOARDS, Self::BRICKS, Self::CEMENT, Self::CHEMICALS, Self::CLOTHES, Self::COAL,
+        Self::CONCRETE, Self::CROPS, Self::ELECTRO_COMP, Self::ELECTRICITY, Self::ELECTRONICS,
+        Self::FABRIC, Self::FOOD, Self::FUEL, Self::GRAVEL, Self::HEAT, Self::IRON, Self::LIVESTOCK,
+        Self::MECH_COMP, Self::MEAT, Self::NUCLEAR_FUEL, Self::NUCLEAR_WASTE, Self::OIL, Self::PLASTIC,
+        Self::PREFABS, Self::RAW_BAUXITE, Self::RAW_COAL, Self::RAW_GRAVEL, Self::RAW_IRON,
+        Self::STEEL, Self::UF_6, Self::URANIUM, Self::VEHICLES, Self::WOOD, Self::WORKERS,
+        Self::YELLOWCAKE
+    ];
+
+    pub(super) fn from_str(src: &str) -> Option<Self> {
         match src {
             Self::ALCOHOL       => Some(Self::Alcohol),
             Self::ALUMINA       => Some(Self::Alumina),
@@ -600,13 +763,18 @@ impl ParseSlice<'_> for ResourceType {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([a-z0-9_]+)", RX_REMAINDER)).unwrap();
         }
 
-        parse_param(src, &RX, |s| ResourceType::from_str(s).ok_or(format!("Unknown resource type '{}'", s)))
+        parse_literal(src, &RX, "resource type", Self::NAMES, Self::from_str)
     }
 }
 
 
 impl AirplaneStationType {
-    fn from_str(src: &str) -> Option<Self> {
+    pub(super) const NAMES: &'static [&'static str] = &[
+        Self::AIRPLANE_STATION_30M, Self::AIRPLANE_STATION_40M, Self::AIRPLANE_STATION_50M,
+        Self::AIRPLANE_STATION_75M
+    ];
+
+    pub(super) fn from_str(src: &str) -> Option<Self> {
         match src {
             Self::AIRPLANE_STATION_30M => Some(Self::M30),
             Self::AIRPLANE_STATION_40M => Some(Self::M40),
@@ -623,13 +791,18 @@ impl ParseSlice<'_> for AirplaneStationType {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([0-9]+M)", RX_REMAINDER)).unwrap();
         }
 
-        parse_param(src, &RX, |s| AirplaneStationType::from_str(s).ok_or(format!("Unknown airplane station type '{}'", s)))
+        parse_literal(src, &RX, "airplane station type", Self::NAMES, Self::from_str)
     }
 }
 
 
 impl AttractionType {
-    fn from_str(src: &str) -> Option<Self> {
+    pub(super) const NAMES: &'static [&'static str] = &[
+        Self::ATTRACTION_TYPE_CARUSEL, Self::ATTRACTION_TYPE_GALLERY, Self::ATTRACTION_TYPE_MUSEUM,
+        Self::ATTRACTION_TYPE_SIGHT, Self::ATTRACTION_TYPE_SWIM, Self::ATTRACTION_TYPE_ZOO
+    ];
+
+    pub(super) fn from_str(src: &str) -> Option<Self> {
         match src {
             Self::ATTRACTION_TYPE_CARUSEL => Some(Self::Carousel),
             Self::ATTRACTION_TYPE_GALLERY => Some(Self::Gallery),
@@ -648,13 +821,20 @@ impl ParseSlice<'_> for AttractionType {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([A-Z_]+)", RX_REMAINDER)).unwrap();
         }
         
-        parse_param(src, &RX, |s| AttractionType::from_str(s).ok_or(format!("Unknown attraction type '{}'", s)))
+        parse_literal(src, &RX, "attraction type", Self::NAMES, Self::from_str)
     }
 }
 
 
 impl ResourceSourceType {
-    fn from_str(src: &str) -> Option<Self> {
+    pub(super) const NAMES: &'static [&'static str] = &[
+        Self::RES_SOURCE_ASPHALT, Self::RES_SOURCE_CONCRETE, Self::RES_SOURCE_COVERED,
+        Self::RES_SOURCE_COVERED_ELECTRO, Self::RES_SOURCE_GRAVEL, Self::RES_SOURCE_OPEN,
+        Self::RES_SOURCE_OPEN_BOARDS, Self::RES_SOURCE_OPEN_BRICKS, Self::RES_SOURCE_OPEN_PANELS,
+        Self::RES_SOURCE_WORKERS
+    ];
+
+    pub(super) fn from_str(src: &str) -> Option<Self> {
         match src {
             Self::RES_SOURCE_ASPHALT         => Some(Self::Asphalt),
             Self::RES_SOURCE_CONCRETE        => Some(Self::Concrete),
@@ -678,7 +858,7 @@ impl ParseSlice<'_> for ResourceSourceType {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([A-Z_]+)", RX_REMAINDER)).unwrap();
         }
         
-        parse_param(src, &RX, |s| ResourceSourceType::from_str(s).ok_or(format!("Unknown resource-source type '{}'", s)))
+        parse_literal(src, &RX, "resource-source type", Self::NAMES, Self::from_str)
     }
 }
 
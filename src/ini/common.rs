@@ -1,12 +1,19 @@
 use std::str::FromStr;
 use std::fmt::{Formatter, Error, Display};
+use std::ops::Range;
 
 use lazy_static::lazy_static;
 use regex::Regex;
 use const_format::concatcp;
 
 
-pub type ParseError = String;
+#[derive(Debug)]
+pub struct ParseError {
+    pub message: String,
+    // address and length of the offending part of the source text, see span_in()
+    at: Option<(usize, usize)>,
+    pub suggestion: Option<&'static str>,
+}
 
 pub type ParseResult<'a, T> = Result<(T, Option<&'a str>), ParseError>;
 
@@ -17,6 +24,7 @@ pub struct Point3f {
     pub z: f32
 }
 
+#[derive(Clone)]
 pub struct Rect {
     pub x1: f32,
     pub z1: f32,
@@ -24,13 +32,16 @@ pub struct Rect {
     pub z2: f32
 }
 
+#[derive(Clone)]
 pub enum StrValue<'a> {
     Borrowed(&'a str),
     Owned(String),
 }
 
+#[derive(Clone)]
 pub struct QuotedStringParam<'a>(pub StrValue<'a>);
 
+#[derive(Clone)]
 pub struct IdStringParam<'a>(pub StrValue<'a>);
 
 impl<'a> IdStringParam<'a> {
@@ -60,6 +71,7 @@ impl AsRef<str> for IdStringParam<'_> {
     }
 }
 
+#[derive(Clone)]
 pub struct CostKeywordParam<'a>(pub IdStringParam<'a>);
 impl<'a> CostKeywordParam<'a> {
     pub fn as_str(&'a self) -> &'a str { self.0.as_str() }
@@ -138,7 +150,7 @@ impl ParseSlice<'_> for f32 {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^(-?[0-9]*\.?[0-9]+)", RX_REMAINDER)).unwrap();
         }
 
-        parse_param(src, &RX, |s| f32::from_str(s).map_err(|e| format!("f32 parse failed: {}", e)))
+        parse_number(src, &RX, "a number")
     }
 }
 
@@ -149,7 +161,7 @@ impl ParseSlice<'_> for u8 {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([0-9]+)", RX_REMAINDER)).unwrap();
         }
 
-        parse_param(src, &RX, |s| u8::from_str(s).map_err(|e| format!("u8 parse failed: {}", e)))
+        parse_number(src, &RX, "an integer")
     }
 }
 
@@ -160,7 +172,7 @@ impl ParseSlice<'_> for u32 {
             static ref RX: Regex = Regex::new(concatcp!(r"(?s)^([0-9]+)", RX_REMAINDER)).unwrap();
         }
 
-        parse_param(src, &RX, |s| u32::from_str(s).map_err(|e| format!("u32 parse failed: {}", e)))
+        parse_number(src, &RX, "an integer")
     }
 }
 
@@ -193,14 +205,14 @@ impl<'a> ParseSlice<'a> for CostKeywordParam<'a> {
             static ref RX: Regex = Regex::new(r"^\$(.+)").unwrap();
         }
 
-        let src = src.ok_or(String::from("Cost keyword parse failed: no data"))?;
+        let src = src.ok_or_else(|| ParseError::new("Cost keyword parse failed: no data"))?;
         match RX.captures(src) {
             Some(caps) => {
                 let rest = caps.get(1).map(|x| x.as_str());
                 let (inner, rest) = IdStringParam::parse(rest)?;
                 Ok((CostKeywordParam(inner), rest))
             },
-            None => Err(format!("Cost keyword must start with '$'. Chunk: [{}]", src))
+            None => Err(ParseError::at("Cost keyword must start with '$'", first_word(src)))
         }
     }
 }
@@ -258,7 +270,7 @@ pub const RX_REMAINDER: &str = r"($|\s*(.*))";
 
 
 pub fn chop_param<'a, 'b>(src: Option<&'a str>, rx: &'b Regex) -> ParseResult<'a, &'a str> {
-    let src = src.ok_or(String::from("Chop param failed: not enough data"))?;
+    let src = src.ok_or_else(|| ParseError::new("Not enough parameters"))?;
 
     match rx.captures(src) {
         Some(caps) => {
@@ -267,16 +279,117 @@ pub fn chop_param<'a, 'b>(src: Option<&'a str>, rx: &'b Regex) -> ParseResult<'a
             let rest = caps.get(3).map(|x| x.as_str());
             Ok((t, rest))
         },
-        None => Err(format!("No match in this chunk: [{}]", src))
+        None if src.trim().is_empty() => Err(ParseError::new("Not enough parameters")),
+        None => {
+            let word = first_word(src);
+            Err(ParseError::at(format!("Unexpected value '{}'", word), word))
+        }
     }
 }
 
 pub fn parse_param<'a, T, F: Fn(&'a str) -> Result<T, ParseError>>(src: Option<&'a str>, rx: &Regex, f: F) -> ParseResult<'a, T> {
     let (src, rest) = chop_param(src, rx)?;
-    let v = f(src).map_err(|e| format!("parse_param failed: {}", e))?;
+    let v = f(src)?;
     Ok((v, rest))
 }
 
+// Parses a literal from a fixed set of names. Unknown values (also the ones with wrong case 
+// or characters) are reported together with the closest known name.
+pub fn parse_literal<'a, T, F>(src: Option<&'a str>, rx: &Regex, kind: &str, names: &[&'static str], from_str: F) -> ParseResult<'a, T> 
+where F: Fn(&str) -> Option<T>
+{
+    match chop_param(src, rx) {
+        Ok((s, rest)) => match from_str(s) {
+            Some(v) => Ok((v, rest)),
+            None => Err(ParseError::unknown(kind, s, names))
+        },
+        Err(e) => match src.map(first_word) {
+            Some(word) if !word.is_empty() => Err(ParseError::unknown(kind, word, names)),
+            _ => Err(e)
+        }
+    }
+}
+
+fn parse_number<'a, T>(src: Option<&'a str>, rx: &Regex, what: &str) -> ParseResult<'a, T>
+where T: FromStr,
+      T::Err: Display
+{
+    match chop_param(src, rx) {
+        Ok((s, rest)) => match T::from_str(s) {
+            Ok(v) => Ok((v, rest)),
+            Err(e) => Err(ParseError::at(format!("Cannot parse '{}' as {}: {}", s, what, e), s))
+        },
+        Err(e) => match src.map(first_word) {
+            Some(word) if !word.is_empty() => Err(ParseError::at(format!("Expected {}, found '{}'", what, word), word)),
+            _ => Err(e)
+        }
+    }
+}
+
+fn first_word(src: &str) -> &str {
+    let src = src.trim_start();
+    let end = src.find(char::is_whitespace).unwrap_or(src.len());
+    &src[.. end]
+}
+
+
+impl ParseError {
+    pub fn new<S: Into<String>>(message: S) -> Self {
+        ParseError { message: message.into(), at: None, suggestion: None }
+    }
+
+    // error pointing at a part of the source text
+    pub fn at<S: Into<String>>(message: S, part: &str) -> Self {
+        ParseError { message: message.into(), at: Some((part.as_ptr() as usize, part.len())), suggestion: None }
+    }
+
+    pub fn unknown(kind: &str, value: &str, names: &[&'static str]) -> Self {
+        ParseError { 
+            suggestion: super::diagnostic::suggest(value, names),
+            ..Self::at(format!("Unknown {} '{}'", kind, value), value)
+        }
+    }
+
+    pub fn context(mut self, ctx: &str) -> Self {
+        self.message = format!("{}: {}", ctx, self.message);
+        self
+    }
+
+    // Byte range of the error in the source text, if the error points into it
+    pub fn span_in(&self, src: &str) -> Option<Range<usize>> {
+        let (addr, len) = self.at?;
+        let base = src.as_ptr() as usize;
+        if addr >= base && addr + len <= base + src.len() {
+            Some(addr - base .. addr - base + len)
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.message)?;
+        if let Some(s) = self.suggestion {
+            write!(f, " (did you mean '{}'?)", s)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<String> for ParseError {
+    fn from(message: String) -> Self {
+        Self::new(message)
+    }
+}
+
+// Byte range of a subslice in its source text
+pub fn subslice_range(src: &str, part: &str) -> Range<usize> {
+    let start = part.as_ptr() as usize - src.as_ptr() as usize;
+    assert!(start + part.len() <= src.len());
+    start .. start + part.len()
+}
+
 
 //---------------------------------------------------------
 
@@ -321,7 +434,7 @@ where F: Fn(&'a str) -> ParseResult<T>
             Ok((t_val, rest)) => {
                 match rest {
                     Some(r) if !r.is_empty() => {
-                        errors.push((t_str, format!("Token parsed incomplete. Remaining: {}", r)));
+                        errors.push((t_str, ParseError::at("Token parsed incomplete, unexpected remainder", r.trim_end())));
                     },
                     _ => res.push((t_str, t_val))
                 }
@@ -0,0 +1,218 @@
+use std::fmt::{self, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use super::common::ParseError;
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+
+// Location in a source file: 1-based line and columns (in chars, end is exclusive).
+// Multiline spans end with the first line. The line itself is kept for rendering.
+#[derive(Clone, Debug)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub end_column: usize,
+    pub text: String,
+}
+
+
+// Problem found by validation. `code` is a short kebab-case id (lint rules use their own id).
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub file: Option<PathBuf>,
+    pub span: Option<Span>,
+    pub message: String,
+    pub help: Option<String>,
+}
+
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Error   => "error",
+            Self::Warning => "warning",
+        }
+    }
+}
+
+
+impl Span {
+    pub fn new(src: &str, span: Range<usize>) -> Self {
+        let (line, line_start) = src[.. span.start].match_indices('\n')
+                                                   .fold((1, 0), |(n, _), (i, _)| (n + 1, i + 1));
+        let line_end = src[line_start ..].find(['\r', '\n']).map(|i| line_start + i).unwrap_or(src.len());
+        let text = &src[line_start .. line_end];
+
+        let col_from = span.start - line_start;
+        let col_to = span.end.min(line_end).max(span.start) - line_start;
+        let column = text[.. col_from].chars().count() + 1;
+        let end_column = column + text[col_from .. col_to].chars().count().max(1);
+
+        Span { line, column, end_column, text: text.to_string() }
+    }
+}
+
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: String) -> Self {
+        Diagnostic { code, severity, file: None, span: None, message, help: None }
+    }
+
+    pub fn error(code: &'static str, message: String) -> Self {
+        Self::new(Severity::Error, code, message)
+    }
+
+    pub fn warning(code: &'static str, message: String) -> Self {
+        Self::new(Severity::Warning, code, message)
+    }
+
+    pub fn in_file(mut self, path: &Path) -> Self {
+        self.file = Some(path.to_path_buf());
+        self
+    }
+
+    pub fn at(mut self, src: &str, span: Range<usize>) -> Self {
+        self.span = Some(Span::new(src, span));
+        self
+    }
+
+    pub fn with_help(mut self, help: Option<String>) -> Self {
+        self.help = help;
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let level = format!("{}[{}]", self.severity.name(), self.code);
+        match (&self.file, &self.span) {
+            (Some(path), Some(span)) => write!(f, "{}", render_span(&level, &self.message, path, span, self.help.as_deref())),
+            (file, _) => {
+                writeln!(f, "{}: {}", level, self.message)?;
+                if let Some(path) = file {
+                    writeln!(f, "  --> {}", path.display())?;
+                }
+                if let Some(help) = self.help.as_ref() {
+                    writeln!(f, "   = help: {}", help)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+
+// Renders a message in rustc style:
+//
+// error: Unknown resource type 'steal'
+//   --> HOUSE3/building.ini:12:14
+//    |
+// 12 | $CONSUMPTION steal 5
+//    |              ^^^^^
+//    = help: did you mean 'steel'?
+//
+pub fn render(level: &str, message: &str, path: &Path, src: &str, span: Range<usize>, help: Option<&str>) -> String {
+    render_span(level, message, path, &Span::new(src, span), help)
+}
+
+
+fn render_span(level: &str, message: &str, path: &Path, span: &Span, help: Option<&str>) -> String {
+    let line = span.text.as_str();
+    let padding: String = line.chars().take(span.column - 1).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+    let carets = "^".repeat(span.end_column - span.column);
+
+    let gutter = span.line.to_string().len();
+    let mut result = String::with_capacity(256);
+    writeln!(result, "{}: {}", level, message).unwrap();
+    writeln!(result, "{:w$}--> {}:{}:{}", "", path.display(), span.line, span.column, w = gutter).unwrap();
+    writeln!(result, "{:w$} |", "", w = gutter).unwrap();
+    writeln!(result, "{} | {}", span.line, line).unwrap();
+    writeln!(result, "{:w$} | {}{}", "", padding, carets, w = gutter).unwrap();
+    if let Some(help) = help {
+        writeln!(result, "{:w$} = help: {}", "", help, w = gutter).unwrap();
+    }
+
+    result
+}
+
+
+// Renders a parse error of the token `chunk` (a part of `src`). Errors without a location
+// (missing parameters) point right after the end of the chunk.
+pub fn render_parse_error(path: &Path, src: &str, chunk: &str, err: &ParseError) -> String {
+    let help = err.suggestion.map(|s| format!("did you mean '{}'?", s));
+    render("error", &err.message, path, src, parse_error_span(src, chunk, err), help.as_deref())
+}
+
+
+fn parse_error_span(src: &str, chunk: &str, err: &ParseError) -> Range<usize> {
+    err.span_in(src).unwrap_or_else(|| {
+        let chunk_range = super::common::subslice_range(src, chunk.trim_end());
+        chunk_range.end .. chunk_range.end
+    })
+}
+
+
+pub fn render_parse_errors(path: &Path, src: &str, errors: &[(&str, ParseError)]) -> String {
+    let mut result = String::with_capacity(1024);
+    for (chunk, err) in errors.iter() {
+        result.push_str(&render_parse_error(path, src, chunk, err));
+        result.push('\n');
+    }
+
+    result
+}
+
+
+pub fn parse_diagnostics(path: &Path, src: &str, errors: &[(&str, ParseError)]) -> Vec<Diagnostic> {
+    errors.iter().map(|(chunk, err)| {
+        Diagnostic::error("parse-error", err.message.clone())
+            .in_file(path)
+            .at(src, parse_error_span(src, chunk, err))
+            .with_help(err.suggestion.map(|s| format!("did you mean '{}'?", s)))
+    }).collect()
+}
+
+
+// The closest name (case-insensitive edit distance), if it is close enough
+pub fn suggest<'n>(value: &str, names: &[&'n str]) -> Option<&'n str> {
+    let value = value.to_ascii_uppercase();
+    let max_distance = (value.len() / 3).max(1);
+
+    names.iter()
+         .map(|n| (edit_distance(&value, &n.to_ascii_uppercase()), *n))
+         .filter(|(d, _)| *d <= max_distance)
+         .min_by_key(|(d, _)| *d)
+         .map(|(_, n)| n)
+}
+
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0 ..= b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = if ca == *cb { prev } else { 1 + prev.min(cur).min(row[j]) };
+            prev = cur;
+        }
+    }
+
+    row[b.len()]
+}
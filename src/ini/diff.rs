@@ -0,0 +1,162 @@
+// Comparing two files token by token. Tokens are grouped by kind (the full keyword, including
+// the tag: $CONNECTION_ROAD and $CONNECTION_RAIL are different kinds), the order of tokens does not matter.
+// Within a kind, equal tokens (numbers within the tolerance) are matched first, the remaining
+// ones are paired in file order and reported as changed, the rest as added or removed.
+
+use std::fmt::{self, Display, Formatter};
+
+use serde_json::Value;
+
+use super::json::JsonToken;
+
+
+pub enum Change<'a, T> {
+    Added(&'a T),
+    Removed(&'a T),
+    Changed(&'a T, &'a T, Vec<Delta>),
+}
+
+
+// A changed value in the token's parameters
+pub struct Delta {
+    pub label: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+
+pub struct KindDiff<'a, T> {
+    pub kind: String,
+    pub changes: Vec<Change<'a, T>>,
+}
+
+
+pub fn diff_tokens<'a, 't, T, I>(a: I, b: I, tolerance: f64) -> Vec<KindDiff<'a, T>>
+where T: JsonToken<'t> + 'a,
+      I: Iterator<Item = &'a T>
+{
+    // kinds in the order of their first appearance in a, then in b
+    let mut kinds: Vec<Kind<'a, T>> = Vec::new();
+    let mut push = |t: &'a T, to_b: bool| {
+        let (keyword, params) = t.to_json();
+        let kind = format!("{}{}", T::PREFIX, keyword);
+        let idx = match kinds.iter().position(|(k, _, _)| *k == kind) {
+            Some(i) => i,
+            None => { kinds.push((kind, Vec::new(), Vec::new())); kinds.len() - 1 }
+        };
+
+        let entry = (t, leaves(&params));
+        if to_b { kinds[idx].2.push(entry) } else { kinds[idx].1.push(entry) }
+    };
+
+    a.for_each(|t| push(t, false));
+    b.for_each(|t| push(t, true));
+
+    kinds.into_iter().filter_map(|(kind, mut xs, mut ys)| {
+        xs.retain(|(_, x)| match ys.iter().position(|(_, y)| deltas(x, y, tolerance).is_empty()) {
+            Some(i) => { ys.remove(i); false },
+            None => true
+        });
+
+        let paired = xs.len().min(ys.len());
+        let mut changes: Vec<Change<T>> = Vec::with_capacity(xs.len().max(ys.len()));
+        let mut ys = ys.into_iter();
+        for (i, (tx, x)) in xs.into_iter().enumerate() {
+            if i < paired {
+                let (ty, y) = ys.next().unwrap();
+                changes.push(Change::Changed(tx, ty, deltas(&x, &y, tolerance)));
+            } else {
+                changes.push(Change::Removed(tx));
+            }
+        }
+        changes.extend(ys.map(|(ty, _)| Change::Added(ty)));
+
+        if changes.is_empty() { None } else { Some(KindDiff { kind, changes }) }
+    }).collect()
+}
+
+
+// kind name, tokens in a, tokens in b
+type Kind<'a, T> = (String, Vec<Leaves<'a, T>>, Vec<Leaves<'a, T>>);
+type Leaves<'a, T> = (&'a T, Vec<(String, Value)>);
+
+// Flattens parameters into labelled scalars: "#1.x", "#2", "#1.numstep_x[0]", ...
+fn leaves(params: &[Value]) -> Vec<(String, Value)> {
+    fn walk(label: String, v: &Value, result: &mut Vec<(String, Value)>) {
+        const POINT: [&str; 3] = ["x", "y", "z"];
+        const RECT:  [&str; 4] = ["x1", "z1", "x2", "z2"];
+
+        match v {
+            Value::Array(xs) => for (i, x) in xs.iter().enumerate() {
+                let sub = match xs.len() {
+                    3 => format!("{}.{}", label, POINT[i]),
+                    4 => format!("{}.{}", label, RECT[i]),
+                    _ => format!("{}[{}]", label, i),
+                };
+                walk(sub, x, result);
+            },
+            Value::Object(obj) => for (k, x) in obj.iter() {
+                walk(format!("{}.{}", label, k), x, result);
+            },
+            _ => result.push((label, v.clone()))
+        }
+    }
+
+    let mut result = Vec::with_capacity(params.len() * 3);
+    for (i, p) in params.iter().enumerate() {
+        walk(format!("#{}", i + 1), p, &mut result);
+    }
+
+    result
+}
+
+
+fn deltas(x: &[(String, Value)], y: &[(String, Value)], tolerance: f64) -> Vec<Delta> {
+    let same_shape = x.len() == y.len() && x.iter().zip(y.iter()).all(|((a, _), (b, _))| a == b);
+    if !same_shape {
+        let whole = |vs: &[(String, Value)]| Value::Array(vs.iter().map(|(_, v)| v.clone()).collect());
+        return vec![Delta { label: "params".to_string(), old: whole(x), new: whole(y) }];
+    }
+
+    x.iter().zip(y.iter()).filter_map(|((label, a), (_, b))| {
+        let equal = match (a.as_f64(), b.as_f64()) {
+            (Some(a), Some(b)) => (a - b).abs() <= tolerance,
+            _ => a == b
+        };
+
+        if equal { None } else { Some(Delta { label: label.clone(), old: a.clone(), new: b.clone() }) }
+    }).collect()
+}
+
+
+impl Display for Delta {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.label, self.old, self.new)?;
+        if let (Some(a), Some(b)) = (self.old.as_f64(), self.new.as_f64()) {
+            // rounded to get rid of f32 noise
+            let d = ((b - a) * 1e6).round() / 1e6;
+            write!(f, " ({:+})", d)?;
+        }
+        Ok(())
+    }
+}
+
+
+impl<T: Display> Display for KindDiff<'_, T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.kind)?;
+        for c in self.changes.iter() {
+            match c {
+                Change::Added(t)   => writeln!(f, "  + {}", t)?,
+                Change::Removed(t) => writeln!(f, "  - {}", t)?,
+                Change::Changed(a, b, ds) => {
+                    writeln!(f, "  ~ {} -> {}", a, b)?;
+                    for d in ds.iter() {
+                        writeln!(f, "      {}", d)?;
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
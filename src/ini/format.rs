@@ -0,0 +1,107 @@
+// Canonical formatting of building.ini: tokens are written by the serializer (uppercase names,
+// one token per line, points on their own lines, fixed precision for coordinates), no blank lines,
+// the line endings of the file (CRLF or LF). Text between tokens is kept: a comment at the end of
+// a token's line stays there, other lines (comments) stay in front of the token that follows them.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{BuildingIni, IniToken, BuildingToken, line_ending};
+use super::building::TokenGroup;
+
+
+// Token names are matched case-sensitively by the parser, so they are uppercased before parsing.
+// The length of the text does not change, so spans of parse errors stay correct.
+pub fn uppercase_token_names(src: &str) -> String {
+    lazy_static! {
+        static ref RX_NAME: Regex = Regex::new(r"(?m)^([ \t]*\$)([0-9A-Za-z_]+)").unwrap();
+    }
+
+    RX_NAME.replace_all(src, |c: &regex::Captures| format!("{}{}", &c[1], c[2].to_ascii_uppercase())).into_owned()
+}
+
+
+// With `sort`, tokens are ordered by their groups (see TokenGroup), keeping the order of tokens
+// within a group. Groups are separated with a blank line.
+pub fn format_building(ini: &BuildingIni<'_>, sort: bool) -> String {
+    let src = ini.ini_slice;
+    let mut entries: Vec<Entry> = Vec::with_capacity(ini.tokens.len());
+    let mut prev_end = 0;
+
+    for (span, t_state) in ini.tokens.iter() {
+        let (trailing, mut comments) = split_comments(&src[prev_end .. span.start]);
+        match (entries.last_mut(), trailing) {
+            (Some(last), _) => last.trailing = trailing,
+            (None, Some(c)) => comments.insert(0, c),
+            (None, None)    => { }
+        }
+
+        let token = t_state.token();
+        entries.push(Entry { group: token.group(), comments, token, trailing: None });
+        prev_end = span.end;
+    }
+
+    let (trailing, tail_comments) = split_comments(&src[prev_end ..]);
+    if let Some(last) = entries.last_mut() {
+        last.trailing = trailing;
+    }
+
+    if sort {
+        entries.sort_by_key(|e| e.group);
+    }
+
+    let eol = line_ending(src);
+    let mut result = String::with_capacity(src.len() + 64);
+    for (i, Entry { group, comments, token, trailing }) in entries.iter().enumerate() {
+        if sort && i > 0 && entries[i - 1].group != *group {
+            result.push_str(eol);
+        }
+
+        for c in comments.iter() {
+            result.push_str(c);
+            result.push_str(eol);
+        }
+
+        let buf = BuildingIni::serialize_eol(token, eol).expect("Writing to a Vec cannot fail");
+        result.push_str(BuildingToken::PREFIX);
+        result.push_str(std::str::from_utf8(&buf).expect("Serialized token is not valid UTF-8"));
+        if let Some(c) = trailing {
+            result.push(' ');
+            result.push_str(c);
+        }
+        result.push_str(eol);
+    }
+
+    for c in tail_comments.iter() {
+        result.push_str(c);
+        result.push_str(eol);
+    }
+    result.push_str("end");
+    result.push_str(eol);
+
+    result
+}
+
+
+struct Entry<'a, 't> {
+    group: TokenGroup,
+    comments: Vec<&'a str>,
+    token: &'a BuildingToken<'t>,
+    trailing: Option<&'a str>,
+}
+
+
+// Text between two tokens: the rest of the line of the previous token, and the lines in front of
+// the next one. Empty lines, the prefix of the next token and the 'end' line are not kept.
+fn split_comments(sep: &str) -> (Option<&str>, Vec<&str>) {
+    fn kept(line: &str) -> Option<&str> {
+        let line = line.trim();
+        let line = line.strip_suffix(BuildingToken::PREFIX).map(str::trim_end).unwrap_or(line);
+        if line.is_empty() || line == "end" { None } else { Some(line) }
+    }
+
+    match sep.split_once('\n') {
+        Some((first, rest)) => (kept(first), rest.lines().filter_map(kept).collect()),
+        None => (kept(sep), Vec::new())
+    }
+}
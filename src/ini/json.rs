@@ -0,0 +1,408 @@
+// JSON representation of ini files: a list of tokens, each with the keyword as it is written
+// in the file and the parameters in file order. Points are [x, y, z], rectangles are [x1, z1, x2, z2].
+//
+// The rest of the file is carried too, so that the file can be written back as it was: "text" is
+// the token as it is written, "before" is the text between the previous token and this one
+// (comments, empty lines, line breaks), "after" is the text after the last token.
+//
+// {
+//   "encoding": "utf-8",
+//   "tokens": [
+//     {"token":"$TYPE_FACTORY","text":"$TYPE_FACTORY"},
+//     {"token":"$STORAGE_EXPORT","params":["RESOURCE_TRANSPORT_OPEN",20.0],"before":"\r\n-- storage\r\n","text":"$STORAGE_EXPORT RESOURCE_TRANSPORT_OPEN 20"},
+//     ...
+//   ],
+//   "after": "\r\nend\r\n"
+// }
+//
+// On import, tokens with changed parameters are written by the serializer, tokens without "text" are
+// new ones: they are placed on their own line after the previous token. Without "after" the file ends
+// with the default footer.
+
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::io::Write;
+use std::slice::Iter;
+
+use serde_json::Value;
+
+use super::{IniToken, IniFile};
+use super::common::ParseError;
+use crate::encoding::TextEncoding;
+use super::common::{Point3f, Rect, StrValue, IdStringParam, QuotedStringParam, CostKeywordParam};
+
+
+pub trait JsonParam<'a>: Sized {
+    fn write_json(&self, params: &mut Vec<Value>);
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String>;
+}
+
+
+// Enum parameters, written as their names in the ini file
+pub trait JsonLiteral: Sized + Display {
+    fn from_name(name: &str) -> Result<Self, String>;
+}
+
+
+pub trait JsonToken<'a>: IniToken + Sized {
+    // keyword (without T::PREFIX) and parameters
+    fn to_json(&self) -> (String, Vec<Value>);
+    fn from_json(keyword: &'a str, params: &mut Iter<'a, Value>) -> Result<Self, String>;
+}
+
+
+pub fn ini_to_json<'t, T: JsonToken<'t>>(ini: &IniFile<'_, T>) -> String {
+    let src = ini.ini_slice;
+    let mut prev_end = 0;
+    let lines: Vec<String> = ini.tokens_with_spans().map(|(span, t)| {
+        let (keyword, params) = t.to_json();
+        // the prefix is a part of the text, not of the text before it
+        let start = if src[.. span.start].ends_with(T::PREFIX) { span.start - T::PREFIX.len() } else { span.start };
+        let before = &src[prev_end .. start];
+        let text = &src[start .. span.end];
+        prev_end = span.end;
+
+        // written by hand to keep "token" in front of "params" and the rest
+        let mut line = format!("{{\"token\":{}", Value::String(format!("{}{}", T::PREFIX, keyword)));
+        if !params.is_empty() {
+            line += &format!(",\"params\":{}", Value::Array(params));
+        }
+        if !before.is_empty() {
+            line += &format!(",\"before\":{}", Value::String(before.to_string()));
+        }
+        line += &format!(",\"text\":{}}}", Value::String(text.to_string()));
+        line
+    }).collect();
+
+    // one token per line: readable and diff-friendly
+    format!("{{\n  \"encoding\": {},\n  \"tokens\": [\n    {}\n  ],\n  \"after\": {}\n}}\n",
+            Value::String(ini.encoding.name().to_string()),
+            lines.join(",\n    "),
+            Value::String(src[prev_end ..].to_string()))
+}
+
+
+// The exported file: texts of the tokens with the text around them. Tokens without "text" are not a part of it.
+pub fn source_from_json(doc: &Value, footer: Option<&str>) -> Result<String, String> {
+    let tokens = doc.get("tokens")
+                    .and_then(Value::as_array)
+                    .ok_or_else(|| "Expected an object with the \"tokens\" array".to_string())?;
+
+    let string = |v: &Value, key: &str, i: usize| -> Result<Option<String>, String> {
+        match v.get(key) {
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(_)                => Err(format!("Token #{}: \"{}\" must be a string", i + 1, key)),
+            None                   => Ok(None)
+        }
+    };
+
+    let mut result = String::with_capacity(tokens.len() * 32);
+    for (i, t) in tokens.iter().enumerate() {
+        result += &string(t, "before", i)?.unwrap_or_default();
+        result += &string(t, "text", i)?.unwrap_or_default();
+    }
+
+    match doc.get("after") {
+        Some(Value::String(s)) => result += s,
+        Some(_)                => return Err("\"after\" must be a string".to_string()),
+        None                   => if let Some(footer) = footer {
+            result += footer;
+            result += "\r\n";
+        }
+    }
+
+    Ok(result)
+}
+
+
+// File of src (from source_from_json) with the tokens of doc: changed tokens are replaced, new ones inserted
+pub fn ini_from_json<'a, T, F>(doc: &'a Value, src: &'a str, parse: F) -> Result<IniFile<'a, T>, String>
+where T: JsonToken<'a>,
+      F: FnOnce(&'a str) -> Result<IniFile<'a, T>, Vec<(&'a str, ParseError)>>
+{
+    let tokens: Vec<T> = tokens_from_json(doc)?;
+    let has_text = doc["tokens"].as_array().map(|ts| ts.iter().map(|t| t.get("text").is_some()).collect::<Vec<_>>()).unwrap_or_default();
+
+    let mut file = parse(src).map_err(|errors| {
+        let (chunk, e) = &errors[0];
+        format!("Texts of the tokens cannot be parsed: {} (in '{}')", e, chunk.trim())
+    })?;
+
+    if let Some(Value::String(name)) = doc.get("encoding") {
+        file.set_encoding(TextEncoding::from_name(name).ok_or_else(|| format!("Unknown encoding '{}'", name))?);
+    }
+
+    // (number of tokens with text in front of the token, token)
+    let mut originals = Vec::with_capacity(tokens.len());
+    let mut new = Vec::with_capacity(0);
+    for (t, has_text) in tokens.into_iter().zip(has_text) {
+        if has_text {
+            originals.push(Some(t));
+        } else {
+            new.push((originals.len(), t));
+        }
+    }
+
+    if file.tokens().count() != originals.len() {
+        return Err(format!("Texts of the tokens have {} tokens, expected {}", file.tokens().count(), originals.len()));
+    }
+
+    for (t_state, t) in file.tokens_mut().zip(originals.iter_mut()) {
+        if t.as_ref().map(JsonToken::to_json) != Some(t_state.token().to_json()) {
+            t_state.modify(|_| t.take());
+        }
+    }
+
+    for (anchor, t) in new {
+        if anchor == 0 {
+            if let Err(t) = file.insert_before(|_| true, t) {
+                file.append(t);
+            }
+        } else {
+            let mut n = 0;
+            file.insert_after(|_| { n += 1; n == anchor }, t).map_err(|_| "Anchor of a new token is not found".to_string())?;
+        }
+    }
+
+    Ok(file)
+}
+
+
+pub fn tokens_from_json<'a, T: JsonToken<'a>>(doc: &'a Value) -> Result<Vec<T>, String> {
+    let tokens = doc.get("tokens")
+                    .and_then(Value::as_array)
+                    .ok_or_else(|| "Expected an object with the \"tokens\" array".to_string())?;
+
+    let mut result = Vec::with_capacity(tokens.len());
+    for (i, t) in tokens.iter().enumerate() {
+        let keyword = t.get("token").and_then(Value::as_str)
+                       .ok_or_else(|| format!("Token #{}: \"token\" must be a string", i + 1))?;
+
+        let params: &[Value] = match t.get("params") {
+            Some(Value::Array(ps)) => ps,
+            Some(_)                => return Err(format!("Token #{} ({}): \"params\" must be an array", i + 1, keyword)),
+            None                   => &[],
+        };
+
+        let mut iter = params.iter();
+        let token = keyword.strip_prefix(T::PREFIX)
+                           .ok_or_else(|| format!("token must start with '{}'", T::PREFIX))
+                           .and_then(|kw| T::from_json(kw, &mut iter))
+                           .and_then(|t| if iter.len() == 0 { Ok(t) } else { Err(format!("{} unexpected parameter(s)", iter.len())) })
+                           .map_err(|e| format!("Token #{} ({}): {}", i + 1, keyword, e))?;
+        result.push(token);
+    }
+
+    Ok(result)
+}
+
+
+// Writes tokens one per line, followed by the footer line (if any)
+pub fn write_ini<T: IniToken, W: Write>(tokens: &[T], footer: Option<&str>, mut wr: W) -> std::io::Result<()> {
+    for t in tokens.iter() {
+        wr.write_all(T::PREFIX.as_bytes())?;
+        t.serialize(&mut wr)?;
+        wr.write_all(b"\r\n")?;
+    }
+
+    if let Some(footer) = footer {
+        write!(wr, "{}\r\n", footer)?;
+    }
+
+    wr.flush()
+}
+
+
+//-------------------------------------------------------------------
+
+fn next<'a>(params: &mut Iter<'a, Value>) -> Result<&'a Value, String> {
+    params.next().ok_or_else(|| "Not enough parameters".to_string())
+}
+
+fn next_str<'a>(params: &mut Iter<'a, Value>) -> Result<&'a str, String> {
+    let v = next(params)?;
+    v.as_str().ok_or_else(|| format!("Expected a string, found {}", v))
+}
+
+fn next_f32(params: &mut Iter<'_, Value>) -> Result<f32, String> {
+    let v = next(params)?;
+    v.as_f64().map(|x| x as f32).ok_or_else(|| format!("Expected a number, found {}", v))
+}
+
+fn next_array<'a>(params: &mut Iter<'a, Value>, len: usize) -> Result<Vec<f32>, String> {
+    let v = next(params)?;
+    match v.as_array() {
+        Some(xs) if xs.len() == len => xs.iter().map(|x| x.as_f64().map(|x| x as f32)).collect::<Option<Vec<_>>>(),
+        _ => None
+    }.ok_or_else(|| format!("Expected an array of {} numbers, found {}", len, v))
+}
+
+// f32 -> f64 through the shortest representation: 0.1 stays 0.1
+fn number(x: f32) -> Value {
+    x.to_string().parse::<f64>().map(Value::from).unwrap_or(Value::Null)
+}
+
+
+impl<'a> JsonParam<'a> for f32 {
+    fn write_json(&self, params: &mut Vec<Value>) {
+        params.push(number(*self));
+    }
+
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        next_f32(params)
+    }
+}
+
+
+impl<'a> JsonParam<'a> for u32 {
+    fn write_json(&self, params: &mut Vec<Value>) {
+        params.push(Value::from(*self));
+    }
+
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        let v = next(params)?;
+        v.as_u64().and_then(|x| u32::try_from(x).ok()).ok_or_else(|| format!("Expected an integer, found {}", v))
+    }
+}
+
+
+impl<'a> JsonParam<'a> for u8 {
+    fn write_json(&self, params: &mut Vec<Value>) {
+        params.push(Value::from(*self));
+    }
+
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        let v = next(params)?;
+        v.as_u64().and_then(|x| u8::try_from(x).ok()).ok_or_else(|| format!("Expected an integer (0-255), found {}", v))
+    }
+}
+
+
+impl<'a> JsonParam<'a> for Point3f {
+    fn write_json(&self, params: &mut Vec<Value>) {
+        params.push(Value::Array(vec![number(self.x), number(self.y), number(self.z)]));
+    }
+
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        let v = next_array(params, 3)?;
+        Ok(Point3f { x: v[0], y: v[1], z: v[2] })
+    }
+}
+
+
+impl<'a> JsonParam<'a> for Rect {
+    fn write_json(&self, params: &mut Vec<Value>) {
+        params.push(Value::Array(vec![number(self.x1), number(self.z1), number(self.x2), number(self.z2)]));
+    }
+
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        let v = next_array(params, 4)?;
+        Ok(Rect { x1: v[0], z1: v[1], x2: v[2], z2: v[3] })
+    }
+}
+
+
+impl<'a> JsonParam<'a> for IdStringParam<'a> {
+    fn write_json(&self, params: &mut Vec<Value>) {
+        params.push(Value::String(self.0.to_string()));
+    }
+
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        let s = next_str(params)?;
+        if s.is_empty() || s.contains(char::is_whitespace) {
+            Err(format!("Expected a string without whitespace, found \"{}\"", s))
+        } else {
+            Ok(IdStringParam::new_borrowed(s))
+        }
+    }
+}
+
+
+impl<'a> JsonParam<'a> for QuotedStringParam<'a> {
+    fn write_json(&self, params: &mut Vec<Value>) {
+        params.push(Value::String(self.0.to_string()));
+    }
+
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        let s = next_str(params)?;
+        if s.is_empty() || s.contains(['"', '\n']) {
+            Err(format!("Expected a non-empty string without quotes and line breaks, found {:?}", s))
+        } else {
+            Ok(QuotedStringParam(StrValue::Borrowed(s)))
+        }
+    }
+}
+
+
+// written without the leading '$'
+impl<'a> JsonParam<'a> for CostKeywordParam<'a> {
+    fn write_json(&self, params: &mut Vec<Value>) {
+        self.0.write_json(params);
+    }
+
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        IdStringParam::read_json(params).map(CostKeywordParam)
+    }
+}
+
+
+impl<'a, T: JsonLiteral> JsonParam<'a> for T {
+    fn write_json(&self, params: &mut Vec<Value>) {
+        params.push(Value::String(self.to_string()));
+    }
+
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        T::from_name(next_str(params)?)
+    }
+}
+
+
+impl<'a, T1, T2> JsonParam<'a> for (T1, T2)
+where T1: JsonParam<'a>,
+      T2: JsonParam<'a>
+{
+    fn write_json(&self, params: &mut Vec<Value>) {
+        self.0.write_json(params);
+        self.1.write_json(params);
+    }
+
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        Ok((T1::read_json(params)?, T2::read_json(params)?))
+    }
+}
+
+
+impl<'a, T1, T2, T3> JsonParam<'a> for (T1, T2, T3)
+where T1: JsonParam<'a>,
+      T2: JsonParam<'a>,
+      T3: JsonParam<'a>
+{
+    fn write_json(&self, params: &mut Vec<Value>) {
+        self.0.write_json(params);
+        self.1.write_json(params);
+        self.2.write_json(params);
+    }
+
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        Ok((T1::read_json(params)?, T2::read_json(params)?, T3::read_json(params)?))
+    }
+}
+
+
+impl<'a, T1, T2, T3, T4> JsonParam<'a> for (T1, T2, T3, T4)
+where T1: JsonParam<'a>,
+      T2: JsonParam<'a>,
+      T3: JsonParam<'a>,
+      T4: JsonParam<'a>
+{
+    fn write_json(&self, params: &mut Vec<Value>) {
+        self.0.write_json(params);
+        self.1.write_json(params);
+        self.2.write_json(params);
+        self.3.write_json(params);
+    }
+
+    fn read_json(params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        Ok((T1::read_json(params)?, T2::read_json(params)?, T3::read_json(params)?, T4::read_json(params)?))
+    }
+}
@@ -1,6 +1,8 @@
 use std::fmt;
+use std::slice::Iter;
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde_json::Value;
 
 use crate::ini::common::{IdStringParam,
                          ParseSlice,
@@ -10,6 +12,9 @@ use crate::ini::common::{IdStringParam,
                          parse_tokens_with,
                          parse_tokens_strict_with,
                          };
+use crate::ini::json::{JsonParam, JsonToken};
+use crate::ini::schema::{self, SchemaToken};
+use crate::cfg::APP_SETTINGS;
 
 pub type Color = (f32, f32, f32, f32);
 
@@ -24,6 +29,9 @@ pub enum Token<'a> {
     AmbientColor(Color),
     SpecularPower(f32),
     End,
+
+    // unknown to the parser, declared in the token schema
+    Schema(SchemaToken<'a>),
 }
 
 
@@ -39,12 +47,22 @@ impl<'a> Token<'a> {
     const SPECULAR_POWER:    &'static str = "$SPECULARPOWER";
     const END:               &'static str = "$END";
 
+    const NAMES: &'static [&'static str] = &[
+        Self::SUBMATERIAL, Self::TEXTURE, Self::TEXTURE_NOMIP, Self::TEXTURE_MTL,
+        Self::TEXTURE_NOMIP_MTL, Self::DIFFUSE_COLOR, Self::SPECULAR_COLOR, Self::AMBIENT_COLOR,
+        Self::SPECULAR_POWER, Self::END
+    ];
+
     fn parse(src: &'a str) -> ParseResult<Self> {
+        schema::parse_declared(Self::parse_builtin(src), &APP_SETTINGS.schema.mtl, src, Self::Schema)
+    }
+
+    pub(crate) fn parse_builtin(src: &'a str) -> ParseResult<Self> {
         lazy_static! {
             static ref RX_TYPE: Regex = Regex::new(r"^(\$?[0-9A-Z_]+)(\s+(.+))?$").unwrap();
         }
 
-        let (t_type, rest) = chop_param(Some(src), &RX_TYPE).map_err(|e| format!("Cannot parse token type: {}", e))?;
+        let (t_type, rest) = chop_param(Some(src), &RX_TYPE).map_err(|e| e.context("Cannot parse token type"))?;
         macro_rules! parse {
             ($id:ident, $t:ty) => {
                 <$t>::parse(rest).map(|(p, rest)| (Self::$id(p), rest))
@@ -65,7 +83,7 @@ impl<'a> Token<'a> {
             Self::AMBIENT_COLOR     => parse!(AmbientColor,    Color),
             Self::SPECULAR_POWER    => parse!(SpecularPower,   f32),
             Self::END               => parse!(End),
-            _ => Err(format!("Unknown token type: \"{}\"", t_type))
+            _ => Err(ParseError::unknown("token type", t_type, Self::NAMES))
         }
     }
 }
@@ -84,6 +102,7 @@ impl fmt::Display for Token<'_> {
             Self::AmbientColor(c)         => write!(f, "{} {:?}",   Self::AMBIENT_COLOR,     c),
             Self::SpecularPower(x)        => write!(f, "{} {}",     Self::SPECULAR_POWER,    x),
             Self::End                     => write!(f, "{}",        Self::END),
+            Self::Schema(t)                   => write!(f, "{}", t),
         }
     }
 }
@@ -95,9 +114,78 @@ impl super::IniToken for Token<'_> {
             Self::DiffuseColor((r, g, b, a))  => write!(wr, "{} {} {} {} {}", Self::DIFFUSE_COLOR,  r, g, b, a),
             Self::SpecularColor((r, g, b, a)) => write!(wr, "{} {} {} {} {}", Self::SPECULAR_COLOR, r, g, b, a),
             Self::AmbientColor((r, g, b, a))  => write!(wr, "{} {} {} {} {}", Self::AMBIENT_COLOR,  r, g, b, a),
+            Self::Schema(t) => t.serialize(wr, false),
             s => write!(wr, "{}", s)
         }
     }
+
+    fn is_end(&self) -> bool {
+        matches!(self, Self::End)
+    }
+}
+
+
+impl<'a> JsonToken<'a> for Token<'a> {
+    fn to_json(&self) -> (String, Vec<Value>) {
+        let mut params = Vec::new();
+        macro_rules! json {
+            ($kw:expr, $p:expr) => {{
+                $p.write_json(&mut params);
+                $kw.to_string()
+            }};
+            ($kw:expr) => {
+                $kw.to_string()
+            };
+        }
+
+        let keyword = match self {
+            Self::Submaterial(p)      => json!(Self::SUBMATERIAL, p),
+            Self::Texture(p)          => json!(Self::TEXTURE, p),
+            Self::TextureNoMip(p)     => json!(Self::TEXTURE_NOMIP, p),
+            Self::TextureMtl(p)       => json!(Self::TEXTURE_MTL, p),
+            Self::TextureNoMipMtl(p)  => json!(Self::TEXTURE_NOMIP_MTL, p),
+            Self::DiffuseColor(p)     => json!(Self::DIFFUSE_COLOR, p),
+            Self::SpecularColor(p)    => json!(Self::SPECULAR_COLOR, p),
+            Self::AmbientColor(p)     => json!(Self::AMBIENT_COLOR, p),
+            Self::SpecularPower(p)    => json!(Self::SPECULAR_POWER, p),
+            Self::End                 => json!(Self::END),
+            Self::Schema(t)           => json!(t.keyword, t),
+        };
+
+        (keyword, params)
+    }
+
+    fn from_json(keyword: &'a str, params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        schema::from_json_declared(&APP_SETTINGS.schema.mtl, keyword, params, Self::builtin_from_json, Self::Schema)
+    }
+}
+
+
+impl<'a> Token<'a> {
+    fn builtin_from_json(keyword: &str, params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        macro_rules! read {
+            ($id:ident, $t:ty) => {
+                <$t>::read_json(params).map(Self::$id)
+            };
+            ($id:ident) => {
+                Ok(Self::$id)
+            };
+        }
+
+        match keyword {
+            Self::SUBMATERIAL         => read!(Submaterial,       IdStringParam),
+            Self::TEXTURE             => read!(Texture,           (u8, IdStringParam)),
+            Self::TEXTURE_NOMIP       => read!(TextureNoMip,      (u8, IdStringParam)),
+            Self::TEXTURE_MTL         => read!(TextureMtl,        (u8, IdStringParam)),
+            Self::TEXTURE_NOMIP_MTL   => read!(TextureNoMipMtl,   (u8, IdStringParam)),
+            Self::DIFFUSE_COLOR       => read!(DiffuseColor,      Color),
+            Self::SPECULAR_COLOR      => read!(SpecularColor,     Color),
+            Self::AMBIENT_COLOR       => read!(AmbientColor,      Color),
+            Self::SPECULAR_POWER      => read!(SpecularPower,     f32),
+            Self::END                 => read!(End),
+            _ => Err(ParseError::unknown("token type", keyword, Self::NAMES).to_string())
+        }
+    }
 }
 
 
@@ -1,8 +1,18 @@
 use std::io::Write;
 use std::path::Path;
 use std::fmt;
+use std::ops::Range;
+
+use lazy_static::lazy_static;
+use regex::Regex;
 
 pub mod common;
+pub mod diagnostic;
+pub mod json;
+pub mod diff;
+pub mod format;
+pub mod schema;
+pub mod tokens;
 
 pub mod building;
 pub mod renderconfig;
@@ -12,44 +22,71 @@ pub mod transform;
 
 use common::{ParseError, IdStringParam};
 use crate::cfg::APP_SETTINGS;
+use crate::encoding::TextEncoding;
 
 
 //---------------------------------------------
 
 
 pub trait IniToken: Sized {
+    // Text in front of a token on its line, which is not a part of the token's chunk
+    const PREFIX: &'static str = "";
+
     fn serialize<W: Write>(&self, wr: W) -> std::io::Result<()>;
+
+    fn is_end(&self) -> bool {
+        false
+    }
 }
 
 
 pub enum IniTokenState<T> {
     Original(T),
-    Modified(T)
+    Modified(T),
+    Removed(T),
+    // new tokens are placed on their own line before or after the line(s) of the anchor chunk
+    InsertedBefore(T),
+    InsertedAfter(T),
 }
 
 
 impl<T> IniTokenState<T> {
     pub fn token(&self) -> &T {
         match self {
-            Self::Original(t) => t,
-            Self::Modified(t) => t
+            Self::Original(t)       => t,
+            Self::Modified(t)       => t,
+            Self::Removed(t)        => t,
+            Self::InsertedBefore(t) => t,
+            Self::InsertedAfter(t)  => t,
         }
     }
 
     pub fn modify<F: FnMut(&T) -> Option<T>>(&mut self, mut f: F) {
         match f(self.token()) {
             None => { },
-            Some(t) => *self = Self::Modified(t)
+            Some(t) => match self {
+                Self::Removed(_)        => { },
+                Self::InsertedBefore(_) => *self = Self::InsertedBefore(t),
+                Self::InsertedAfter(_)  => *self = Self::InsertedAfter(t),
+                _                       => *self = Self::Modified(t)
+            }
         }
     }
+
+    fn is_removed(&self) -> bool {
+        matches!(self, Self::Removed(_))
+    }
 }
 
 
 impl<T: fmt::Display> fmt::Display for IniTokenState<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match self {
-            Self::Original(t) => write!(f, "{}", t),
-            Self::Modified(t) => write!(f, "* {}", t),
+            Self::Original(t)       => write!(f, "{}", t),
+            Self::Modified(t)       => write!(f, "* {}", t),
+            Self::Removed(t)        => write!(f, "- {}", t),
+            Self::InsertedBefore(t) => write!(f, "+ {}", t),
+            Self::InsertedAfter(t)  => write!(f, "+ {}", t),
         }
     }
 }
@@ -61,27 +98,126 @@ impl<'a, T: ?Sized> Captures<'a> for T {}
 
 pub struct IniFile<'a, T: IniToken> {
     ini_slice: &'a str,
-    tokens: Vec<(&'a str, IniTokenState<T>)>
+    // byte range of every token in ini_slice; inserted tokens have the range of their anchor
+    tokens: Vec<(Range<usize>, IniTokenState<T>)>,
+    // the file is written back in the encoding it was read in
+    encoding: TextEncoding,
 }
 
 
 impl<'a, T> IniFile<'a, T> where T: IniToken {
 
+    // chunks are the subslices of ini_slice, which the tokens are parsed from
     pub fn from_parts(ini_slice: &'a str, tokens: Vec<(&'a str, T)>) -> Self {
         IniFile { 
             ini_slice, 
             tokens: tokens.into_iter()
-                          .map(|(chunk, t_val)| (chunk, IniTokenState::Original(t_val)))
-                          .collect()
+                          .map(|(chunk, t_val)| (common::subslice_range(ini_slice, chunk), IniTokenState::Original(t_val)))
+                          .collect(),
+            encoding: TextEncoding::Utf8,
         }
     }
 
+    pub fn set_encoding(&mut self, encoding: TextEncoding) {
+        self.encoding = encoding;
+    }
+
     pub fn tokens(&self) -> impl Iterator<Item = &T> + Clone {
-        self.tokens.iter().map(|(_, t)| t.token())
+        self.tokens.iter().filter(|(_, t)| !t.is_removed()).map(|(_, t)| t.token())
+    }
+
+    // Tokens with their byte ranges in the source text. Inserted tokens have the range of their anchor.
+    pub fn tokens_with_spans(&self) -> impl Iterator<Item = (Range<usize>, &T)> {
+        self.tokens.iter()
+                   .filter(|(_, t)| !t.is_removed())
+                   .map(|(span, t)| (span.clone(), t.token()))
     }
 
     pub fn tokens_mut(&mut self) -> impl Iterator<Item = &mut IniTokenState<T>> + Captures<'a> {
-        self.tokens.iter_mut().map(|(_, t)| t)
+        self.tokens.iter_mut().filter(|(_, t)| !t.is_removed()).map(|(_, t)| t)
+    }
+
+    // Marks all tokens matching the predicate as removed. Returns the number of removed tokens.
+    pub fn remove_tokens<F: FnMut(&T) -> bool>(&mut self, mut f: F) -> usize {
+        let mut count = 0;
+        let tokens = std::mem::take(&mut self.tokens);
+        self.tokens = tokens.into_iter().filter_map(|(span, t_state)| {
+            match t_state {
+                IniTokenState::Removed(_) => Some((span, t_state)),
+                _ if !f(t_state.token()) => Some((span, t_state)),
+                // inserted tokens have no text of their own
+                IniTokenState::InsertedBefore(_) | IniTokenState::InsertedAfter(_) => { count += 1; None },
+                IniTokenState::Original(t) | IniTokenState::Modified(t) => { count += 1; Some((span, IniTokenState::Removed(t))) },
+            }
+        }).collect();
+
+        count
+    }
+
+    // Inserts a new token before the first token matching the predicate.
+    // Gives the token back if there is no such token.
+    pub fn insert_before<F: FnMut(&T) -> bool>(&mut self, anchor: F, token: T) -> Result<(), T> {
+        match self.find_anchor(anchor) {
+            Some(idx) => {
+                let span = self.tokens[idx].0.clone();
+                self.tokens.insert(idx, (span, IniTokenState::InsertedBefore(token)));
+                Ok(())
+            },
+            None => Err(token)
+        }
+    }
+
+    // Inserts a new token after the first token matching the predicate (and after the tokens
+    // already inserted there). Gives the token back if there is no such token.
+    pub fn insert_after<F: FnMut(&T) -> bool>(&mut self, anchor: F, token: T) -> Result<(), T> {
+        match self.find_anchor(anchor) {
+            Some(idx) => {
+                let span = self.tokens[idx].0.clone();
+                let mut pos = idx + 1;
+                while let Some((s, IniTokenState::InsertedAfter(_))) = self.tokens.get(pos) {
+                    if *s != span {
+                        break;
+                    }
+                    pos += 1;
+                }
+
+                self.tokens.insert(pos, (span, IniTokenState::InsertedAfter(token)));
+                Ok(())
+            },
+            None => Err(token)
+        }
+    }
+
+    // Appends a new token before the END token (or the 'end' line), or at the end of the file.
+    pub fn append(&mut self, token: T) {
+        lazy_static! {
+            static ref RX_END: Regex = Regex::new(r"(?im)^[ \t]*end\b").unwrap();
+        }
+
+        let token = match self.insert_before(T::is_end, token) {
+            Ok(()) => return,
+            Err(token) => token
+        };
+
+        let tail_start = self.tokens.last().map(|(s, _)| s.end).unwrap_or(0);
+        if let Some(m) = RX_END.find(&self.ini_slice[tail_start ..]) {
+            let end_pos = tail_start + m.start();
+            self.tokens.push((end_pos .. end_pos, IniTokenState::InsertedBefore(token)));
+        } else if let Some((span, _)) = self.tokens.last() {
+            let span = span.clone();
+            self.tokens.push((span, IniTokenState::InsertedAfter(token)));
+        } else {
+            let end_pos = self.ini_slice.len();
+            self.tokens.push((end_pos .. end_pos, IniTokenState::InsertedBefore(token)));
+        }
+    }
+
+    // Original and modified tokens only: inserted ones have no place of their own
+    fn find_anchor<F: FnMut(&T) -> bool>(&self, mut anchor: F) -> Option<usize> {
+        self.tokens.iter().position(|(_, t)| match t {
+            IniTokenState::Original(t) | IniTokenState::Modified(t) => anchor(t),
+            _ => false
+        })
     }
 
     pub fn write_file<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
@@ -91,45 +227,110 @@ impl<'a, T> IniFile<'a, T> where T: IniToken {
     }
 
     pub fn write_to<W: Write>(&self, mut wr: W) -> std::io::Result<()> {
-        unsafe {
-            // replace 'modified' tokens, dump other stuff as is
-            let mut chunk_start = self.ini_slice.as_ptr();
-
-            for (t_str, t_state) in self.tokens.iter() {
-                match t_state {
-                    IniTokenState::Original(_) => { },
-                    IniTokenState::Modified(t) => {
-                        let chunk_end = t_str.as_ptr();
-                        let chunk_len = chunk_end.offset_from(chunk_start);
-                        if chunk_len > 0 {
-                            // can write original chunk
-                            let chunk = std::slice::from_raw_parts(chunk_start, chunk_len as usize);
-                            wr.write_all(chunk)?;
-
-                            chunk_start = chunk_end.add(t_str.len());
-                        }
-
-                        t.serialize(&mut wr)?;
+        if self.encoding == TextEncoding::Utf8 {
+            return self.write_text(wr);
+        }
+
+        let mut buf = Vec::with_capacity(self.ini_slice.len() + 256);
+        self.write_text(&mut buf)?;
+        let text = String::from_utf8(buf).expect("Tokens are serialized as UTF-8");
+        wr.write_all(&self.encoding.encode(&text)?)
+    }
+
+    fn write_text<W: Write>(&self, mut wr: W) -> std::io::Result<()> {
+        // replace 'modified' tokens, cut 'removed' ones, add 'inserted' ones, dump other stuff as is
+        let src = self.ini_slice;
+        let bytes = src.as_bytes();
+        let eol = line_ending(src);
+        let mut written = 0;
+
+        for (span, t_state) in self.tokens.iter() {
+            let (start, end) = (span.start, span.end);
+            match t_state {
+                IniTokenState::Original(_) => { },
+                IniTokenState::Modified(t) => {
+                    wr.write_all(&bytes[written .. start])?;
+                    wr.write_all(&Self::serialize_eol(t, eol)?)?;
+                    written = end;
+                },
+                IniTokenState::Removed(_) => {
+                    let line_start = self.line_start(start);
+                    let (cut_from, cut_to) = if self.owns_line(line_start, start) {
+                        (line_start, self.next_line_start(end))
+                    } else {
+                        (start, end)
+                    };
+
+                    wr.write_all(&bytes[written .. cut_from.max(written)])?;
+                    written = written.max(cut_to);
+                },
+                IniTokenState::InsertedBefore(t) => {
+                    let line_start = self.line_start(start).max(written);
+                    wr.write_all(&bytes[written .. line_start])?;
+                    written = line_start;
+
+                    self.write_inserted(&mut wr, t, line_start, eol)?;
+                    wr.write_all(eol.as_bytes())?;
+                },
+                IniTokenState::InsertedAfter(t) => {
+                    let next_line = self.next_line_start(end).max(written);
+                    wr.write_all(&bytes[written .. next_line])?;
+                    written = next_line;
+
+                    if !src[.. next_line].ends_with('\n') {
+                        wr.write_all(eol.as_bytes())?;
                     }
-                }
+                    self.write_inserted(&mut wr, t, self.line_start(start), eol)?;
+                    if next_line < src.len() || src.ends_with('\n') {
+                        wr.write_all(eol.as_bytes())?;
+                    }
+                },
             }
+        }
 
-            let written = chunk_start.offset_from(self.ini_slice.as_ptr());
-            assert!(written >= 0);
-            let written = written as usize;
-            assert!(written <= self.ini_slice.len());
-            let remaining = self.ini_slice.len() - written;
-            if remaining > 0 {
-                let last_chunk = std::slice::from_raw_parts(chunk_start, remaining);
-                wr.write_all(last_chunk)?;
-            }
-        } //unsafe
+        wr.write_all(&bytes[written ..])
+    }
 
-        Ok(())
+    // writes a token with the same indentation as the line at line_start
+    fn write_inserted<W: Write>(&self, mut wr: W, t: &T, line_start: usize, eol: &str) -> std::io::Result<()> {
+        let line = &self.ini_slice[line_start ..];
+        let indent = &line[.. line.len() - line.trim_start_matches([' ', '\t']).len()];
+        write!(wr, "{}{}", indent, T::PREFIX)?;
+        wr.write_all(&Self::serialize_eol(t, eol)?)
+    }
+
+    fn serialize_eol(t: &T, eol: &str) -> std::io::Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(64);
+        t.serialize(&mut buf)?;
+        // multiline tokens are serialized with CRLF
+        if eol == "\n" {
+            buf.retain(|&b| b != b'\r');
+        }
+        Ok(buf)
+    }
+
+    fn line_start(&self, pos: usize) -> usize {
+        self.ini_slice[.. pos].rfind('\n').map(|i| i + 1).unwrap_or(0)
+    }
+
+    fn next_line_start(&self, pos: usize) -> usize {
+        self.ini_slice[pos ..].find('\n').map(|i| pos + i + 1).unwrap_or(self.ini_slice.len())
+    }
+
+    // true when there is nothing but indentation and the token prefix in front of the chunk
+    fn owns_line(&self, line_start: usize, chunk_start: usize) -> bool {
+        let pfx = self.ini_slice[line_start .. chunk_start].trim_start();
+        pfx.is_empty() || pfx == T::PREFIX
     }
 }
 
 
+// Line ending of the text: LF when the text has LF line endings only, CRLF (as in the game files) otherwise
+pub fn line_ending(src: &str) -> &'static str {
+    if src.contains("\r\n") || !src.contains('\n') { "\r\n" } else { "\n" }
+}
+
+
 // --------------------- BUILDING.INI ---------------------------------
 
 
@@ -224,3 +425,116 @@ pub fn resolve_source_path(local_root: &Path, tail: &IdStringParam) -> PathBuf {
         _   => normalize_join(local_root, tail)
     }
 }
+
+
+// Splicing of removed, inserted and appended tokens into the original text
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::common::IdStringParam;
+    use MaterialToken as MT;
+
+    // One token per line (except empty lines and comments), as the parser would produce
+    fn mtl<'a>(src: &'a str, tokens: Vec<MT<'a>>) -> MaterialMtl<'a> {
+        let chunks = src.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with("--"));
+        MaterialMtl::from_parts(src, chunks.zip(tokens).collect())
+    }
+
+    fn text(file: &MaterialMtl) -> String {
+        let mut buf = Vec::new();
+        file.write_to(&mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn sm(name: &str) -> MT<'_> {
+        MT::Submaterial(IdStringParam::new_borrowed(name))
+    }
+
+    fn tx(name: &str) -> MT<'_> {
+        MT::Texture((0, IdStringParam::new_borrowed(name)))
+    }
+
+    #[test]
+    fn removed_tokens_take_their_lines() {
+        let src = "$SUBMATERIAL a\r\n$TEXTURE 0 a.dds\r\n$SUBMATERIAL b\r\n$TEXTURE 0 b.dds\r\n$END\r\n";
+        let mut file = mtl(src, vec![sm("a"), tx("a.dds"), sm("b"), tx("b.dds"), MT::End]);
+        let removed = file.remove_tokens(|t| match t {
+            MT::Submaterial(p) | MT::Texture((_, p)) => p.as_str().starts_with('b'),
+            _ => false
+        });
+
+        assert_eq!(removed, 2);
+        assert_eq!(text(&file), "$SUBMATERIAL a\r\n$TEXTURE 0 a.dds\r\n$END\r\n");
+    }
+
+    #[test]
+    fn appended_tokens_go_before_end() {
+        let src = "-- comment\n  $SUBMATERIAL a\n  $TEXTURE 0 a.dds\n  $END\n";
+        let mut file = mtl(src, vec![sm("a"), tx("a.dds"), MT::End]);
+        file.append(sm("b"));
+        file.append(tx("b.dds"));
+
+        assert_eq!(text(&file), "-- comment\n  $SUBMATERIAL a\n  $TEXTURE 0 a.dds\n  $SUBMATERIAL b\n  $TEXTURE 0 b.dds\n  $END\n");
+    }
+
+    #[test]
+    fn appended_tokens_without_end() {
+        let src = "$SUBMATERIAL a";
+        let mut file = mtl(src, vec![sm("a")]);
+        file.append(sm("b"));
+
+        assert_eq!(text(&file), "$SUBMATERIAL a\r\n$SUBMATERIAL b");
+    }
+
+    #[test]
+    fn inserted_before_anchor() {
+        let src = "$SUBMATERIAL a\r\n  $TEXTURE 0 a.dds\r\n$END\r\n";
+        let mut file = mtl(src, vec![sm("a"), tx("a.dds"), MT::End]);
+        assert!(file.insert_before(|t| matches!(t, MT::Texture(_)), tx("b.dds")).is_ok());
+        assert!(file.insert_before(|t| matches!(t, MT::Submaterial(_)), sm("z")).is_ok());
+        assert!(file.insert_before(|t| matches!(t, MT::TextureMtl(_)), sm("x")).is_err());
+
+        assert_eq!(text(&file), "$SUBMATERIAL z\r\n$SUBMATERIAL a\r\n  $TEXTURE 0 b.dds\r\n  $TEXTURE 0 a.dds\r\n$END\r\n");
+    }
+
+    #[test]
+    fn inserted_after_anchor_keep_their_order() {
+        let src = "$SUBMATERIAL a\n$TEXTURE 0 a.dds\n\n$SUBMATERIAL b\n$END";
+        let mut file = mtl(src, vec![sm("a"), tx("a.dds"), sm("b"), MT::End]);
+        assert!(file.insert_after(|t| matches!(t, MT::Texture(_)), tx("1.dds")).is_ok());
+        assert!(file.insert_after(|t| matches!(t, MT::Texture(_)), tx("2.dds")).is_ok());
+        assert!(file.insert_after(|t| matches!(t, MT::Submaterial(p) if p.as_str() == "b"), tx("b.dds")).is_ok());
+
+        assert_eq!(text(&file), "$SUBMATERIAL a\n$TEXTURE 0 a.dds\n$TEXTURE 0 1.dds\n$TEXTURE 0 2.dds\n\n$SUBMATERIAL b\n$TEXTURE 0 b.dds\n$END");
+    }
+
+    #[test]
+    fn inserted_after_last_line_without_eol() {
+        let src = "$SUBMATERIAL a";
+        let mut file = mtl(src, vec![sm("a")]);
+        assert!(file.insert_after(|_| true, tx("a.dds")).is_ok());
+
+        assert_eq!(text(&file), "$SUBMATERIAL a\r\n$TEXTURE 0 a.dds");
+    }
+
+    #[test]
+    fn removed_anchor_is_not_used() {
+        let src = "$SUBMATERIAL a\n$TEXTURE 0 a.dds\n$END\n";
+        let mut file = mtl(src, vec![sm("a"), tx("a.dds"), MT::End]);
+        file.remove_tokens(|t| matches!(t, MT::Texture(_)));
+        assert!(file.insert_after(|t| matches!(t, MT::Texture(_)), tx("b.dds")).is_err());
+        assert!(file.insert_after(|t| matches!(t, MT::Submaterial(_)), tx("b.dds")).is_ok());
+
+        assert_eq!(text(&file), "$SUBMATERIAL a\n$TEXTURE 0 b.dds\n$END\n");
+    }
+
+    #[test]
+    fn removed_and_appended_tokens() {
+        let src = "$SUBMATERIAL a\n$TEXTURE 0 a.dds\n$END";
+        let mut file = mtl(src, vec![sm("a"), tx("a.dds"), MT::End]);
+        file.remove_tokens(|t| matches!(t, MT::Texture(_)));
+        file.append(tx("b.dds"));
+
+        assert_eq!(text(&file), "$SUBMATERIAL a\n$TEXTURE 0 b.dds\n$END");
+    }
+}
@@ -1,6 +1,8 @@
 use std::fmt;
+use std::slice::Iter;
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde_json::Value;
 
 use crate::ini::common::{Point3f, 
                          IdStringParam,
@@ -11,6 +13,9 @@ use crate::ini::common::{Point3f,
                          parse_tokens_with,
                          parse_tokens_strict_with,
                          };
+use crate::ini::json::{JsonParam, JsonToken};
+use crate::ini::schema::{self, SchemaToken};
+use crate::cfg::APP_SETTINGS;
 
 pub type LightColor = (f32, f32, f32);
 
@@ -42,6 +47,9 @@ pub enum Token<'a> {
     Light((Point3f, f32)),
     LightRgb((Point3f, f32, LightColor)),
     LightRgbBlink((Point3f, f32, LightColor)),
+
+    // unknown to the parser, declared in the token schema
+    Schema(SchemaToken<'a>),
 }
 
 impl<'a> Token<'a> {
@@ -73,12 +81,26 @@ impl<'a> Token<'a> {
     const LIGHT_RGB:                 &'static str = "LIGHT_RGB";
     const LIGHT_RGB_BLINK:           &'static str = "LIGHT_RGB_BLICK";
 
+    const NAMES: &'static [&'static str] = &[
+        Self::END, Self::TYPE_STOCK, Self::TYPE_WORKSHOP, Self::MODEL, Self::MODEL_LOD,
+        Self::MODEL_LOD2, Self::MODEL_EMISSIVE, Self::MATERIAL, Self::MATERIAL_EMISSIVE,
+        Self::PLANE_SHADOW, Self::REFLECTION, Self::EXACT_SPECULAR, Self::FIELD_COLLISION,
+        Self::VARIABLE_MATERIAL_PARAMS, Self::SMOKEPOINT_CHANCE, Self::LIFE, Self::EXPLOSION_GROUP,
+        Self::LIGHT, Self::LIGHT_RGB, Self::LIGHT_RGB_BLINK, Self::DERBIS_FALLING_FX,
+        Self::DERBIS_FALLED_FX, Self::DERBIS_FALLED_SFX, Self::DERBIS_NUM, Self::DERBIS_SCALE,
+        Self::DERBIS_MESH, Self::DERBIS_FALLING_FX_MAXTIME
+    ];
+
     fn parse(src: &'a str) -> ParseResult<Self> {
+        schema::parse_declared(Self::parse_builtin(src), &APP_SETTINGS.schema.renderconfig, src, Self::Schema)
+    }
+
+    pub(crate) fn parse_builtin(src: &'a str) -> ParseResult<Self> {
         lazy_static! {
             static ref RX_TYPE: Regex = Regex::new(r"^(\$?[0-9A-Z_]+)(\s+(.+))?$").unwrap();
         }
 
-        let (t_type, rest) = chop_param(Some(src), &RX_TYPE).map_err(|e| format!("Cannot parse token type: {}", e))?;
+        let (t_type, rest) = chop_param(Some(src), &RX_TYPE).map_err(|e| e.context("Cannot parse token type"))?;
         macro_rules! parse {
             ($id:ident, $t:ty) => {
                 <$t>::parse(rest).map(|(p, rest)| (Self::$id(p), rest))
@@ -116,7 +138,7 @@ impl<'a> Token<'a> {
             Self::DERBIS_SCALE              => parse!(DerbisScale,        f32),
             Self::DERBIS_MESH               => parse!(DerbisMesh,         (IdStringParam, IdStringParam)),
             Self::DERBIS_FALLING_FX_MAXTIME => parse!(DerbisFallingFxMaxTime, f32),
-            _ => Err(format!("Unknown token type: \"{}\"", t_type))
+            _ => Err(ParseError::unknown("token type", t_type, Self::NAMES))
         }
     }
 }
@@ -152,6 +174,7 @@ impl fmt::Display for Token<'_> {
             Self::Light((pt, x))              => write!(f, "{} {} {}", Self::LIGHT, pt, x),
             Self::LightRgb((pt, x, col))      => write!(f, "{} {} {} {:?}", Self::LIGHT_RGB, pt, x, col),
             Self::LightRgbBlink((pt, x, col)) => write!(f, "{} {} {} {:?}", Self::LIGHT_RGB_BLINK, pt, x, col),
+            Self::Schema(t)                   => write!(f, "{}", t),
         }
     }
 }
@@ -163,9 +186,112 @@ impl super::IniToken for Token<'_> {
             Self::Light((pt, x))                    => write!(wr, "{} {} {} {} {}", Self::LIGHT, pt.x, pt.y, pt.z, x),
             Self::LightRgb((pt, x, (r, g, b)))      => write!(wr, "{} {} {} {} {} {} {} {}", Self::LIGHT_RGB, pt.x, pt.y, pt.z, x, r, g, b),
             Self::LightRgbBlink((pt, x, (r, g, b))) => write!(wr, "{} {} {} {} {} {} {} {}", Self::LIGHT_RGB_BLINK, pt.x, pt.y, pt.z, x, r, g, b),
+            Self::Schema(t) => t.serialize(wr, false),
             s => write!(wr, "{}", s)
         }
     }
+
+    fn is_end(&self) -> bool {
+        matches!(self, Self::End)
+    }
+}
+
+
+impl<'a> JsonToken<'a> for Token<'a> {
+    fn to_json(&self) -> (String, Vec<Value>) {
+        let mut params = Vec::new();
+        macro_rules! json {
+            ($kw:expr, $p:expr) => {{
+                $p.write_json(&mut params);
+                $kw.to_string()
+            }};
+            ($kw:expr) => {
+                $kw.to_string()
+            };
+        }
+
+        let keyword = match self {
+            Self::End                         => json!(Self::END),
+            Self::ObjectTypeStock(p)          => json!(Self::TYPE_STOCK, p),
+            Self::ObjectTypeWorkshop          => json!(Self::TYPE_WORKSHOP),
+            Self::Model(p)                    => json!(Self::MODEL, p),
+            Self::ModelLod(p)                 => json!(Self::MODEL_LOD, p),
+            Self::ModelLod2(p)                => json!(Self::MODEL_LOD2, p),
+            Self::ModelEmissive(p)            => json!(Self::MODEL_EMISSIVE, p),
+            Self::Material(p)                 => json!(Self::MATERIAL, p),
+            Self::MaterialEmissive(p)         => json!(Self::MATERIAL_EMISSIVE, p),
+            Self::PlaneShadow                 => json!(Self::PLANE_SHADOW),
+            Self::Reflection                  => json!(Self::REFLECTION),
+            Self::ExactSpecular               => json!(Self::EXACT_SPECULAR),
+            Self::FieldCollision              => json!(Self::FIELD_COLLISION),
+            Self::VariableMaterialParams      => json!(Self::VARIABLE_MATERIAL_PARAMS),
+            Self::SmokePointChance(p)         => json!(Self::SMOKEPOINT_CHANCE, p),
+            Self::Life(p)                     => json!(Self::LIFE, p),
+            Self::ExplosionGroup(p)           => json!(Self::EXPLOSION_GROUP, p),
+            Self::Light(p)                    => json!(Self::LIGHT, p),
+            Self::LightRgb(p)                 => json!(Self::LIGHT_RGB, p),
+            Self::LightRgbBlink(p)            => json!(Self::LIGHT_RGB_BLINK, p),
+            Self::DerbisFallingFx(p)          => json!(Self::DERBIS_FALLING_FX, p),
+            Self::DerbisFalledFx(p)           => json!(Self::DERBIS_FALLED_FX, p),
+            Self::DerbisFalledSfx(p)          => json!(Self::DERBIS_FALLED_SFX, p),
+            Self::DerbisNum(p)                => json!(Self::DERBIS_NUM, p),
+            Self::DerbisScale(p)              => json!(Self::DERBIS_SCALE, p),
+            Self::DerbisMesh(p)               => json!(Self::DERBIS_MESH, p),
+            Self::DerbisFallingFxMaxTime(p)   => json!(Self::DERBIS_FALLING_FX_MAXTIME, p),
+            Self::Schema(t)                   => json!(t.keyword, t),
+        };
+
+        (keyword, params)
+    }
+
+    fn from_json(keyword: &'a str, params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        schema::from_json_declared(&APP_SETTINGS.schema.renderconfig, keyword, params, Self::builtin_from_json, Self::Schema)
+    }
+}
+
+
+impl<'a> Token<'a> {
+    fn builtin_from_json(keyword: &str, params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        macro_rules! read {
+            ($id:ident, $t:ty) => {
+                <$t>::read_json(params).map(Self::$id)
+            };
+            ($id:ident) => {
+                Ok(Self::$id)
+            };
+        }
+
+        match keyword {
+            Self::END                         => read!(End),
+            Self::TYPE_STOCK                  => read!(ObjectTypeStock,           IdStringParam),
+            Self::TYPE_WORKSHOP               => read!(ObjectTypeWorkshop),
+            Self::MODEL                       => read!(Model,                     IdStringParam),
+            Self::MODEL_LOD                   => read!(ModelLod,                  (IdStringParam, f32)),
+            Self::MODEL_LOD2                  => read!(ModelLod2,                 (IdStringParam, f32)),
+            Self::MODEL_EMISSIVE              => read!(ModelEmissive,             IdStringParam),
+            Self::MATERIAL                    => read!(Material,                  IdStringParam),
+            Self::MATERIAL_EMISSIVE           => read!(MaterialEmissive,          IdStringParam),
+            Self::PLANE_SHADOW                => read!(PlaneShadow),
+            Self::REFLECTION                  => read!(Reflection),
+            Self::EXACT_SPECULAR              => read!(ExactSpecular),
+            Self::FIELD_COLLISION             => read!(FieldCollision),
+            Self::VARIABLE_MATERIAL_PARAMS    => read!(VariableMaterialParams),
+            Self::SMOKEPOINT_CHANCE           => read!(SmokePointChance,          f32),
+            Self::LIFE                        => read!(Life,                      f32),
+            Self::EXPLOSION_GROUP             => read!(ExplosionGroup,            u32),
+            Self::LIGHT                       => read!(Light,                     (Point3f, f32)),
+            Self::LIGHT_RGB                   => read!(LightRgb,                  (Point3f, f32, LightColor)),
+            Self::LIGHT_RGB_BLINK             => read!(LightRgbBlink,             (Point3f, f32, LightColor)),
+            Self::DERBIS_FALLING_FX           => read!(DerbisFallingFx,           (IdStringParam, f32)),
+            Self::DERBIS_FALLED_FX            => read!(DerbisFalledFx,            (IdStringParam, f32)),
+            Self::DERBIS_FALLED_SFX           => read!(DerbisFalledSfx,           IdStringParam),
+            Self::DERBIS_NUM                  => read!(DerbisNum,                 u32),
+            Self::DERBIS_SCALE                => read!(DerbisScale,               f32),
+            Self::DERBIS_MESH                 => read!(DerbisMesh,                (IdStringParam, IdStringParam)),
+            Self::DERBIS_FALLING_FX_MAXTIME   => read!(DerbisFallingFxMaxTime,    f32),
+            _ => Err(ParseError::unknown("token type", keyword, Self::NAMES).to_string())
+        }
+    }
 }
 
 
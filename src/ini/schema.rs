@@ -0,0 +1,572 @@
+// Token schema: user-declared extra tokens. Tokens known to the parser are parsed, written,
+// transformed and linted by their own code; the embedded schema (schema.json) only describes
+// them for the token reference (`tokens`). A schema file given with --schema declares tokens
+// which the parser does not know, or redeclares known ones (a declaration with the same token
+// and tag replaces the embedded one).
+//
+// Declared tokens which the parser rejects (unknown ones, or known ones with different parameters,
+// e.g. after a game patch) are parsed, written, transformed and exported to JSON by their declaration:
+//
+// {
+//   "building": [
+//     {"token": "$HEATING_POWER", "params": ["float"], "group": "economy", "once": true},
+//     {"token": "$CONNECTION_", "tag": "connection-2p", "params": ["point", "point"]}
+//   ],
+//   "renderconfig": [{"token": "LIGHT_SPOT", "params": ["point", "float", "float"]}],
+//   "mtl": []
+// }
+
+use std::fmt::{self, Display, Formatter};
+use std::io::Write;
+use std::path::Path;
+use std::slice::Iter;
+
+use const_format::concatcp;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use super::common::{ParseSlice,
+                    ParseError,
+                    ParseResult,
+                    Point3f,
+                    Rect,
+                    QuotedStringParam,
+                    IdStringParam,
+                    CostKeywordParam,
+                    RX_REMAINDER,
+                    chop_param,
+                    parse_literal,
+                   };
+use super::json::JsonParam;
+use super::building::{TokenGroup, LITERALS};
+
+
+// description of the parser's tokens for the token reference,
+// kept in sync with the parser and the duplicate-token lint by the tests at the end of the file
+const EMBEDDED: &str = include_str!("schema.json");
+
+
+// Literal parameter type: (name, kind, values), see building::LITERALS
+pub type Literal = (&'static str, &'static str, &'static [&'static str]);
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ParamType {
+    Int,
+    Float,
+    // distance, scaled with the building
+    Length,
+    // x coordinate, scaled and moved with the building
+    X,
+    Point,
+    Rect,
+    // text without whitespace: node name, path, ...
+    Id,
+    // quoted text
+    Str,
+    // cost keyword ($...)
+    Keyword,
+    Literal(&'static Literal),
+}
+
+
+pub struct TokenDef {
+    // keyword as it is written in the file (without the '$' of building.ini);
+    // for tagged tokens, the part in front of the tag ("CONNECTION_")
+    pub token: String,
+    pub tag: Option<&'static Literal>,
+    pub params: Vec<ParamType>,
+    pub group: Option<TokenGroup>,
+    // expected at most once in a file
+    pub once: bool,
+    pub description: Option<String>,
+    // declared in the embedded schema: a description of a token handled by the parser itself
+    pub builtin: bool,
+}
+
+
+pub struct Schema {
+    pub building: Vec<TokenDef>,
+    pub renderconfig: Vec<TokenDef>,
+    pub mtl: Vec<TokenDef>,
+}
+
+
+impl ParamType {
+    const BASIC: [(&'static str, ParamType); 9] = [
+        ("int", Self::Int), ("float", Self::Float), ("length", Self::Length), ("x", Self::X),
+        ("point", Self::Point), ("rect", Self::Rect), ("id", Self::Id), ("string", Self::Str),
+        ("keyword", Self::Keyword)
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::BASIC.iter()
+                   .find(|(n, _)| *n == name)
+                   .map(|(_, t)| *t)
+                   .or_else(|| LITERALS.iter().find(|l| l.0 == name).map(Self::Literal))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Literal((name, _, _)) => name,
+            t => Self::BASIC.iter().find(|(_, b)| b == t).map(|(n, _)| *n).unwrap()
+        }
+    }
+
+    fn names() -> &'static [&'static str] {
+        lazy_static! {
+            static ref NAMES: Vec<&'static str> = ParamType::BASIC.iter().map(|(n, _)| *n)
+                                                                  .chain(LITERALS.iter().map(|l| l.0))
+                                                                  .collect();
+        }
+
+        &NAMES
+    }
+}
+
+
+impl Display for ParamType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+
+impl Schema {
+    pub fn load(path: Option<&Path>) -> Result<Schema, String> {
+        let mut schema = Self::from_json(EMBEDDED, true).expect("Embedded token schema is broken");
+        if let Some(path) = path {
+            let src = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+            let file = Self::from_json(&src, false).map_err(|e| format!("{}: {}", path.display(), e))?;
+            merge(&mut schema.building, file.building);
+            merge(&mut schema.renderconfig, file.renderconfig);
+            merge(&mut schema.mtl, file.mtl);
+        }
+
+        Ok(schema)
+    }
+
+    fn from_json(src: &str, builtin: bool) -> Result<Schema, String> {
+        let doc: Value = serde_json::from_str(src).map_err(|e| format!("Invalid JSON: {}", e))?;
+        let doc = doc.as_object().ok_or_else(|| "Expected an object with \"building\", \"renderconfig\" and \"mtl\" arrays".to_string())?;
+        if let Some(k) = doc.keys().find(|k| !["building", "renderconfig", "mtl"].contains(&k.as_str())) {
+            return Err(format!("Unexpected key \"{}\"", k));
+        }
+
+        let section = |name: &str, prefix: &str| -> Result<Vec<TokenDef>, String> {
+            let defs: &[Value] = match doc.get(name) {
+                Some(Value::Array(xs)) => xs,
+                Some(_)                => return Err(format!("\"{}\" must be an array", name)),
+                None                   => &[],
+            };
+
+            defs.iter().enumerate().map(|(i, d)| {
+                TokenDef::from_json(d, prefix, builtin).map_err(|e| format!("{}, declaration #{}: {}", name, i + 1, e))
+            }).collect()
+        };
+
+        Ok(Schema {
+            building:     section("building", "$")?,
+            renderconfig: section("renderconfig", "")?,
+            mtl:          section("mtl", "")?,
+        })
+    }
+}
+
+
+fn merge(defs: &mut Vec<TokenDef>, new: Vec<TokenDef>) {
+    for d in new.into_iter() {
+        match defs.iter_mut().find(|x| x.token == d.token && x.tag == d.tag) {
+            Some(x) => *x = d,
+            None => defs.push(d)
+        }
+    }
+}
+
+
+// Declaration of the keyword: an exact one, or a tagged one with a valid tag
+pub fn find<'s>(defs: &'s [TokenDef], keyword: &str) -> Option<&'s TokenDef> {
+    defs.iter()
+        .find(|d| d.tag.is_none() && d.token == keyword)
+        .or_else(|| defs.iter().find(|d| match (d.tag, keyword.strip_prefix(d.token.as_str())) {
+            (Some((_, _, names)), Some(tag)) => names.contains(&tag),
+            _ => false
+        }))
+}
+
+
+impl TokenDef {
+    fn from_json(v: &Value, prefix: &str, builtin: bool) -> Result<TokenDef, String> {
+        const KEYS: [&str; 6] = ["token", "tag", "params", "group", "once", "description"];
+
+        let obj: &Map<String, Value> = v.as_object().ok_or_else(|| format!("Expected an object, found {}", v))?;
+        if let Some(k) = obj.keys().find(|k| !KEYS.contains(&k.as_str())) {
+            return Err(format!("Unexpected key \"{}\"", k));
+        }
+
+        let token = obj.get("token").and_then(Value::as_str).ok_or_else(|| "\"token\" must be a string".to_string())?;
+        let name = token.strip_prefix(prefix).ok_or_else(|| format!("{}: token must start with '{}'", token, prefix))?;
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("Invalid token name \"{}\"", token));
+        }
+
+        let type_of = |v: &Value| -> Result<ParamType, String> {
+            let s = v.as_str().ok_or_else(|| format!("Expected a type name, found {}", v))?;
+            ParamType::from_name(s).ok_or_else(|| ParseError::unknown("parameter type", s, ParamType::names()).to_string())
+        };
+
+        let tag = match obj.get("tag") {
+            None => None,
+            Some(v) => match type_of(v) {
+                Ok(ParamType::Literal(l)) => Some(l),
+                Ok(t) => return Err(format!("{}: tag must be a literal type, found '{}'", token, t)),
+                Err(e) => return Err(format!("{}: {}", token, e))
+            }
+        };
+
+        let params = match obj.get("params") {
+            None => Vec::new(),
+            Some(Value::Array(xs)) => xs.iter().map(type_of).collect::<Result<_, _>>().map_err(|e| format!("{}: {}", token, e))?,
+            Some(_) => return Err(format!("{}: \"params\" must be an array", token)),
+        };
+
+        let group = match obj.get("group") {
+            None => None,
+            Some(v) => {
+                let s = v.as_str().ok_or_else(|| format!("{}: \"group\" must be a string", token))?;
+                let g = TokenGroup::from_name(s).ok_or_else(|| format!("{}: {}", token, ParseError::unknown("token group", s, TokenGroup::NAMES)))?;
+                Some(g)
+            }
+        };
+
+        let once = match obj.get("once") {
+            None => false,
+            Some(v) => v.as_bool().ok_or_else(|| format!("{}: \"once\" must be true or false", token))?
+        };
+
+        let description = match obj.get("description") {
+            None => None,
+            Some(v) => Some(v.as_str().ok_or_else(|| format!("{}: \"description\" must be a string", token))?.to_string())
+        };
+
+        Ok(TokenDef { token: name.to_string(), tag, params, group, once, description, builtin })
+    }
+}
+
+
+//-------------------------------------------------------------------
+
+
+// Token parsed by its schema declaration
+pub struct SchemaToken<'a> {
+    // full keyword, including the tag
+    pub keyword: &'a str,
+    pub def: &'static TokenDef,
+    pub params: Vec<SchemaValue<'a>>,
+}
+
+
+#[derive(Clone)]
+pub enum SchemaValue<'a> {
+    Int(u32),
+    // also length and x
+    Float(f32),
+    Point(Point3f),
+    Rect(Rect),
+    Id(IdStringParam<'a>),
+    Str(QuotedStringParam<'a>),
+    Keyword(CostKeywordParam<'a>),
+    Literal(&'static str),
+}
+
+
+lazy_static! {
+    static ref RX_WORD: Regex = Regex::new(concatcp!(r"(?s)^([^[:space:]]+)", RX_REMAINDER)).unwrap();
+}
+
+
+// Tokens which the built-in parser rejects, or parses incompletely, are parsed by their declaration
+// from the schema file. Declarations from the embedded schema only describe the parser, so its error is kept.
+pub fn parse_declared<'a, T, F>(builtin: ParseResult<'a, T>, defs: &'static [TokenDef], src: &'a str, wrap: F) -> ParseResult<'a, T>
+where F: FnOnce(SchemaToken<'a>) -> T
+{
+    if let Ok((_, rest)) = &builtin {
+        if matches!(rest, None | Some("")) {
+            return builtin;
+        }
+    }
+
+    match chop_param(Some(src), &RX_WORD).ok().and_then(|(kw, _)| find(defs, kw)) {
+        Some(def) if !def.builtin => SchemaToken::parse(def, src).map(|(t, rest)| (wrap(t), rest)),
+        _ => builtin
+    }
+}
+
+
+// Same for tokens read from JSON
+pub fn from_json_declared<'a, T, B, F>(defs: &'static [TokenDef], keyword: &'a str, params: &mut Iter<'a, Value>, builtin: B, wrap: F) -> Result<T, String>
+where B: FnOnce(&'a str, &mut Iter<'a, Value>) -> Result<T, String>,
+      F: FnOnce(SchemaToken<'a>) -> T
+{
+    let start = params.clone();
+    let result = builtin(keyword, params);
+    if result.is_ok() && params.len() == 0 {
+        return result;
+    }
+
+    match find(defs, keyword) {
+        Some(def) if !def.builtin => {
+            *params = start;
+            SchemaToken::from_json(def, keyword, params).map(wrap)
+        },
+        _ => result
+    }
+}
+
+
+impl<'a> SchemaToken<'a> {
+    pub fn parse(def: &'static TokenDef, src: &'a str) -> ParseResult<'a, Self> {
+        let (keyword, mut rest) = chop_param(Some(src), &RX_WORD)?;
+        let mut params = Vec::with_capacity(def.params.len());
+        for t in def.params.iter() {
+            let (v, r) = SchemaValue::parse(*t, rest).map_err(|e| e.context(&format!("{} ({})", keyword, t)))?;
+            params.push(v);
+            rest = r;
+        }
+
+        Ok((SchemaToken { keyword, def, params }, rest))
+    }
+
+    pub fn from_json(def: &'static TokenDef, keyword: &'a str, params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        let values = def.params.iter()
+                               .map(|t| SchemaValue::read_json(*t, params))
+                               .collect::<Result<_, _>>()?;
+        Ok(SchemaToken { keyword, def, params: values })
+    }
+
+    pub fn write_json(&self, params: &mut Vec<Value>) {
+        for v in self.params.iter() {
+            match v {
+                SchemaValue::Int(n)     => n.write_json(params),
+                SchemaValue::Float(x)   => x.write_json(params),
+                SchemaValue::Point(p)   => p.write_json(params),
+                SchemaValue::Rect(r)    => r.write_json(params),
+                SchemaValue::Id(s)      => s.write_json(params),
+                SchemaValue::Str(s)     => s.write_json(params),
+                SchemaValue::Keyword(k) => k.write_json(params),
+                SchemaValue::Literal(s) => params.push(Value::String(s.to_string())),
+            }
+        }
+    }
+
+    // In building.ini (multiline) tokens are written like the built-in ones: coordinates with fixed
+    // precision, points and rectangles on their own lines unless other values follow them
+    pub fn serialize<W: Write>(&self, mut wr: W, multiline: bool) -> std::io::Result<()> {
+        const PREC: usize = 4;
+
+        let is_geometry = |v: &SchemaValue| matches!(v, SchemaValue::Point(_) | SchemaValue::Rect(_));
+        let first = self.params.iter().position(is_geometry).unwrap_or(self.params.len());
+        let own_lines = multiline && self.params[first ..].iter().all(is_geometry);
+
+        write!(wr, "{}", self.keyword)?;
+        for v in self.params.iter() {
+            match v {
+                SchemaValue::Point(p) if own_lines => write!(wr, "\r\n{:.prec$} {:.prec$} {:.prec$}", p.x, p.y, p.z, prec = PREC)?,
+                SchemaValue::Rect(r) if own_lines  => write!(wr, "\r\n{:.prec$} {:.prec$}\r\n{:.prec$} {:.prec$}", r.x1, r.z1, r.x2, r.z2, prec = PREC)?,
+                SchemaValue::Point(p) if multiline => write!(wr, " {:.prec$} {:.prec$} {:.prec$}", p.x, p.y, p.z, prec = PREC)?,
+                SchemaValue::Rect(r) if multiline  => write!(wr, " {:.prec$} {:.prec$} {:.prec$} {:.prec$}", r.x1, r.z1, r.x2, r.z2, prec = PREC)?,
+                SchemaValue::Point(p) => write!(wr, " {} {} {}", p.x, p.y, p.z)?,
+                SchemaValue::Rect(r)  => write!(wr, " {} {} {} {}", r.x1, r.z1, r.x2, r.z2)?,
+                v => write!(wr, " {}", v)?
+            }
+        }
+
+        Ok(())
+    }
+
+    // Copy of the token with the values changed by f (along with their declared types).
+    // None if f changes nothing.
+    pub fn map_values<F>(&self, mut f: F) -> Option<Self>
+    where F: FnMut(ParamType, &SchemaValue<'a>) -> Option<SchemaValue<'a>>
+    {
+        let mut changed = false;
+        let params = self.def.params.iter().zip(self.params.iter()).map(|(t, v)| match f(*t, v) {
+            Some(x) => { changed = true; x },
+            None => v.clone()
+        }).collect();
+
+        if changed {
+            Some(SchemaToken { keyword: self.keyword, def: self.def, params })
+        } else {
+            None
+        }
+    }
+}
+
+
+impl<'a> SchemaValue<'a> {
+    fn parse(t: ParamType, src: Option<&'a str>) -> ParseResult<'a, Self> {
+        macro_rules! parse {
+            ($id:ident, $t:ty) => {
+                <$t>::parse(src).map(|(v, rest)| (Self::$id(v), rest))
+            };
+        }
+
+        match t {
+            ParamType::Int                              => parse!(Int,     u32),
+            ParamType::Float | ParamType::Length
+            | ParamType::X                              => parse!(Float,   f32),
+            ParamType::Point                            => parse!(Point,   Point3f),
+            ParamType::Rect                             => parse!(Rect,    Rect),
+            ParamType::Id                               => parse!(Id,      IdStringParam),
+            ParamType::Str                              => parse!(Str,     QuotedStringParam),
+            ParamType::Keyword                          => parse!(Keyword, CostKeywordParam),
+            ParamType::Literal((_, kind, names))        => {
+                parse_literal(src, &RX_WORD, kind, names, |s| names.iter().find(|n| **n == s).copied())
+                    .map(|(v, rest)| (Self::Literal(v), rest))
+            }
+        }
+    }
+
+    fn read_json(t: ParamType, params: &mut Iter<'a, Value>) -> Result<Self, String> {
+        macro_rules! read {
+            ($id:ident, $t:ty) => {
+                <$t>::read_json(params).map(Self::$id)
+            };
+        }
+
+        match t {
+            ParamType::Int                              => read!(Int,     u32),
+            ParamType::Float | ParamType::Length
+            | ParamType::X                              => read!(Float,   f32),
+            ParamType::Point                            => read!(Point,   Point3f),
+            ParamType::Rect                             => read!(Rect,    Rect),
+            ParamType::Id                               => read!(Id,      IdStringParam),
+            ParamType::Str                              => read!(Str,     QuotedStringParam),
+            ParamType::Keyword                          => read!(Keyword, CostKeywordParam),
+            ParamType::Literal((_, kind, names))        => {
+                let s = IdStringParam::read_json(params)?.to_string();
+                names.iter()
+                     .find(|n| **n == s)
+                     .map(|n| Self::Literal(n))
+                     .ok_or_else(|| ParseError::unknown(kind, &s, names).to_string())
+            }
+        }
+    }
+}
+
+
+impl Display for SchemaToken<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.keyword)?;
+        for v in self.params.iter() {
+            write!(f, " {}", v)?;
+        }
+        Ok(())
+    }
+}
+
+
+impl Display for SchemaValue<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Int(n)     => write!(f, "{}", n),
+            Self::Float(x)   => write!(f, "{}", x),
+            Self::Point(p)   => write!(f, "{}", p),
+            Self::Rect(r)    => write!(f, "{}", r),
+            Self::Id(s)      => write!(f, "{}", s),
+            Self::Str(s)     => write!(f, "{}", s),
+            Self::Keyword(k) => write!(f, "{}", k),
+            Self::Literal(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+
+
+// The embedded schema describes what the built-in parser knows: every declaration must be parsed
+// by the parser alone, and its "once" flag must agree with the duplicate-token lint
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ini::{building, renderconfig, material};
+
+    // The token with a valid value of every parameter, for each tag
+    fn samples(def: &TokenDef) -> Vec<String> {
+        let params: String = def.params.iter().map(|t| match t {
+            ParamType::Int                                      => " 1".to_string(),
+            ParamType::Float | ParamType::Length | ParamType::X => " 1.5".to_string(),
+            ParamType::Point                                    => " 1 2 3".to_string(),
+            ParamType::Rect                                     => " 1 2 3 4".to_string(),
+            ParamType::Id                                       => " name".to_string(),
+            ParamType::Str                                      => " \"name\"".to_string(),
+            ParamType::Keyword                                  => " $NAME".to_string(),
+            ParamType::Literal((_, _, names))                   => format!(" {}", names[0]),
+        }).collect();
+
+        match def.tag {
+            Some((_, _, tags)) => tags.iter().map(|tag| format!("{}{}{}", def.token, tag, params)).collect(),
+            None               => vec![format!("{}{}", def.token, params)],
+        }
+    }
+
+    fn embedded() -> Schema {
+        Schema::from_json(EMBEDDED, true).unwrap()
+    }
+
+    fn is_complete<T>(r: &ParseResult<T>) -> bool {
+        matches!(r, Ok((_, None)) | Ok((_, Some(""))))
+    }
+
+    #[test]
+    fn building_declarations_match_parser() {
+        let mut errors = Vec::new();
+        for def in embedded().building.iter() {
+            for src in samples(def) {
+                match building::Token::parse_builtin(&src) {
+                    r @ Ok(_) if !is_complete(&r) => errors.push(format!("${}: parameters left over", src)),
+                    Ok((t, _)) => {
+                        let once = matches!(building::lint::duplicate_key(&t), Some((_, None)));
+                        if def.once != once {
+                            errors.push(format!("${}: \"once\" is {}, the lint expects {}", src, def.once, once));
+                        }
+                    },
+                    Err(e) => errors.push(format!("${}: {}", src, e)),
+                }
+            }
+        }
+
+        assert!(errors.is_empty(), "\n{}", errors.join("\n"));
+    }
+
+    #[test]
+    fn renderconfig_declarations_match_parser() {
+        let mut errors = Vec::new();
+        for def in embedded().renderconfig.iter() {
+            for src in samples(def) {
+                let r = renderconfig::Token::parse_builtin(&src);
+                if !is_complete(&r) {
+                    errors.push(format!("{}: {}", src, r.err().map_or("parameters left over".to_string(), |e| e.to_string())));
+                }
+            }
+        }
+
+        assert!(errors.is_empty(), "\n{}", errors.join("\n"));
+    }
+
+    #[test]
+    fn mtl_declarations_match_parser() {
+        let mut errors = Vec::new();
+        for def in embedded().mtl.iter() {
+            for src in samples(def) {
+                let r = material::Token::parse_builtin(&src);
+                if !is_complete(&r) {
+                    errors.push(format!("{}: {}", src, r.err().map_or("parameters left over".to_string(), |e| e.to_string())));
+                }
+            }
+        }
+
+        assert!(errors.is_empty(), "\n{}", errors.join("\n"));
+    }
+}
@@ -0,0 +1,251 @@
+// Token reference for the `tokens` command, built from the token schema
+// (so it also covers the tokens declared with --schema)
+
+use std::fmt::Write;
+
+use super::schema::{Schema, TokenDef, SchemaToken, SchemaValue, ParamType};
+use super::common::{Point3f, Rect, StrValue, IdStringParam, QuotedStringParam, CostKeywordParam};
+use super::building::LITERALS;
+use super::diagnostic;
+
+
+// (name on the command line, file name, keyword prefix)
+pub const FILES: [(&str, &str, &str); 3] = [
+    ("building",     "building.ini",     "$"),
+    ("renderconfig", "renderconfig.ini", ""),
+    ("mtl",          "*.mtl",            ""),
+];
+
+const WIDTH: usize = 100;
+
+
+fn defs<'s>(schema: &'s Schema, file: &str) -> &'s [TokenDef] {
+    match file {
+        "building"     => &schema.building,
+        "renderconfig" => &schema.renderconfig,
+        _              => &schema.mtl,
+    }
+}
+
+
+// Keyword as it is looked up: without the leading '$', uppercase
+fn normalize(s: &str) -> String {
+    s.trim_start_matches('$').to_ascii_uppercase()
+}
+
+
+pub fn signature(def: &TokenDef, prefix: &str) -> String {
+    let mut s = format!("{}{}", prefix, def.token);
+    if let Some((name, _, _)) = def.tag {
+        write!(s, "<{}>", name).unwrap();
+    }
+
+    for p in def.params.iter() {
+        write!(s, " <{}>", p).unwrap();
+    }
+
+    s
+}
+
+
+// Sample token with plausible values, as it would be written in the file
+fn example(def: &'static TokenDef, prefix: &str, tag: Option<&str>) -> String {
+    let keyword = format!("{}{}", def.token, tag.or_else(|| def.tag.map(|(_, _, values)| values[0])).unwrap_or(""));
+
+    let mut points = 0;
+    let params = def.params.iter().map(|t| match t {
+        ParamType::Int     => SchemaValue::Int(1),
+        ParamType::Float   => SchemaValue::Float(1.0),
+        ParamType::Length  => SchemaValue::Float(10.0),
+        ParamType::X       => SchemaValue::Float(5.0),
+        ParamType::Point   => {
+            points += 1;
+            SchemaValue::Point(Point3f { x: 0.0, y: 0.0, z: 10.0 * (points - 1) as f32 })
+        },
+        ParamType::Rect    => SchemaValue::Rect(Rect { x1: -10.0, z1: -10.0, x2: 10.0, z2: 10.0 }),
+        ParamType::Id      => SchemaValue::Id(IdStringParam::new_borrowed("name")),
+        ParamType::Str     => SchemaValue::Str(QuotedStringParam(StrValue::Borrowed("Name"))),
+        ParamType::Keyword => SchemaValue::Keyword(CostKeywordParam(IdStringParam::new_borrowed("keyword"))),
+        ParamType::Literal((_, _, values)) => SchemaValue::Literal(values[0]),
+    }).collect();
+
+    let token = SchemaToken { keyword: &keyword, def, params };
+    let mut buf = Vec::new();
+    token.serialize(&mut buf, prefix == "$").unwrap();
+
+    format!("{}{}", prefix, String::from_utf8(buf).unwrap().replace("\r\n", "\n"))
+}
+
+
+// Writes "label: a, b, c" wrapped to WIDTH, continuation lines indented
+fn write_wrapped<'v, I>(out: &mut String, indent: &str, label: &str, values: I)
+where I: IntoIterator<Item = &'v str>
+{
+    let mut line = format!("{}{}:", indent, label);
+    let mut first = true;
+    for v in values {
+        let sep = if first { " " } else { ", " };
+        if !first && line.len() + sep.len() + v.len() > WIDTH {
+            writeln!(out, "{},", line).unwrap();
+            line = format!("{}    {}", indent, v);
+        } else {
+            line.push_str(sep);
+            line.push_str(v);
+        }
+        first = false;
+    }
+
+    writeln!(out, "{}", line).unwrap();
+}
+
+
+pub fn list(schema: &Schema, file: Option<&str>, filter: Option<&str>) -> String {
+    let filter = filter.map(normalize);
+    let matches = |s: &str| match filter.as_ref() {
+        Some(f) => normalize(s).contains(f.as_str()),
+        None => true
+    };
+
+    let mut out = String::new();
+    for (name, file_name, prefix) in FILES.iter().filter(|(n, _, _)| file.is_none() || file == Some(*n)) {
+        let lines: Vec<String> = defs(schema, name).iter()
+            .filter(|d| matches(&d.token) || d.tag.iter().any(|(_, _, values)| values.iter().any(|v| matches(&format!("{}{}", d.token, v)))))
+            .map(|d| if d.builtin { signature(d, prefix) } else { format!("{}    (schema file)", signature(d, prefix)) })
+            .collect();
+
+        if !lines.is_empty() {
+            writeln!(out, "{}:", file_name).unwrap();
+            for l in lines {
+                writeln!(out, "  {}", l).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+    }
+
+    if file.is_none() || file == Some("building") {
+        let mut header = false;
+        for (name, _, values) in LITERALS.iter() {
+            let values: Vec<&str> = values.iter().copied().filter(|v| matches(v)).collect();
+            if values.is_empty() {
+                continue;
+            }
+
+            if !header {
+                writeln!(out, "Literals:").unwrap();
+                header = true;
+            }
+            write_wrapped(&mut out, "  ", &format!("<{}>", name), values);
+        }
+    }
+
+    out
+}
+
+
+pub fn explain(schema: &'static Schema, query: &str) -> Result<String, String> {
+    let q = normalize(query);
+    let mut out = String::new();
+
+    for (name, file_name, prefix) in FILES.iter() {
+        let defs = defs(schema, name);
+        // "CONNECTION_" also brings up the untagged tokens of the family, like $CONNECTION_RAIL_DEADEND
+        let family = defs.iter().any(|d| d.tag.is_some() && normalize(&d.token) == q);
+
+        for def in defs.iter() {
+            let token = normalize(&def.token);
+            let tag = match def.tag {
+                None if token == q || (family && token.starts_with(&q)) => None,
+                Some(_) if token == q => None,
+                Some((_, _, values)) => match q.strip_prefix(token.as_str()).and_then(|t| values.iter().find(|v| v.eq_ignore_ascii_case(t))) {
+                    Some(v) => Some(*v),
+                    None => continue
+                },
+                None => continue
+            };
+
+            explain_def(&mut out, def, file_name, prefix, tag);
+        }
+    }
+
+    if let Some((name, descr, values)) = LITERALS.iter().find(|(n, _, _)| n.eq_ignore_ascii_case(query.trim_matches(|c| c == '<' || c == '>'))) {
+        writeln!(out, "<{}> ({}):", name, descr).unwrap();
+        write_wrapped(&mut out, "  ", "values", values.iter().copied());
+        writeln!(out).unwrap();
+    }
+
+    for (name, _, values) in LITERALS.iter() {
+        if let Some(v) = values.iter().find(|v| v.eq_ignore_ascii_case(query)) {
+            writeln!(out, "{} is a value of <{}>\n", v, name).unwrap();
+        }
+    }
+
+    if out.is_empty() {
+        let mut msg = format!("Unknown token '{}'", query);
+        if let Some(s) = suggest(schema, &q) {
+            write!(msg, ", did you mean '{}'?", s).unwrap();
+        }
+        return Err(msg);
+    }
+
+    Ok(out)
+}
+
+
+fn explain_def(out: &mut String, def: &'static TokenDef, file_name: &str, prefix: &str, tag: Option<&str>) {
+    match tag {
+        Some(tag) => {
+            let sig = signature(def, prefix);
+            let (_, params) = sig.split_at(sig.find('>').unwrap() + 1);
+            writeln!(out, "{}{}{}{}", prefix, def.token, tag, params).unwrap();
+        },
+        None => writeln!(out, "{}", signature(def, prefix)).unwrap()
+    }
+
+    write!(out, "  file: {}", file_name).unwrap();
+    if let Some(g) = def.group {
+        write!(out, ", group: {}", g).unwrap();
+    }
+    if def.once {
+        write!(out, ", once per file").unwrap();
+    }
+    if !def.builtin {
+        write!(out, ", declared in the schema file").unwrap();
+    }
+    writeln!(out).unwrap();
+
+    if let Some(d) = def.description.as_ref() {
+        writeln!(out, "  {}", d).unwrap();
+    }
+
+    if let (Some((name, _, values)), None) = (def.tag, tag) {
+        let variants: Vec<String> = values.iter().map(|v| format!("{}{}{}", prefix, def.token, v)).collect();
+        write_wrapped(out, "  ", &format!("variants <{}>", name), variants.iter().map(String::as_str));
+    }
+
+    let mut shown: Vec<&str> = Vec::new();
+    for p in def.params.iter() {
+        if let ParamType::Literal((name, _, values)) = p {
+            if !shown.contains(name) {
+                write_wrapped(out, "  ", &format!("<{}>", name), values.iter().copied());
+                shown.push(name);
+            }
+        }
+    }
+
+    writeln!(out, "  example:").unwrap();
+    for l in example(def, prefix, tag).lines() {
+        writeln!(out, "    {}", l).unwrap();
+    }
+    writeln!(out).unwrap();
+}
+
+
+fn suggest(schema: &Schema, q: &str) -> Option<String> {
+    let names: Vec<String> = FILES.iter().flat_map(|(name, _, _)| defs(schema, name).iter()).flat_map(|d| {
+        let tagged: Vec<String> = d.tag.map(|(_, _, values)| values.iter().map(|v| format!("{}{}", d.token, v)).collect()).unwrap_or_default();
+        std::iter::once(d.token.clone()).chain(tagged)
+    }).map(|n| normalize(&n)).collect();
+
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    diagnostic::suggest(q, &names).map(str::to_string)
+}
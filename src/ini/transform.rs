@@ -1,6 +1,8 @@
 use crate::ini;
 use crate::ini::common::{Point3f, Rect};
 use crate::ini::BuildingToken;
+use crate::ini::schema::{SchemaToken, SchemaValue, ParamType};
+use crate::gizmos::Gizmo;
 
 
 pub fn scale_building(file: &mut ini::BuildingIni<'_>, factor: f64) {
@@ -16,6 +18,7 @@ pub fn scale_building(file: &mut ini::BuildingIni<'_>, factor: f64) {
                     T::HarborWaterFrom(x)            => Some(T::HarborWaterFrom(mul(*x))),
                     T::HarborExtendWhenBuilding(x)   => Some(T::HarborExtendWhenBuilding(mul(*x))),
                     T::ParticleSnowRemove((p, i, r)) => Some(T::ParticleSnowRemove((p.scaled(factor), *i, mul(*r)))),
+                    T::Schema(t)                     => scale_schema(t, factor).map(T::Schema),
 
                     T::ResourceVisualization(rv) => Some(T::ResourceVisualization (RV {
                         storage_id: rv.storage_id,
@@ -44,6 +47,7 @@ pub fn scale_render(f: &mut ini::RenderIni<'_>, factor: f64) {
            T::Light((pt, x))            => Some(T::Light((pt.scaled(factor), *x))),
            T::LightRgb((pt, x, c))      => Some(T::LightRgb((pt.scaled(factor), *x, *c))),
            T::LightRgbBlink((pt, x, c)) => Some(T::LightRgbBlink((pt.scaled(factor), *x, *c))),
+           T::Schema(t)                 => scale_schema(t, factor).map(T::Schema),
             _ => None 
         });
     }
@@ -63,6 +67,7 @@ pub fn offset_building(file: &mut ini::BuildingIni<'_>, dx: f32, dy: f32, dz: f3
                     T::HarborWaterFrom(x)            => Some(T::HarborWaterFrom(*x + dx)),
                     T::HarborExtendWhenBuilding(x)   => Some(T::HarborExtendWhenBuilding(*x - dx)),
                     T::ParticleSnowRemove((p, i, r)) => Some(T::ParticleSnowRemove((p.offset(dx, dy, dz), *i, *r))),
+                    T::Schema(t)                     => offset_schema(t, dx, dy, dz).map(T::Schema),
 
                     T::ResourceVisualization(rv) => Some(T::ResourceVisualization (RV {
                         storage_id: rv.storage_id,
@@ -90,6 +95,7 @@ pub fn offset_render(f: &mut ini::RenderIni<'_>, dx: f32, dy: f32, dz: f32) {
            T::Light((pt, x))            => Some(T::Light((pt.offset(dx, dy, dz), *x))),
            T::LightRgb((pt, x, c))      => Some(T::LightRgb((pt.offset(dx, dy, dz), *x, *c))),
            T::LightRgbBlink((pt, x, c)) => Some(T::LightRgbBlink((pt.offset(dx, dy, dz), *x, *c))),
+           T::Schema(t)                 => offset_schema(t, dx, dy, dz).map(T::Schema),
             _ => None 
         });
     }
@@ -119,6 +125,7 @@ pub fn mirror_z_building(file: &mut ini::BuildingIni<'_>) {
             // must flip these points, otherwise the text faces backwards
             T::TextCaption((p1, p2)) => Some(T::TextCaption((mirror_z_point(p2), mirror_z_point(p1)))),
             T::ParticleSnowRemove((p, i, r)) => Some(T::ParticleSnowRemove((mirror_z_point(p), *i, *r))),
+            T::Schema(t) => mirror_z_schema(t).map(T::Schema),
             other => transform_point(other, |p| mirror_z_point(p))
                      .or_else(|| transform_rect(t_source, |r|
                         Rect {  x1: r.x1, 
@@ -137,16 +144,92 @@ pub fn mirror_z_render(f: &mut ini::RenderIni<'_>) {
            T::Light((pt, x))            => Some(T::Light((mirror_z_point(pt), *x))),
            T::LightRgb((pt, x, c))      => Some(T::LightRgb((mirror_z_point(pt), *x, *c))),
            T::LightRgbBlink((pt, x, c)) => Some(T::LightRgbBlink((mirror_z_point(pt), *x, *c))),
+           T::Schema(t)                 => mirror_z_schema(t).map(T::Schema),
             _ => None 
         });
     }
 }
 
 
+//-------------------------------------------------------------------
+
+pub fn apply_gizmos_building(file: &mut ini::BuildingIni<'_>, gizmos: &[Gizmo]) -> Result<usize, Vec<String>> {
+    let mut errors = Vec::with_capacity(0);
+    let mut updated = 0;
+
+    for gizmo in gizmos.iter() {
+        let mut occurrence = 0;
+        let t_state = file.tokens.iter_mut().map(|(_, t)| t).find(|t_state| {
+            let t = t_state.token();
+            let is_geometry = transform_point(t, |p| p.clone()).is_some() || transform_rect(t, |r| Rect { ..*r }).is_some();
+            if is_geometry && token_keyword(t) == gizmo.keyword {
+                occurrence += 1;
+                occurrence == gizmo.index + 1
+            } else {
+                false
+            }
+        });
+
+        let t_state = match t_state {
+            Some(t) => t,
+            None => {
+                errors.push(format!("Gizmo '{}': building.ini has no matching ${} token (occurrence {})", gizmo.name, gizmo.keyword, gizmo.index));
+                continue;
+            }
+        };
+
+        let mut pt_count = 0;
+        if transform_point(t_state.token(), |p| { pt_count += 1; p.clone() }).is_some() {
+            if pt_count != gizmo.points.len() {
+                errors.push(format!("Gizmo '{}': ${} needs {} vertices, found {}", gizmo.name, gizmo.keyword, pt_count, gizmo.points.len()));
+                continue;
+            }
+
+            let mut pts = gizmo.points.iter();
+            t_state.modify(|t| transform_point(t, |_| pts.next().unwrap().clone()));
+        } else {
+            if gizmo.points.len() < 2 {
+                errors.push(format!("Gizmo '{}': ${} needs at least 2 vertices, found {}", gizmo.name, gizmo.keyword, gizmo.points.len()));
+                continue;
+            }
+
+            let min = |f: fn(&Point3f) -> f32| gizmo.points.iter().map(f).fold(f32::INFINITY, f32::min);
+            let max = |f: fn(&Point3f) -> f32| gizmo.points.iter().map(f).fold(f32::NEG_INFINITY, f32::max);
+            let (x_min, x_max) = (min(|p| p.x), max(|p| p.x));
+            let (z_min, z_max) = (min(|p| p.z), max(|p| p.z));
+
+            // keep the original corners' order
+            t_state.modify(|t| transform_rect(t, |r| {
+                let (x1, x2) = if r.x1 <= r.x2 { (x_min, x_max) } else { (x_max, x_min) };
+                let (z1, z2) = if r.z1 <= r.z2 { (z_min, z_max) } else { (z_max, z_min) };
+                Rect { x1, z1, x2, z2 }
+            }));
+        }
+
+        updated += 1;
+    }
+
+    if errors.is_empty() {
+        Ok(updated)
+    } else {
+        Err(errors)
+    }
+}
+
+
+fn token_keyword(t: &BuildingToken<'_>) -> String {
+    let s = t.to_string();
+    match s.find(' ') {
+        Some(pos) => s[.. pos].to_string(),
+        None => s
+    }
+}
+
+
 //----------------------------------------------------------------------------------------------
 
 
-fn transform_point<'a, F: Fn(&Point3f) -> Point3f>(t: &BuildingToken<'a>, f: F) -> Option<BuildingToken<'a>> {
+fn transform_point<'a, F: FnMut(&Point3f) -> Point3f>(t: &BuildingToken<'a>, mut f: F) -> Option<BuildingToken<'a>> {
     use crate::ini::BuildingToken as T;
     match t {
         T::VehicleStation((p1, p2))               => Some(T::VehicleStation((                f(p1), f(p2)  ))),
@@ -177,6 +260,11 @@ fn transform_point<'a, F: Fn(&Point3f) -> Point3f>(t: &BuildingToken<'a>, f: F)
 
         T::CostWorkVehicleStation((p1, p2))       => Some(T::CostWorkVehicleStation((        f(p1), f(p2)  ))),
 
+        T::Schema(t) => t.map_values(|_, v| match v {
+            SchemaValue::Point(p) => Some(SchemaValue::Point(f(p))),
+            _ => None
+        }).map(T::Schema),
+
         _ => None 
     }
 }
@@ -187,8 +275,47 @@ fn transform_rect<'a, F: Fn(&Rect) -> Rect>(t: &BuildingToken<'a>, f: F) -> Opti
     match t {
         T::ConnectionsSpace(r)                 => Some(T::ConnectionsSpace(f(r))),
         T::ConnectionsRoadDeadSquare(r)        => Some(T::ConnectionsRoadDeadSquare(f(r))),
+        T::ConnectionsPedestrianDeadSquare(r)  => Some(T::ConnectionsPedestrianDeadSquare(f(r))),
         T::ConnectionsWaterDeadSquare((x, r))  => Some(T::ConnectionsWaterDeadSquare((*x, f(r)))),
+        T::Schema(t) => t.map_values(|_, v| match v {
+            SchemaValue::Rect(r) => Some(SchemaValue::Rect(f(r))),
+            _ => None
+        }).map(T::Schema),
         _ => None 
     }
 }
 
+
+
+// Tokens declared in the token schema: coordinates by the declared parameter types
+
+fn scale_schema<'a>(t: &SchemaToken<'a>, factor: f64) -> Option<SchemaToken<'a>> {
+    let mul = |x: f32| { ((x as f64) * factor) as f32 };
+
+    t.map_values(|ty, v| match (ty, v) {
+        (_, SchemaValue::Point(p))                            => Some(SchemaValue::Point(p.scaled(factor))),
+        (_, SchemaValue::Rect(r))                             => Some(SchemaValue::Rect(Rect { x1: mul(r.x1), z1: mul(r.z1), x2: mul(r.x2), z2: mul(r.z2) })),
+        (ParamType::Length, SchemaValue::Float(x))
+        | (ParamType::X, SchemaValue::Float(x))               => Some(SchemaValue::Float(mul(*x))),
+        _ => None
+    })
+}
+
+
+fn offset_schema<'a>(t: &SchemaToken<'a>, dx: f32, dy: f32, dz: f32) -> Option<SchemaToken<'a>> {
+    t.map_values(|ty, v| match (ty, v) {
+        (_, SchemaValue::Point(p))                 => Some(SchemaValue::Point(p.offset(dx, dy, dz))),
+        (_, SchemaValue::Rect(r))                  => Some(SchemaValue::Rect(Rect { x1: r.x1 + dx, z1: r.z1 + dz, x2: r.x2 + dx, z2: r.z2 + dz })),
+        (ParamType::X, SchemaValue::Float(x))      => Some(SchemaValue::Float(*x + dx)),
+        _ => None
+    })
+}
+
+
+fn mirror_z_schema<'a>(t: &SchemaToken<'a>) -> Option<SchemaToken<'a>> {
+    t.map_values(|_, v| match v {
+        SchemaValue::Point(p) => Some(SchemaValue::Point(mirror_z_point(p))),
+        SchemaValue::Rect(r)  => Some(SchemaValue::Rect(Rect { x1: r.x1, z1: 0f32 - r.z1, x2: r.x2, z2: 0f32 - r.z2 })),
+        _ => None
+    })
+}
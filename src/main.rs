@@ -6,9 +6,17 @@ use const_format::concatcp;
 
 mod nmf;
 mod ini;
+mod dds;
 
 mod building_def;
 mod modpack;
+mod gizmos;
+mod thumbnail;
+mod encoding;
+mod report;
+mod portability;
+mod scaffold;
+mod autofix;
 
 mod cfg;
 
@@ -25,20 +33,21 @@ fn main() {
 
     match &APP_SETTINGS.command {
         cfg::AppCommand::Modpack(cmd) => {
-            print_dirs();
+            let mut report = report::Report::new(APP_SETTINGS.format);
+            print_dirs(&report);
 
             match cmd {
                 cfg::ModpackCommand::Install(cfg::ModpackInstallCommand { source, destination }) => {
-                    println!("Installing from source: {}", source.display());
+                    report.note(format_args!("Installing from source: {}", source.display()));
                     assert!(source.exists(), "Modpack source directory does not exist!");
-                    println!("Reading modpack sources...");
+                    report.note(format_args!("Reading modpack sources..."));
 
-                    match modpack::read_validate_sources(source.as_path()) {
+                    match modpack::read_validate_sources(source.as_path(), &mut report) {
                         Ok((buildings, skins_count)) => {
-                            println!("Found {} buildings, {} skins", buildings.len(), skins_count);
+                            report.note(format_args!("Found {} buildings, {} skins", buildings.len(), skins_count));
                             let max_buildings = AppSettings::MAX_BUILDINGS - (skins_count / AppSettings::MAX_SKINS_IN_MOD + 1) * AppSettings::MAX_BUILDINGS_IN_MOD;
                             assert!(buildings.len() < max_buildings, "Too many building sources");
-                            println!("Installing to {}...", destination.display());
+                            report.note(format_args!("Installing to {}...", destination.display()));
                             assert!(destination.exists(), "Destination directory does not exist");
 
                             let mut log_path = destination.to_path_buf();
@@ -53,28 +62,30 @@ fn main() {
                             modpack::install(buildings, destination, &mut log_file);
 
                             log_file.flush().unwrap();
-                            println!("Modpack installed");
+                            report.note(format_args!("Modpack installed"));
                         },
                         Err(e) => {
-                            panic!("FAILED: encountered {} errors when reading sources", e);
+                            report.note(format_args!("FAILED: encountered {} errors when reading sources", e));
                         }
                     }
                 },
                 cfg::ModpackCommand::Validate(source) => {
-                    println!("Validating modpack at {}", source.display());
+                    report.note(format_args!("Validating modpack at {}", source.display()));
                     assert!(source.exists(), "Modpack source directory does not exist!");
-                    println!("Reading modpack sources...");
+                    report.note(format_args!("Reading modpack sources..."));
 
-                    match modpack::read_validate_sources(source.as_path()) {
+                    match modpack::read_validate_sources(source.as_path(), &mut report) {
                         Ok((buildings, skins_count)) => {
-                            println!("OK: found {} buildings, {} skins", buildings.len(), skins_count);
+                            report.note(format_args!("OK: found {} buildings, {} skins", buildings.len(), skins_count));
                         },
                         Err(e) => {
-                            eprintln!("FAILED: encountered {} errors", e);
+                            report.note(format_args!("FAILED: encountered {} errors", e));
                         }
                     }
                 },
             }
+
+            std::process::exit(report.finish());
         },
 
 
@@ -226,14 +237,15 @@ fn main() {
             fn check_and_copy_building(dir_input: &PathBuf, dir_output: &PathBuf) -> ModBuildingDef {
                 let render_ini = dir_input.join(RENDERCONFIG_INI);
                 let bld_ini = dir_input.join(BUILDING_INI);
-                let bld_def = ModBuildingDef::from_render_path(&bld_ini, &render_ini, ini::normalize_join, false)
+                let bld_def = ModBuildingDef::from_render_path(&bld_ini, &render_ini, ini::resolve_source_path, false)
                     .expect("Cannot parse building");
 
                 {
                     let check_path = |path: &Path| assert!(path.starts_with(dir_input), 
                                           "To update the whole building in one operation, all potentially modified files (building.ini, \
                                           renderconfig.ini, *.nmf) must be located in the input directory. Otherwise you should update \
-                                          files individually, one-by-one (using appropriate commands).");
+                                          files individually, one-by-one (using appropriate commands), or make a local copy of the \
+                                          building first with 'mod-building copy --deep'.");
 
                     let check_path_opt = |opt: &Option<PathBuf>| if let Some(p) = opt.as_ref() { check_path(p) };
 
@@ -243,6 +255,9 @@ fn main() {
                     check_path_opt(&bld_def.model_lod);
                     check_path_opt(&bld_def.model_lod2);
                     check_path_opt(&bld_def.model_e);
+                    for mesh in bld_def.extra_meshes.iter() {
+                        check_path(&mesh.model);
+                    }
                 }
 
                 println!("Building parsed successfully. Copying files...");
@@ -253,8 +268,9 @@ fn main() {
 
             macro_rules! modify_ini {
                 ($buf:ident, $path:expr, $name:expr, $parser:expr, $modifier:expr $(, $m_p:expr)*) => {{
-                    read_to_string_buf($path, &mut $buf).expect(concatcp!("Cannot read ", $name));
+                    let enc = read_to_string_buf($path, &mut $buf).expect(concatcp!("Cannot read ", $name));
                     let mut ini = $parser(&mut $buf).expect(concatcp!("Cannot parse ", $name));
+                    ini.set_encoding(enc);
                     $modifier(&mut ini $(, $m_p)*);
                     let mut out_writer = io::BufWriter::new(fs::OpenOptions::new().write(true).truncate(true).open($path).unwrap());
                     ini.write_to(&mut out_writer).unwrap();
@@ -264,8 +280,11 @@ fn main() {
             }
 
             fn modify_models<F: Fn(&mut nmf::ObjectFull)>(bld_def: &ModBuildingDef, pfx: &Path, obj_modifier: F) {
-                let modify_nmf = |path: Option<&PathBuf>| {
-                    if let Some(path) = path {
+                // a mesh can be referenced by several tokens, it is modified once
+                let mut modified = Vec::<PathBuf>::with_capacity(8);
+                let mut modify_nmf = |path: Option<&PathBuf>| {
+                    if let Some(path) = path.filter(|p| !modified.contains(p)) {
+                        modified.push(path.clone());
                         let mut nmf = nmf::NmfBufFull::from_path(path).expect("Failed to read the nmf file");
                         for o in nmf.objects.iter_mut() {
                             obj_modifier(o);
@@ -280,22 +299,58 @@ fn main() {
                 modify_nmf(bld_def.model_lod.as_ref());
                 modify_nmf(bld_def.model_lod2.as_ref());
                 modify_nmf(bld_def.model_e.as_ref());
+                for mesh in bld_def.extra_meshes.iter() {
+                    modify_nmf(Some(&mesh.model));
+                }
             }
 
 
             match cmd {
-                cfg::ModCommand::Validate(dir_input) => {
+                cfg::ModCommand::Validate(cfg::ValidateCommand { dir_input, fix_case, fix, dry_run }) => {
                     let bld_ini = dir_input.join(BUILDING_INI);
                     let render_ini = dir_input.join(RENDERCONFIG_INI);
-                    match building_def::ModBuildingDef::from_render_path(&bld_ini, &render_ini, ini::normalize_join, true) {
+                    let mut report = report::Report::new(APP_SETTINGS.format);
+
+                    if *fix_case {
+                        for (path, old, new) in portability::fix_case(&render_ini, &bld_ini).expect("Cannot fix path tokens") {
+                            report.note(format_args!("{}: {} -> {}", path.display(), old, new));
+                        }
+                    }
+
+                    if *fix {
+                        apply_fixes(&bld_ini, &render_ini, *dry_run, &report);
+                    }
+
+                    // reported with both the successful and the failed validation
+                    let mut image_missing = None;
+                    let validated = match ModBuildingDef::from_render_path(&bld_ini, &render_ini, ini::resolve_source_path, false) {
                         Ok(bld) => {
-                            println!("{}\nOK", bld);
+                            if bld.image_gui.is_none() {
+                                image_missing = Some(ini::diagnostic::Diagnostic::warning("imagegui-missing", "imagegui.png is missing, the building has no picture in the game menu".to_string())
+                                                     .in_file(&render_ini)
+                                                     .with_help(Some("--fix renders it from the model".to_string())));
+                            }
+                            bld.parse_and_validate(None).map(|warnings| (bld, warnings))
                         },
                         Err(e) => {
-                            eprintln!("Building has errors:\n{}", e);
-                            std::process::exit(1);
+                            // a wrong case or a backslash is often the reason why a file cannot be found
+                            report.extend(portability::check(&render_ini, &bld_ini));
+                            Err(e)
+                        }
+                    };
+
+                    match validated {
+                        Ok((bld, warnings)) => {
+                            report.extend(warnings.into_iter().chain(image_missing));
+                            report.note(format_args!("{}\nOK", bld));
+                        },
+                        Err(e) => {
+                            report.extend(e.into_diagnostics().into_iter().chain(image_missing));
+                            report.note(format_args!("FAILED: building has {} errors", report.errors()));
                         }
                     }
+
+                    std::process::exit(report.finish());
                 },
 
                 cfg::ModCommand::Scale(cfg::ScaleCommand { input: dir_input, factor, output: dir_output }) => {
@@ -317,6 +372,194 @@ fn main() {
                     modify_ini!(buf, &bld_def.render,       RENDERCONFIG_INI, ini::parse_renderconfig_ini, ini::transform::mirror_z_render);
                     modify_models(&bld_def, dir_output, |o| o.mirror_z());
                 },
+                cfg::ModCommand::ApplyGizmos(cfg::ApplyGizmosCommand { dir_input, gizmos }) => {
+                    let mut buf = String::with_capacity(16 * 1024);
+                    let gizmos = gizmos::read_gizmos(gizmos, &mut buf).expect("Cannot read gizmos");
+                    println!("Found {} gizmos", gizmos.len());
+
+                    let bld_ini = dir_input.join(BUILDING_INI);
+                    let enc = read_to_string_buf(&bld_ini, &mut buf).expect(concatcp!("Cannot read ", BUILDING_INI));
+                    let mut ini = ini::parse_building_ini(&buf).expect(concatcp!("Cannot parse ", BUILDING_INI));
+                    ini.set_encoding(enc);
+
+                    match ini::transform::apply_gizmos_building(&mut ini, &gizmos) {
+                        Ok(updated) => {
+                            ini.write_file(&bld_ini).expect(concatcp!("Cannot write ", BUILDING_INI));
+                            println!("{}: updated {} tokens", BUILDING_INI, updated);
+                        },
+                        Err(errors) => {
+                            eprintln!("Cannot apply gizmos:");
+                            for e in errors.iter() {
+                                eprintln!("    {}", e);
+                            }
+                            std::process::exit(1);
+                        }
+                    }
+                },
+                cfg::ModCommand::Thumbnail(cfg::ThumbnailCommand { dir_input, size, angle }) => {
+                    let bld_ini = dir_input.join(BUILDING_INI);
+                    let render_ini = dir_input.join(RENDERCONFIG_INI);
+                    let bld_def = ModBuildingDef::from_render_path(&bld_ini, &render_ini, ini::resolve_source_path, false)
+                        .expect("Cannot parse building");
+
+                    let nmf = nmf::NmfBufFull::from_path(&bld_def.model).expect("Failed to read the nmf file");
+                    let (textures, warnings) = thumbnail::load_diffuse_textures(&nmf, &bld_def.material);
+                    for w in warnings.iter() {
+                        eprintln!("Warning: {}", w);
+                    }
+
+                    let image = thumbnail::render(&nmf, &textures, *size, *angle);
+                    let img_path = dir_input.join("imagegui.png");
+                    image.write_png(&img_path).expect("Cannot write the thumbnail");
+                    println!("Done. Thumbnail saved as {}", img_path.display());
+                },
+
+                cfg::ModCommand::Copy(cfg::CopyCommand { dir_input, dir_output, deep }) => {
+                    use normpath::PathExt;
+
+                    let dir_input = dir_input.normalize_virtually().expect("Cannot normalize the building path").into_path_buf();
+                    let bld_ini = dir_input.join(BUILDING_INI);
+                    let render_ini = dir_input.join(RENDERCONFIG_INI);
+                    let bld_def = ModBuildingDef::from_render_path(&bld_ini, &render_ini, ini::resolve_source_path, false)
+                        .expect("Cannot parse building");
+
+                    let new_def = if *deep {
+                        bld_def.deep_copy_to(dir_output)
+                    } else {
+                        bld_def.shallow_copy_to(dir_output)
+                    }.expect("Cannot copy building files");
+
+                    let mut external = Vec::<(&Path, &Path)>::with_capacity(0);
+                    for (src, dest) in bld_def.files().into_iter().zip(new_def.files()) {
+                        if !src.starts_with(&dir_input) && external.iter().all(|(s, _)| *s != src) {
+                            external.push((src, dest));
+                        }
+                    }
+
+                    for (src, dest) in external.iter() {
+                        if *deep {
+                            println!("{} -> {}", src.display(), dest.strip_prefix(dir_output).unwrap_or(dest).display());
+                        } else {
+                            println!("Not copied: {}", src.display());
+                        }
+                    }
+
+                    if !*deep && !external.is_empty() {
+                        println!("{} files outside of the building directory are not copied, use --deep to copy them as well", external.len());
+                    }
+
+                    println!("Done. Building copied to {}", dir_output.display());
+                },
+
+                cfg::ModCommand::CloneStock(cfg::CloneStockCommand { name, dir_output, copy }) => {
+                    let (stock_bld, stock_render) = find_stock_building(name);
+                    let stock_dir = stock_render.parent().unwrap();
+                    let stock_name = stock_dir.file_name().unwrap().to_string_lossy();
+                    println!("Stock building: {}", stock_dir.display());
+                    let bld_def = ModBuildingDef::from_render_path(&stock_bld, &stock_render, ini::resolve_source_path, false)
+                        .expect("Cannot parse stock building");
+
+                    // a mod with one building, in the '00' subdirectory
+                    let bld_dir = dir_output.join("00");
+                    assert!(!bld_dir.exists(), "{} already exists", bld_dir.display());
+                    fs::create_dir_all(&bld_dir).expect("Cannot create the building directory");
+
+                    let mut new_def = bld_def.clone();
+                    new_def.render = bld_dir.join(RENDERCONFIG_INI);
+                    new_def.building_ini = bld_dir.join(BUILDING_INI);
+                    fs::copy(&bld_def.render, &new_def.render).expect("Cannot copy renderconfig");
+                    fs::copy(&bld_def.building_ini, &new_def.building_ini).expect("Cannot copy building ini");
+                    if let Some(img) = bld_def.image_gui.as_ref() {
+                        let new_img = bld_dir.join("imagegui.png");
+                        fs::copy(img, &new_img).expect("Cannot copy imagegui");
+                        new_def.image_gui = Some(new_img);
+                    }
+
+                    if *copy {
+                        new_def.make_local()
+                    } else {
+                        new_def.relink_configs(|_| true)
+                    }.expect("Cannot update the building files");
+
+                    fn set_workshop_type(render_ini: &mut ini::RenderIni) {
+                        for token_state in render_ini.tokens_mut() {
+                            token_state.modify(|t| match t {
+                                ini::RenderToken::ObjectTypeStock(_) => Some(ini::RenderToken::ObjectTypeWorkshop),
+                                _ => None
+                            });
+                        }
+                    }
+
+                    let mut buf = String::with_capacity(16 * 1024);
+                    modify_ini!(buf, &new_def.render, RENDERCONFIG_INI, ini::parse_renderconfig_ini, set_workshop_type);
+
+                    // the directory name is the item id in workshop_wip
+                    let item_id = dir_output.file_name().and_then(|n| n.to_str()).and_then(|n| n.parse().ok()).unwrap_or(0);
+                    modpack::write_workshop_ini_buildings(&dir_output.join(modpack::WORKSHOPCONFIG), item_id, 1, &format!("{} (clone)", stock_name), &mut buf);
+                    println!("Done. New mod created in {}", dir_output.display());
+                },
+
+                cfg::ModCommand::New(cfg::NewCommand { dir_output, model, textures, building_type }) => {
+                    assert!(!dir_output.join(RENDERCONFIG_INI).exists(), "{} already has a building", dir_output.display());
+                    let nmf = nmf::NmfInfo::from_path(model).expect("Cannot read the model");
+                    scaffold::create(dir_output, model, &nmf, textures, building_type.clone()).expect("Cannot create the building files");
+                    println!("Done. Building created in {}: {} submaterials, {} nodes", dir_output.display(), nmf.submaterials.len(), nmf.objects.len());
+                },
+
+                cfg::ModCommand::Unused(cfg::UnusedCommand { dir_input, delete }) => {
+                    use normpath::PathExt;
+                    const UNUSED_DIR: &str = "_unused";
+
+                    let bld_ini = dir_input.join(BUILDING_INI);
+                    let render_ini = dir_input.join(RENDERCONFIG_INI);
+                    let bld_def = ModBuildingDef::from_render_path(&bld_ini, &render_ini, ini::resolve_source_path, false)
+                        .expect("Cannot parse building");
+
+                    // Compared ignoring case: a file referenced with a wrong case is still used on Windows
+                    let key = |p: &Path| p.normalize_virtually().map(|p| p.into_path_buf()).unwrap_or_else(|_| p.to_path_buf()).to_string_lossy().to_lowercase();
+                    let used: Vec<String> = bld_def.files().into_iter().map(key).collect();
+
+                    let root = dir_input.normalize_virtually().expect("Cannot normalize the building path").into_path_buf();
+                    let trash = root.join(UNUSED_DIR);
+                    let mut unused = Vec::<(PathBuf, u64)>::with_capacity(16);
+                    let mut backlog = vec![root.clone()];
+                    while let Some(dir) = backlog.pop() {
+                        for entry in fs::read_dir(&dir).unwrap_or_else(|e| panic!("Cannot read {}: {}", dir.display(), e)) {
+                            let entry = entry.expect("Cannot read directory entry");
+                            let path = entry.path();
+                            let meta = entry.metadata().unwrap_or_else(|e| panic!("Cannot read {}: {}", path.display(), e));
+                            if meta.is_dir() {
+                                if path != trash {
+                                    backlog.push(path);
+                                }
+                            } else if entry.file_name() != modpack::WORKSHOPCONFIG && !used.contains(&key(&path)) {
+                                unused.push((path, meta.len()));
+                            }
+                        }
+                    }
+
+                    unused.sort();
+                    for (path, size) in unused.iter() {
+                        println!("{:>10}  {}", format_size(*size), path.strip_prefix(&root).unwrap().display());
+                    }
+                    println!("{} unused files, {} total", unused.len(), format_size(unused.iter().map(|(_, s)| s).sum()));
+
+                    if *delete && !unused.is_empty() {
+                        let mut moved = 0;
+                        for (path, _) in unused.iter() {
+                            let target = trash.join(path.strip_prefix(&root).unwrap());
+                            if target.exists() {
+                                eprintln!("Warning: {} is already in {}, not moved", target.display(), UNUSED_DIR);
+                                continue;
+                            }
+
+                            fs::create_dir_all(target.parent().unwrap()).expect("Cannot create the trash folder");
+                            fs::rename(path, &target).unwrap_or_else(|e| panic!("Cannot move {}: {}", path.display(), e));
+                            moved += 1;
+                        }
+                        println!("Moved {} files to {}", moved, trash.display());
+                    }
+                },
             }
         },
 
@@ -324,7 +567,7 @@ fn main() {
         //---------------- ini subcommand --------------------------------
         cfg::AppCommand::Ini(cmd) => {
 
-            fn process_tokens<T: std::fmt::Display>(ts: Vec<(&str, ini::common::ParseResult<T>)>) {
+            fn process_tokens<T: std::fmt::Display>(path: &Path, src: &str, ts: Vec<(&str, ini::common::ParseResult<T>)>) {
                 for (t_str, t_val) in ts.iter() {
                     match t_val {
                         Ok((t, rest)) => {
@@ -334,7 +577,7 @@ fn main() {
                             }
                             println!();
                         },
-                        Err(e) => println!(" > > > Error > > >\n > > > {}\n > > > chunk: [{}]", e, t_str),
+                        Err(e) => println!("{}", ini::diagnostic::render_parse_error(path, src, t_str, e)),
                     }
                 }
             }
@@ -345,64 +588,343 @@ fn main() {
                 println!("Done. File saved as {}", path.display());
             }
 
+            fn exit_parse_errors<T>(path: &Path, src: &str, errors: Vec<(&str, ini::common::ParseError)>) -> T {
+                eprint!("{}", ini::diagnostic::render_parse_errors(path, src, &errors));
+                std::process::exit(1);
+            }
+
+            fn export_json<'a, U>(path: &Path, src: &'a str, enc: encoding::TextEncoding, ini: Result<ini::IniFile<'a, U>, Vec<(&'a str, ini::common::ParseError)>>)
+            where U: ini::json::JsonToken<'a>
+            {
+                let mut ini = ini.unwrap_or_else(|e| exit_parse_errors(path, src, e));
+                ini.set_encoding(enc);
+                print!("{}", ini::json::ini_to_json(&ini));
+            }
+
+            fn read_json(path: &Path) -> serde_json::Value {
+                let buf = fs::read_to_string(path).expect("Cannot read the specified file");
+                serde_json::from_str(&buf).unwrap_or_else(|e| {
+                    eprintln!("Cannot parse {}: {}", path.display(), e);
+                    std::process::exit(1);
+                })
+            }
+
+            fn save_json_as<'a, U, F>(path: &Path, doc: &'a serde_json::Value, src: &'a str, parse: F)
+            where U: ini::json::JsonToken<'a>,
+                  F: FnOnce(&'a str) -> Result<ini::IniFile<'a, U>, Vec<(&'a str, ini::common::ParseError)>>
+            {
+                let ini = ini::json::ini_from_json(doc, src, parse).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+
+                save_ini_as(path, ini);
+            }
+
+            fn read_json_source(doc: &serde_json::Value, footer: Option<&str>) -> String {
+                ini::json::source_from_json(doc, footer).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                })
+            }
+
+            fn print_diff<'a, 't, U>(old: &Path, new: &Path, diff: Vec<ini::diff::KindDiff<'a, U>>)
+            where U: ini::json::JsonToken<'t> + std::fmt::Display
+            {
+                if diff.is_empty() {
+                    println!("No differences");
+                } else {
+                    println!("--- {}\n+++ {}", old.display(), new.display());
+                    for d in diff.iter() {
+                        print!("{}", d);
+                    }
+                    std::process::exit(1);
+                }
+            }
+
+            fn read_ini(path: &Path) -> (String, encoding::TextEncoding) {
+                read_text(path).unwrap_or_else(|e| panic!("Cannot read {}: {}", path.display(), e))
+            }
+
             match cmd {
                 cfg::IniCommand::ParseBuilding(path) => {
-                    let buf = fs::read_to_string(path).expect("Cannot read the specified file");
+                    let (buf, _) = read_ini(path);
                     let tokens = ini::parse_building_tokens(&buf);
-                    process_tokens(tokens);
+                    process_tokens(path, &buf, tokens);
                 },
                 cfg::IniCommand::ParseRender(path) => {
-                    let buf = fs::read_to_string(path).expect("Cannot read the specified file");
+                    let (buf, _) = read_ini(path);
                     let tokens = ini::parse_render_tokens(&buf);
-                    process_tokens(tokens);
+                    process_tokens(path, &buf, tokens);
                 },
                 cfg::IniCommand::ParseMtl(path) => {
-                    let buf = fs::read_to_string(path).expect("Cannot read the specified file");
+                    let (buf, _) = read_ini(path);
                     let tokens = ini::parse_material_tokens(&buf);
-                    process_tokens(tokens);
+                    process_tokens(path, &buf, tokens);
                 },
                 cfg::IniCommand::ScaleBuilding(cfg::ScaleCommand { input, factor, output }) => {
-                    let file = fs::read_to_string(input).expect("Cannot read the specified file");
+                    let (file, enc) = read_ini(input);
                     let mut ini = ini::parse_building_ini(&file).expect("Cannot parse building.ini");
+                    ini.set_encoding(enc);
                     ini::transform::scale_building(&mut ini, *factor);
                     save_ini_as(output, ini);
                 },
                 cfg::IniCommand::ScaleRender(cfg::ScaleCommand { input, factor, output }) => {
-                    let file = fs::read_to_string(input).expect("Cannot read the specified file");
+                    let (file, enc) = read_ini(input);
                     let mut ini = ini::parse_renderconfig_ini(&file).expect("Cannot parse renderconfig");
+                    ini.set_encoding(enc);
                     ini::transform::scale_render(&mut ini, *factor);
                     save_ini_as(output, ini);
                 },
                 cfg::IniCommand::MirrorBuilding(cfg::FromToCommand { input, output }) => {
-                    let file = fs::read_to_string(input).expect("Cannot read the specified file");
+                    let (file, enc) = read_ini(input);
                     let mut ini = ini::parse_building_ini(&file).expect("Cannot parse building.ini");
+                    ini.set_encoding(enc);
                     ini::transform::mirror_z_building(&mut ini);
                     save_ini_as(output, ini);
                 },
                 cfg::IniCommand::MirrorRender(cfg::FromToCommand { input, output }) => {
-                    let file = fs::read_to_string(input).expect("Cannot read the specified file");
+                    let (file, enc) = read_ini(input);
                     let mut ini = ini::parse_renderconfig_ini(&file).expect("Cannot parse renderconfig");
+                    ini.set_encoding(enc);
                     ini::transform::mirror_z_render(&mut ini);
                     save_ini_as(output, ini);
-                }
+                },
+                cfg::IniCommand::ExportBuilding(path) => {
+                    let (buf, enc) = read_ini(path);
+                    export_json(path, &buf, enc, ini::parse_building_ini(&buf));
+                },
+                cfg::IniCommand::ExportRender(path) => {
+                    let (buf, enc) = read_ini(path);
+                    export_json(path, &buf, enc, ini::parse_renderconfig_ini(&buf));
+                },
+                cfg::IniCommand::ExportMtl(path) => {
+                    let (buf, enc) = read_ini(path);
+                    export_json(path, &buf, enc, ini::parse_mtl(&buf));
+                },
+                cfg::IniCommand::ImportBuilding(cfg::FromToCommand { input, output }) => {
+                    let doc = read_json(input);
+                    let src = read_json_source(&doc, Some("end"));
+                    save_json_as(output, &doc, &src, ini::parse_building_ini);
+                },
+                cfg::IniCommand::ImportRender(cfg::FromToCommand { input, output }) => {
+                    let doc = read_json(input);
+                    let src = read_json_source(&doc, None);
+                    save_json_as(output, &doc, &src, ini::parse_renderconfig_ini);
+                },
+                cfg::IniCommand::ImportMtl(cfg::FromToCommand { input, output }) => {
+                    let doc = read_json(input);
+                    let src = read_json_source(&doc, None);
+                    save_json_as(output, &doc, &src, ini::parse_mtl);
+                },
+                cfg::IniCommand::FmtBuilding(cfg::FmtCommand { path, check, sort }) => {
+                    let (buf, enc) = read_ini(path);
+                    let src = ini::format::uppercase_token_names(&buf);
+                    let ini = ini::parse_building_ini(&src).unwrap_or_else(|e| exit_parse_errors(path, &src, e));
+                    let formatted = ini::format::format_building(&ini, *sort);
+
+                    if formatted == buf {
+                        println!("{} is formatted", path.display());
+                    } else if *check {
+                        println!("{} needs formatting", path.display());
+                        std::process::exit(1);
+                    } else {
+                        fs::write(path, enc.encode(&formatted).expect("Could not encode the file")).expect("Could not write the file");
+                        println!("Done. File saved as {}", path.display());
+                    }
+                },
+                cfg::IniCommand::DiffBuilding(cfg::DiffCommand { old, new, tolerance }) => {
+                    let ((buf_old, _), (buf_new, _)) = (read_ini(old), read_ini(new));
+                    let ini_old = ini::parse_building_ini(&buf_old).unwrap_or_else(|e| exit_parse_errors(old, &buf_old, e));
+                    let ini_new = ini::parse_building_ini(&buf_new).unwrap_or_else(|e| exit_parse_errors(new, &buf_new, e));
+                    print_diff(old, new, ini::diff::diff_tokens(ini_old.tokens(), ini_new.tokens(), *tolerance));
+                },
+                cfg::IniCommand::DiffRender(cfg::DiffCommand { old, new, tolerance }) => {
+                    let ((buf_old, _), (buf_new, _)) = (read_ini(old), read_ini(new));
+                    let ini_old = ini::parse_renderconfig_ini(&buf_old).unwrap_or_else(|e| exit_parse_errors(old, &buf_old, e));
+                    let ini_new = ini::parse_renderconfig_ini(&buf_new).unwrap_or_else(|e| exit_parse_errors(new, &buf_new, e));
+                    print_diff(old, new, ini::diff::diff_tokens(ini_old.tokens(), ini_new.tokens(), *tolerance));
+                },
             }
 
         },
 
+        cfg::AppCommand::Dds(cmd) => {
+            match cmd {
+                cfg::DdsCommand::Show(path) => {
+                    let header = dds::Header::from_path(path).unwrap_or_else(|e| panic!("Cannot read {}: {}", path.display(), e));
+                    println!("{}", header);
+
+                    let issues = header.validate(false);
+                    for (severity, e) in issues.iter() {
+                        println!("{}: {}", severity.name(), e);
+                    }
+
+                    if issues.iter().any(|(s, _)| *s == ini::diagnostic::Severity::Error) {
+                        std::process::exit(1);
+                    }
+                    println!("OK");
+                },
+
+                cfg::DdsCommand::ToPng(cfg::FromToCommand { input, output }) => {
+                    let dds = dds::Dds::from_path(input).unwrap_or_else(|e| panic!("Cannot read {}: {}", input.display(), e));
+                    dds.decode_rgba().write_png(output).expect("Cannot write png file");
+                    println!("Done");
+                },
+
+                cfg::DdsCommand::FromPng(cfg::DdsFromPngCommand { input, output, format, mips }) => {
+                    let image = dds::RgbaImage::from_png(input).unwrap_or_else(|e| panic!("Cannot read {}: {}", input.display(), e));
+                    let dds = dds::Dds::from_rgba(&image, format.clone(), *mips);
+                    dds.write_file(output).expect("Cannot write dds file");
+                    println!("{}\nDone", dds.header);
+                },
+
+                cfg::DdsCommand::Resize(cfg::DdsResizeCommand { input, max_size, output }) => {
+                    let dds = dds::Dds::from_path(input).unwrap_or_else(|e| panic!("Cannot read {}: {}", input.display(), e));
+                    match dds.shrink_to(*max_size).unwrap_or_else(|e| panic!("Cannot resize {}: {}", input.display(), e)) {
+                        Some(smaller) => {
+                            smaller.write_file(output).expect("Cannot write dds file");
+                            println!("{}\nDone", smaller.header);
+                        },
+                        None => println!("Texture is {}x{}, already fits into {}. Nothing to do", dds.header.width, dds.header.height, max_size)
+                    }
+                },
+            }
+        },
+
+        cfg::AppCommand::Tokens(cmd) => {
+            match cmd {
+                cfg::TokensCommand::List(cfg::TokensListCommand { file, filter }) => {
+                    print!("{}", ini::tokens::list(&APP_SETTINGS.schema, file.as_deref(), filter.as_deref()));
+                },
+
+                cfg::TokensCommand::Explain(name) => {
+                    match ini::tokens::explain(&APP_SETTINGS.schema, name) {
+                        Ok(s) => print!("{}", s),
+                        Err(e) => {
+                            eprintln!("Error: {}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                },
+            }
+        },
+
         //---------------- subcommands end --------------------------------
     };
 }
 
 
-fn print_dirs() {
-    println!("Stock game files:   {}", APP_SETTINGS.path_stock.as_path().display());
+fn print_dirs(report: &report::Report) {
+    report.note(format_args!("Stock game files:   {}", APP_SETTINGS.path_stock.as_path().display()));
     assert!(APP_SETTINGS.path_stock.exists(), "Stock game files directory does not exist.");
 
-    println!("Workshop directory: {}", APP_SETTINGS.path_workshop.as_path().display());
+    report.note(format_args!("Workshop directory: {}", APP_SETTINGS.path_workshop.as_path().display()));
     assert!(APP_SETTINGS.path_workshop.exists(), "Workshop directory does not exist.");
 }
 
 
+// mod-building validate --fix: prints the diff of every fixed file, then writes it (unless dry_run),
+// renders the missing imagegui.png and lists the issues which have no safe fix
+fn apply_fixes(bld_ini: &Path, render_ini: &Path, dry_run: bool, report: &report::Report) {
+    let bld = match building_def::ModBuildingDef::from_render_path(bld_ini, render_ini, ini::resolve_source_path, false) {
+        Ok(bld) => bld,
+        Err(_) => {
+            report.note(format_args!("Cannot fix: the building cannot be loaded"));
+            return;
+        }
+    };
+
+    let fixes = autofix::collect(&bld);
+    for f in fixes.files.iter() {
+        report.note(format_args!("--- {}\n+++ {}", f.path.display(), f.path.display()));
+        for line in f.diff() {
+            report.note(format_args!("{}", line));
+        }
+
+        if !dry_run {
+            f.write().unwrap_or_else(|e| panic!("Cannot write {}: {}", f.path.display(), e));
+        }
+    }
+
+    let mut not_fixed = fixes.not_fixed;
+    if bld.image_gui.is_none() {
+        if dry_run {
+            report.note(format_args!("imagegui.png would be rendered from the model"));
+        } else {
+            match autofix::render_imagegui(&bld) {
+                Ok(path) => report.note(format_args!("Rendered {}", path.display())),
+                Err(e) => not_fixed.push(format!("imagegui.png: {}", e)),
+            }
+        }
+    }
+
+    if fixes.files.is_empty() && bld.image_gui.is_some() {
+        report.note(format_args!("Nothing to fix"));
+    }
+
+    for msg in not_fixed.iter() {
+        report.note(format_args!("Not fixed: {}", msg));
+    }
+}
+
+
+// (building ini, renderconfig) of the stock building: <stock>/<name>, or the directory named so
+// (ignoring case) anywhere in the stock files. The building ini is the building.ini next to
+// renderconfig, or buildings_types/<$TYPE>.ini.
+fn find_stock_building(name: &str) -> (PathBuf, PathBuf) {
+    let stock = APP_SETTINGS.path_stock.as_path();
+    let mut dir = stock.join(name);
+    if !dir.join(RENDERCONFIG_INI).exists() {
+        let mut found = Vec::<PathBuf>::with_capacity(1);
+        let mut backlog = vec![stock.to_path_buf()];
+        while let Some(d) = backlog.pop() {
+            for entry in fs::read_dir(&d).into_iter().flatten().flatten() {
+                if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                    let path = entry.path();
+                    if entry.file_name().to_string_lossy().eq_ignore_ascii_case(name) && path.join(RENDERCONFIG_INI).exists() {
+                        found.push(path.clone());
+                    }
+                    backlog.push(path);
+                }
+            }
+        }
+
+        dir = match found.len() {
+            0 => panic!("Cannot find stock building '{}' in {}", name, stock.display()),
+            1 => found.pop().unwrap(),
+            _ => panic!("There are {} stock buildings named '{}', use the path relative to {}:\n{}", found.len(), name, stock.display(),
+                        found.iter().map(|p| p.strip_prefix(stock).unwrap().display().to_string()).collect::<Vec<_>>().join("\n")),
+        };
+    }
+
+    let render = dir.join(RENDERCONFIG_INI);
+    let bld = dir.join(BUILDING_INI);
+    if bld.exists() {
+        return (bld, render);
+    }
+
+    let (src, _) = read_text(&render).expect("Cannot read stock renderconfig");
+    let type_name = ini::parse_render_tokens(&src).into_iter().find_map(|(_, t)| match t {
+        Ok((ini::RenderToken::ObjectTypeStock(p), _)) => Some(p.to_string()),
+        _ => None
+    }).unwrap_or_else(|| panic!("{} has neither building.ini nor $TYPE", dir.display()));
+
+    let bld = stock.join("buildings_types").join(format!("{}.ini", type_name));
+    assert!(bld.exists(), "Cannot find the building ini of '{}': {} does not exist", name, bld.display());
+    (bld, render)
+}
+
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0 ..= 1023           => format!("{} B", bytes),
+        1024 ..= 1048575     => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _                    => format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+
 pub fn read_to_buf(path: &Path, buf: &mut Vec<u8>) -> Result<(), std::io::Error> {
     use std::io::Read;
     use std::convert::TryInto;
@@ -417,15 +939,15 @@ pub fn read_to_buf(path: &Path, buf: &mut Vec<u8>) -> Result<(), std::io::Error>
 }
 
 
-pub fn read_to_string_buf<P: AsRef<Path>>(path: P, buf: &mut String) -> Result<(), std::io::Error> {
-    use std::io::Read;
-    use std::convert::TryInto;
-    buf.clear();
+// Reads a game text file into buf, decoding it (see encoding::decode). Returns the file's encoding.
+pub fn read_to_string_buf<P: AsRef<Path>>(path: P, buf: &mut String) -> Result<encoding::TextEncoding, std::io::Error> {
+    let bytes = fs::read(path)?;
+    encoding::decode(&bytes, APP_SETTINGS.encoding, buf)
+}
 
-    let mut file = fs::File::open(path)?;
-    let meta = file.metadata()?;
-    let sz: usize = meta.len().try_into().expect("Cannot get file length");
-    buf.reserve(sz);
-    file.read_to_string(buf)?;
-    Ok(())
+
+pub fn read_text<P: AsRef<Path>>(path: P) -> Result<(String, encoding::TextEncoding), std::io::Error> {
+    let mut buf = String::new();
+    let enc = read_to_string_buf(path, &mut buf)?;
+    Ok((buf, enc))
 }
@@ -8,12 +8,31 @@ use regex::Regex;
 
 use crate::read_to_string_buf;
 use crate::{ini, nmf};
+use crate::ini::diagnostic::Diagnostic;
 
 
 pub enum Error {
     FileRead(IOErr),
     FileParse(String),
-    Validation(Vec<String>),
+    Validation(Vec<Diagnostic>),
+}
+
+
+impl Error {
+    // actions_path: the building.actions file, for the diagnostics which do not have a file
+    pub fn into_diagnostics(self, actions_path: &Path) -> Vec<Diagnostic> {
+        let mut result = match self {
+            Error::FileRead(e)   => vec![Diagnostic::error("file-read", format!("Could not read file: {}", e))],
+            Error::FileParse(e)  => vec![Diagnostic::error("actions-parse", e)],
+            Error::Validation(d) => d,
+        };
+
+        for d in result.iter_mut().filter(|d| d.file.is_none()) {
+            d.file = Some(actions_path.to_path_buf());
+        }
+
+        result
+    }
 }
 
 
@@ -25,6 +44,7 @@ pub struct ModActions {
     pub mirror: bool,
     pub objects: Option<(ObjectVerb, Vec<String>)>,
     pub rename_sm: Vec<(String, String)>,
+    pub texture_max_size: Option<usize>,
 }
 
 
@@ -54,6 +74,8 @@ pub fn read_actions(actions_path: &Path, buf: &mut String) -> Result<ModActions,
         static ref RX_NAMES:    Regex = Regex::new(r"(?s)\s+([^\s]+)").unwrap();
 
         static ref RX_SUBMAT:   Regex = Regex::new(r"(?s)^SUBMATERIAL_RENAME\s+([^\s]+)\s+([^\s]+)").unwrap();
+
+        static ref RX_TX_SIZE:  Regex = Regex::new(r"(?s)^TEXTURE_MAX_SIZE\s+(\d+)\s*$").unwrap();
     }
 
     buf.clear();
@@ -65,6 +87,7 @@ pub fn read_actions(actions_path: &Path, buf: &mut String) -> Result<ModActions,
     let mut optimize = false;
     let mut objects = None;
     let mut rename_sm = Vec::with_capacity(0);
+    let mut texture_max_size = None;
 
     for token in RX_TOKENS.split(&buf) {
         if token.is_empty() {
@@ -118,17 +141,35 @@ pub fn read_actions(actions_path: &Path, buf: &mut String) -> Result<ModActions,
             let to_name = cap[2].to_string();
 
             rename_sm.push((from_name, to_name));
+        } else if let Some(cap) = RX_TX_SIZE.captures(token) {
+            let size = usize::from_str(&cap[1])
+                .map_err(|e| Error::FileParse(format!("Could not parse TEXTURE_MAX_SIZE as integer: {:?}", e)))?;
+            texture_max_size = Some(size);
         } else {
             return Err(Error::FileParse(format!("Unknown token: [{}]", token)))
         }
 
     }
 
-    Ok(ModActions { scale, offset, optimize, mirror, objects, rename_sm })
+    Ok(ModActions { scale, offset, optimize, mirror, objects, rename_sm, texture_max_size })
 }
 
 
 impl ModActions {
+    // Actions for the other meshes of the building (ANIMATION_MESH etc.): the same geometry changes.
+    // Object and submaterial actions are about the main model.
+    pub fn geometry_only(&self) -> ModActions {
+        ModActions {
+            scale: self.scale,
+            offset: self.offset,
+            optimize: self.optimize,
+            mirror: self.mirror,
+            objects: None,
+            rename_sm: Vec::new(),
+            texture_max_size: self.texture_max_size,
+        }
+    }
+
     pub fn apply_to(&self, nmf: &mut nmf::NmfInfo) {
         if let Some((verb, names)) = &self.objects {
             let mut new_objs = Vec::with_capacity(nmf.objects.len());
@@ -157,11 +198,14 @@ impl ModActions {
     }
 
     pub fn validate<'a>(&self, bld_ini: &Path, nmf_info: &nmf::NmfInfo, str_buf: &mut String) -> Result<(), Error> {
-        if self.scale.is_none() && !self.mirror && self.objects.is_none() && self.rename_sm.is_empty() {
-            return Err(Error::Validation(vec!["Empty ModActions".to_string()]));
+        if self.scale.is_none() && !self.mirror && self.objects.is_none() && self.rename_sm.is_empty() && self.texture_max_size.is_none() {
+            return Err(Error::Validation(vec![Diagnostic::error("actions-invalid", "Empty ModActions".to_string())]));
         }
 
-        let mut errors = Vec::with_capacity(0);
+        // errors about building.ini get its path, others are about building.actions
+        let mut errors = Vec::<Diagnostic>::with_capacity(0);
+        let ini_error = |msg: String| Diagnostic::error("actions-invalid", msg).in_file(bld_ini);
+        let error = |msg: String| Diagnostic::error("actions-invalid", msg);
 
         if let Some((verb, names)) = &self.objects {
             use ini::BuildingNodeRef as REF;
@@ -179,10 +223,10 @@ impl ModActions {
                     for mref in model_refs {
                         match mref {
                             REF::Exact(node)  => if names.iter().all(|kept| kept != node) {
-                                errors.push(format!("building.ini refers to model node '{}', but this node is not present in the actions' KEEP list", node));
+                                errors.push(ini_error(format!("building.ini refers to model node '{}', but this node is not present in the actions' KEEP list", node)));
                             },
                             REF::Keyword(key) => if names.iter().all(|kept| !(kept.starts_with(key))) {
-                                errors.push(format!("Node-referring keyword '${}' is used in the building.ini, but is not present in the actions' KEEP list", key));
+                                errors.push(ini_error(format!("Node-referring keyword '${}' is used in the building.ini, but is not present in the actions' KEEP list", key)));
                             }
                         }
                     }
@@ -191,10 +235,10 @@ impl ModActions {
                     for mref in model_refs {
                         match mref {
                             REF::Exact(node)  => if names.iter().any(|remd| remd == node) {
-                                errors.push(format!("building.ini refers to model node '{}', but this node is present in actions' REMOVE list", node));
+                                errors.push(ini_error(format!("building.ini refers to model node '{}', but this node is present in actions' REMOVE list", node)));
                             },
                             REF::Keyword(key) => {
-                                errors.push(format!("Node-referring keyword '{}' is used in the building.ini. OBJECTS REMOVE action is not supported in this case.", key));
+                                errors.push(ini_error(format!("Node-referring keyword '{}' is used in the building.ini. OBJECTS REMOVE action is not supported in this case.", key)));
                             }
                         }
                     }
@@ -203,14 +247,14 @@ impl ModActions {
 
             for name in names.iter() {
                 if nmf_info.object_names().all(|o| o != name) {
-                    errors.push(format!("Cannot {} object '{}' in the NMF, because such object does not exist", verb, name));
+                    errors.push(error(format!("Cannot {} object '{}' in the NMF, because such object does not exist", verb, name)));
                 }
             }
 
             if names.len() == nmf_info.objects.len() {
                 match verb {
-                    ObjectVerb::Remove => errors.push(format!("Possible attempt to remove all objects. Entries count equals nmf objects count.")),
-                    ObjectVerb::Keep   => errors.push(format!("Possible attempt to keep all objects. Entries count equals nmf objects count."))
+                    ObjectVerb::Remove => errors.push(error("Possible attempt to remove all objects. Entries count equals nmf objects count.".to_string())),
+                    ObjectVerb::Keep   => errors.push(error("Possible attempt to keep all objects. Entries count equals nmf objects count.".to_string()))
                 }
             }
 
@@ -218,7 +262,13 @@ impl ModActions {
 
         for (r, _) in self.rename_sm.iter() {
             if nmf_info.submaterials.iter().all(|sm| sm.as_str() != r) {
-                errors.push(format!("Cannot rename submaterial '{}' in the NMF, because such submaterial does not exist", r));
+                errors.push(error(format!("Cannot rename submaterial '{}' in the NMF, because such submaterial does not exist", r)));
+            }
+        }
+
+        if let Some(size) = self.texture_max_size {
+            if !size.is_power_of_two() {
+                errors.push(error(format!("TEXTURE_MAX_SIZE must be a power of two, got {}", size)));
             }
         }
 
@@ -233,23 +283,6 @@ impl ModActions {
 
 use std::fmt;
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        match self {
-            Error::FileRead(e)   => write!(f, "Could not read file: {}", e),
-            Error::FileParse(e)  => write!(f, "Could not parse file: {}", e),
-            Error::Validation(e) => {
-                writeln!(f, "Validation failed: ")?;
-                for i in e.iter() {
-                    writeln!(f, "    {}", i)?;
-                }
-                Ok(())
-            }
-        }
-    }
-}
-
-
 impl fmt::Display for ObjectVerb {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match self {
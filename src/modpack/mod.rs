@@ -13,10 +13,12 @@ mod actions;
 
 use crate::{read_to_buf, read_to_string_buf};
 use crate::cfg::{AppSettings, APP_SETTINGS, RENDERCONFIG_INI, BUILDING_INI};
-use crate::building_def::{ModBuildingDef, BuildingError as DefError};
-use crate::nmf;
+use crate::building_def::{ModBuildingDef, ExtraMeshKind, EffectRef, BuildingError as DefError};
+use crate::{nmf, dds};
 use crate::ini::{self, resolve_source_path, resolve_stock_path};
 use crate::ini::common::IdStringParam;
+use crate::ini::diagnostic::Diagnostic;
+use crate::report::Report;
 
 use skins::{Skins, Error as SkinsError};
 use actions::{ModActions, Error as ActionsError};
@@ -39,7 +41,7 @@ pub enum SourceError {
     RefParse,
     Skins(SkinsError),
     Actions(ActionsError),
-    Nmf(nmf::Error),
+    Nmf(PathBuf, nmf::Error),
 }
 
 pub const MODPACK_LOG:     &str = "modpack.log";
@@ -51,10 +53,12 @@ const BUILDING_ACTIONS:    &str = "building.actions";
 
 const MATERIAL_MTL:        &str = "material.mtl";
 const MATERIAL_E_MTL:      &str = "material_e.mtl";
-const WORKSHOPCONFIG:      &str = "workshopconfig.ini";
+pub const WORKSHOPCONFIG:  &str = "workshopconfig.ini";
+const INSTALLER_TITLE:     &str = "Automatically generated by wrsr-mt modpack installer";
 
 
-pub fn read_validate_sources(source_dir: &Path) -> Result<(Vec::<BuildingSource>, usize), usize> {
+// Diagnostics go to the report. Err(number of failed sources)
+pub fn read_validate_sources(source_dir: &Path, report: &mut Report) -> Result<(Vec::<BuildingSource>, usize), usize> {
     let mut result = Vec::<BuildingSource>::with_capacity(10000);
 
     let mut errors: usize = 0;
@@ -67,10 +71,10 @@ pub fn read_validate_sources(source_dir: &Path) -> Result<(Vec::<BuildingSource>
 
     while let Some(mut path) = backlog.pop() {
         macro_rules! log_err {
-            ($err:expr $(, $v:expr)*) => {{
+            ($diagnostics:expr) => {{
                 errors += 1;
-                eprintln!("ERROR: {}: {}", path.strip_prefix(source_dir).expect("Impossible: could not strip root prefix").display(), $err);
-                $($v)*
+                report.note(format_args!("FAILED: {}", path.strip_prefix(source_dir).expect("Impossible: could not strip root prefix").display()));
+                report.extend($diagnostics);
             }};
         }
 
@@ -127,26 +131,29 @@ pub fn read_validate_sources(source_dir: &Path) -> Result<(Vec::<BuildingSource>
 
             // VALIDATIONS
             let building_source = building_source.and_then(|bs| {
-                let mut nmf_info = nmf::NmfInfo::from_path(bs.def.model.as_path()).map_err(SourceError::Nmf)?;
+                let mut nmf_info = nmf::NmfInfo::from_path(bs.def.model.as_path()).map_err(|e| SourceError::Nmf(bs.def.model.clone(), e))?;
                 if let Some(act) = &bs.actions {
                     act.validate(&bs.def.building_ini, &nmf_info, &mut str_buf).map_err(SourceError::Actions)?;
                     act.apply_to(&mut nmf_info);
                 }
 
-                bs.def.parse_and_validate(Some(&nmf_info)).map_err(SourceError::Def)?;
+                let apply_actions = |m: &mut nmf::NmfInfo| if let Some(act) = &bs.actions { act.apply_to(m) };
+                let warnings = bs.def.parse_and_validate(Some(&apply_actions)).map_err(SourceError::Def)?;
+                report.extend(warnings);
 
                 let sm_used = nmf_info.get_used_sumbaterials().collect::<Vec<_>>();
-                skins::validate(&bs.skins, &sm_used[..], &mut str_buf).map_err(SourceError::Skins)?;
+                let warnings = skins::validate(&bs.skins, &sm_used[..], &mut str_buf).map_err(SourceError::Skins)?;
+                report.extend(warnings);
 
                 Ok(bs)
             });
 
             match building_source {
                 Ok(bs) => {
-                    println!("OK: {}", path.strip_prefix(source_dir).expect("Impossible: could not strip root prefix").display());
+                    report.note(format_args!("OK: {}", path.strip_prefix(source_dir).expect("Impossible: could not strip root prefix").display()));
                     result.push(bs)
                 },
-                Err(e) => log_err!(e)
+                Err(e) => log_err!(e.into_diagnostics(&path))
             }
         } else {
             // try to push sub-dirs to backlog
@@ -161,14 +168,14 @@ pub fn read_validate_sources(source_dir: &Path) -> Result<(Vec::<BuildingSource>
                                 }
                                 Ok(())
                             })
-                        ) { log_err!(e) }
+                        ) { log_err!(vec![dir_error(&path, e)]) }
                     }
 
                     while let Some(x) = rev_buf.pop() {
                         backlog.push(x);
                     }
                 },
-                Err(e) => log_err!(e)
+                Err(e) => log_err!(vec![dir_error(&path, e)])
             }
         }
     }
@@ -181,9 +188,17 @@ pub fn read_validate_sources(source_dir: &Path) -> Result<(Vec::<BuildingSource>
 }
 
 
+fn dir_error(path: &Path, e: IOErr) -> Diagnostic {
+    Diagnostic::error("file-read", format!("Cannot read directory: {}", e)).in_file(path)
+}
+
+
 
 
-type AssetsMap = ahash::AHashMap::<PathBuf, PathBuf>;
+// (source path, texture max size) -> installed asset
+type AssetsMap = ahash::AHashMap::<(PathBuf, Option<usize>), PathBuf>;
+
+type SkinEntry<'a> = (usize, usize, &'a PathBuf, Option<&'a PathBuf>, Option<usize>);
 
 pub fn install(sources: Vec<BuildingSource>, target: &Path, log_file: &mut BufWriter<fs::File>) {
     
@@ -191,12 +206,14 @@ pub fn install(sources: Vec<BuildingSource>, target: &Path, log_file: &mut BufWr
     fs::create_dir_all(&dds_root).unwrap();
     let nmf_root = target.join("nmf");
     fs::create_dir_all(&nmf_root).unwrap();
+    // created with the first sound or effect file
+    let fx_root = target.join("fx");
 
     let mut pathbuf = target.to_path_buf();
     let mut assets_map = AssetsMap::with_capacity(10000);
     let mut str_buf = String::with_capacity(16 * 1024);
     let mut byte_buf = Vec::<u8>::with_capacity(32 * 1024 * 1024);
-    let mut skins_buf = Vec::<(usize, usize, &PathBuf, Option<&PathBuf>)>::with_capacity(AppSettings::MAX_SKINS_IN_MOD);
+    let mut skins_buf = Vec::<SkinEntry>::with_capacity(AppSettings::MAX_SKINS_IN_MOD);
 
     let mut src_iter = sources.iter();
     let mut mod_id_iter = (AppSettings::MOD_IDS_START .. AppSettings::MOD_IDS_END).into_iter();
@@ -213,9 +230,10 @@ pub fn install(sources: Vec<BuildingSource>, target: &Path, log_file: &mut BufWr
 
                 fs::create_dir_all(&pathbuf).unwrap();
 
-                install_building(&src.def, &src.actions, &pathbuf, &dds_root, &nmf_root, &mut assets_map, &mut str_buf, &mut byte_buf).unwrap();
+                install_building(&src.def, &src.actions, &pathbuf, &dds_root, &nmf_root, &fx_root, &mut assets_map, &mut str_buf, &mut byte_buf).unwrap();
+                let texture_max_size = src.actions.as_ref().and_then(|a| a.texture_max_size);
                 for (skin, skin_e) in src.skins.iter() {
-                    skins_buf.push((mod_id, bld_id, skin, skin_e.as_ref()));
+                    skins_buf.push((mod_id, bld_id, skin, skin_e.as_ref(), texture_max_size));
                     if skins_buf.len() == AppSettings::MAX_SKINS_IN_MOD {
                         let skin_mod_id = write_skins_mod(target, &mut mod_id_iter, &skins_buf[..], &dds_root, &mut assets_map, &mut str_buf, &mut byte_buf);
                         skins_buf.clear();
@@ -226,7 +244,7 @@ pub fn install(sources: Vec<BuildingSource>, target: &Path, log_file: &mut BufWr
                 pathbuf.pop();
             } else {
                 pathbuf.push(WORKSHOPCONFIG);
-                write_workshop_ini_buildings(pathbuf.as_path(), mod_id, bld_id, &mut str_buf);
+                write_workshop_ini_buildings(pathbuf.as_path(), mod_id, bld_id, INSTALLER_TITLE, &mut str_buf);
                 if !skins_buf.is_empty() {
                     let skin_mod_id = write_skins_mod(target, &mut mod_id_iter, &skins_buf[..], &dds_root, &mut assets_map, &mut str_buf, &mut byte_buf);
                     writeln!(log_file, "{} <SKINS>", skin_mod_id).unwrap();
@@ -236,7 +254,7 @@ pub fn install(sources: Vec<BuildingSource>, target: &Path, log_file: &mut BufWr
         }
 
         pathbuf.push(WORKSHOPCONFIG);
-        write_workshop_ini_buildings(pathbuf.as_path(), mod_id, AppSettings::MAX_BUILDINGS_IN_MOD, &mut str_buf);
+        write_workshop_ini_buildings(pathbuf.as_path(), mod_id, AppSettings::MAX_BUILDINGS_IN_MOD, INSTALLER_TITLE, &mut str_buf);
         pathbuf.pop();
         pathbuf.pop();
     }
@@ -245,7 +263,7 @@ pub fn install(sources: Vec<BuildingSource>, target: &Path, log_file: &mut BufWr
 #[must_use]
 fn write_skins_mod(target: &Path, 
                    mod_id_iter: &mut impl Iterator<Item = usize>, 
-                   skins: &[(usize, usize, &PathBuf, Option<&PathBuf>)], 
+                   skins: &[SkinEntry], 
                    dds_root: &Path,
                    assets_map: &mut AssetsMap,
                    str_buf: &mut String,
@@ -268,14 +286,14 @@ fn write_skins_mod(target: &Path,
          $VISIBILITY 2\n", 
         mod_id).unwrap();
 
-    for ((m, b, mtl, mtl_e), i) in skins.iter().zip(1..) {
+    for ((m, b, mtl, mtl_e, tx_max_size), i) in skins.iter().zip(1..) {
         str_buf.clear();
         write!(str_buf, "{:0>2}.mtl", i).unwrap();
         write!(config_buf, "\n$TARGET_BUILDING_SKIN {}/{:0>2} {}", m, b, str_buf).unwrap();
 
         pathbuf.push(&str_buf);
         fs::copy(&mtl, &pathbuf).expect("Could not copy skin's mtl file");
-        update_mtl(&pathbuf, &mtl, dds_root, assets_map, str_buf, byte_buf).unwrap();
+        update_mtl(&pathbuf, &mtl, *tx_max_size, dds_root, assets_map, str_buf, byte_buf).unwrap();
         pathbuf.pop();
 
         if let Some(mtl) = mtl_e {
@@ -285,7 +303,7 @@ fn write_skins_mod(target: &Path,
 
             pathbuf.push(&str_buf);
             fs::copy(mtl, &pathbuf).expect("Could not copy skin's mtl_e file");
-            update_mtl(&pathbuf, &mtl, dds_root, assets_map, str_buf, byte_buf).unwrap();
+            update_mtl(&pathbuf, &mtl, *tx_max_size, dds_root, assets_map, str_buf, byte_buf).unwrap();
             pathbuf.pop();
         }
     }
@@ -300,7 +318,8 @@ fn write_skins_mod(target: &Path,
     mod_id
 }
 
-fn write_workshop_ini_buildings(path: &Path, mod_id: usize, count: usize, buf: &mut String) {
+// Mod with the buildings in subdirectories 00 .. count; title is the item name and description
+pub fn write_workshop_ini_buildings(path: &Path, mod_id: usize, count: usize, title: &str, buf: &mut String) {
     if count == 0 {
         return;
     }
@@ -317,9 +336,9 @@ fn write_workshop_ini_buildings(path: &Path, mod_id: usize, count: usize, buf: &
         writeln!(buf, "$OBJECT_BUILDING {:0>2}", i).unwrap();
     }
 
-    writeln!(buf, "\n$ITEM_NAME \"Automatically generated by wrsr-mt modpack installer\"\n\
-                   $ITEM_DESC \"Automatically generated by wrsr-mt modpack installer\"\n\n\
-                   $END").unwrap();
+    writeln!(buf, "\n$ITEM_NAME \"{0}\"\n\
+                   $ITEM_DESC \"{0}\"\n\n\
+                   $END", title).unwrap();
 
     fs::write(path, buf).unwrap();
 }
@@ -329,6 +348,7 @@ fn install_building(src_def: &ModBuildingDef,
                     destination: &Path, 
                     dds_root: &Path,
                     nmf_root: &Path,
+                    fx_root: &Path,
                     assets_map: &mut AssetsMap, 
                     str_buf: &mut String,
                     byte_buf: &mut Vec<u8>) -> Result<(), IOErr> {
@@ -360,10 +380,11 @@ fn install_building(src_def: &ModBuildingDef,
     }
 
     macro_rules! copy_nmf_token {
-        ($nmf_path:expr) => {{
+        ($nmf_path:expr) => { copy_nmf_token!($nmf_path, actions) };
+        ($nmf_path:expr, $actions:expr) => {{
             let nmf_path = $nmf_path;
-            match actions {
-                None          => nmf_path.push(copy_asset_md5(nmf_path, nmf_root, byte_buf, assets_map)?),
+            match $actions {
+                None          => nmf_path.push(copy_asset_md5(nmf_path, nmf_root, None, byte_buf, assets_map)?),
                 Some(actions) => nmf_path.push(copy_nmf_with_actions(nmf_path, nmf_root, byte_buf, actions)?)
             };
 
@@ -381,7 +402,7 @@ fn install_building(src_def: &ModBuildingDef,
 
     macro_rules! update_mtl {
         ($mtl_path:expr, $old_mtl_path:expr) => {
-            update_mtl($mtl_path, $old_mtl_path, &dds_root, assets_map, str_buf, byte_buf)
+            update_mtl($mtl_path, $old_mtl_path, actions.as_ref().and_then(|a| a.texture_max_size), &dds_root, assets_map, str_buf, byte_buf)
         }
     }
 
@@ -399,10 +420,43 @@ fn install_building(src_def: &ModBuildingDef,
         let model_lod2_token: Option<String> = copy_nmf_token_opt!(new_def.model_lod2)?;
         let model_e_token:    Option<String> = copy_nmf_token_opt!(new_def.model_e)?;
 
+        // Extra meshes: models are installed as assets, with only the geometry actions applied,
+        // materials are copied next to the config files
+        let mesh_actions = actions.as_ref().map(|a| a.geometry_only());
+        let mut mesh_tokens = Vec::<(ExtraMeshKind, String, String)>::with_capacity(new_def.extra_meshes.len());
+        for (i, (mesh, src_mesh)) in new_def.extra_meshes.iter_mut().zip(src_def.extra_meshes.iter()).enumerate() {
+            let model_token = copy_nmf_token!(&mut mesh.model, &mesh_actions)?;
+            let mtl_name = format!("{}_{}.mtl", mesh.kind.token_name().to_lowercase(), i);
+            mesh.material = destination.join(&mtl_name);
+            fs::copy(&src_mesh.material, &mesh.material)?;
+            mesh_tokens.push((mesh.kind, model_token, mtl_name));
+        }
+
+        // Sounds and effects: stock files are referenced as they are, others are installed as assets
+        let mut fx_tokens = Vec::<Option<String>>::with_capacity(new_def.effects.len());
+        for fx in new_def.effects.iter_mut() {
+            if fx.path.starts_with(APP_SETTINGS.path_stock.as_path()) {
+                fx_tokens.push(None);
+            } else {
+                fs::create_dir_all(fx_root)?;
+                fx.path = copy_asset_md5(&fx.path, fx_root, None, byte_buf, assets_map)?.to_path_buf();
+                fx_tokens.push(Some(make_relative_token(&new_render_path, &fx.path).expect("Could not construct relative effect token")));
+            }
+        }
+
+        let is_building_fx = |fx: &&EffectRef| fx.token == EffectRef::WORKING_SFX;
+        let mut render_fx = new_def.effects.iter().zip(fx_tokens.iter()).filter(|(fx, _)| !is_building_fx(fx)).map(|(_, t)| t.as_ref());
+        let mut building_fx = new_def.effects.iter().zip(fx_tokens.iter()).filter(|(fx, _)| is_building_fx(fx)).map(|(_, t)| t.as_ref());
+
+        let mk_mesh_param = |(_, m, mtl): &(ExtraMeshKind, String, String)| (IdStringParam::new_cloned(m), IdStringParam::new_cloned(mtl));
+        let mut derbis_meshes = mesh_tokens.iter().filter(|(k, _, _)| *k == ExtraMeshKind::Derbis);
+        let mut building_meshes = mesh_tokens.iter().filter(|(k, _, _)| *k != ExtraMeshKind::Derbis);
+
         // Update renderconfig.ini
 
-        read_to_string_buf(&new_render_path, str_buf)?;
+        let enc = read_to_string_buf(&new_render_path, str_buf)?;
         let mut render_ini = ini::parse_renderconfig_ini(str_buf).expect("Invalid building renderconfig");
+        render_ini.set_encoding(enc);
         for token_state in render_ini.tokens_mut() {
             token_state.modify(|t| {
                 use ini::renderconfig::Token as RT;
@@ -414,6 +468,10 @@ fn install_building(src_def: &ModBuildingDef,
                     RT::ModelEmissive(_)    => model_e_token.as_ref().map(|t| RT::ModelEmissive(IdStringParam::new_cloned(t))),
                     RT::Material(_)         => Some(RT::Material(IdStringParam::new_cloned(MATERIAL_MTL))),
                     RT::MaterialEmissive(_) => new_def.material_e.as_ref().map(|_| RT::MaterialEmissive(IdStringParam::new_cloned(MATERIAL_E_MTL))),
+                    RT::DerbisMesh(_)       => derbis_meshes.next().map(|m| RT::DerbisMesh(mk_mesh_param(m))),
+                    RT::DerbisFalledSfx(_)      => render_fx.next().flatten().map(|t| RT::DerbisFalledSfx(IdStringParam::new_cloned(t))),
+                    RT::DerbisFallingFx((_, x)) => render_fx.next().flatten().map(|t| RT::DerbisFallingFx((IdStringParam::new_cloned(t), *x))),
+                    RT::DerbisFalledFx((_, x))  => render_fx.next().flatten().map(|t| RT::DerbisFalledFx((IdStringParam::new_cloned(t), *x))),
                     _ => None
                 }
             });
@@ -437,8 +495,23 @@ fn install_building(src_def: &ModBuildingDef,
         render_ini.write_file(new_render_path)?;
 
         // Apply actions to building.ini
-        read_to_string_buf(&new_def.building_ini, str_buf)?;
+        let enc = read_to_string_buf(&new_def.building_ini, str_buf)?;
         let mut bld_ini = ini::parse_building_ini(str_buf).expect("Invalid building ini");
+        bld_ini.set_encoding(enc);
+        for token_state in bld_ini.tokens_mut() {
+            token_state.modify(|t| {
+                use ini::BuildingToken as BT;
+
+                match t {
+                    BT::AnimationMesh(_)         => building_meshes.next().map(|m| BT::AnimationMesh(mk_mesh_param(m))),
+                    BT::AnimationMeshWorkshop(_) => building_meshes.next().map(|m| BT::AnimationMeshWorkshop(mk_mesh_param(m))),
+                    BT::UndergroundMesh(_)       => building_meshes.next().map(|m| BT::UndergroundMesh(mk_mesh_param(m))),
+                    BT::WorkingSfx(_)            => building_fx.next().flatten().map(|t| BT::WorkingSfx(IdStringParam::new_cloned(t))),
+                    _ => None
+                }
+            });
+        }
+
         if let Some(actions) = actions {
             if let Some(factor) = actions.scale {
                 ini::transform::scale_building(&mut bld_ini, factor);
@@ -460,6 +533,9 @@ fn install_building(src_def: &ModBuildingDef,
     if let (Some(material_e), Some(src_mtl_e)) = (&new_def.material_e, &src_def.material_e) {
         update_mtl!(material_e, src_mtl_e)?;
     }
+    for (mesh, src_mesh) in new_def.extra_meshes.iter().zip(src_def.extra_meshes.iter()) {
+        update_mtl!(&mesh.material, &src_mesh.material)?;
+    }
 
     Ok(())
 }
@@ -490,28 +566,42 @@ fn get_source_type_from_ref(bld_ini: PathBuf, mut render_ref: BasePathBuf, buf:
 }
 
 
-fn copy_asset_md5<'map>(asset_path: &Path, assets_root: &Path, byte_buf: &mut Vec<u8>, assets_map: &'map mut AssetsMap) -> Result<&'map Path, IOErr> {
+// texture_max_size: *.dds assets are downscaled to fit (see dds::Dds::shrink_to)
+fn copy_asset_md5<'map>(asset_path: &Path, assets_root: &Path, texture_max_size: Option<usize>, byte_buf: &mut Vec<u8>, assets_map: &'map mut AssetsMap) -> Result<&'map Path, IOErr> {
+    let key = (asset_path.to_path_buf(), texture_max_size);
 
     // TODO: update this when borrowchecker is made less stupid
-    if !assets_map.contains_key(asset_path) {
+    if !assets_map.contains_key(&key) {
         let file_ext = asset_path.extension()
             .ok_or_else(|| IOErr::new(std::io::ErrorKind::Other, "Asset has no extension"))?
             .to_string_lossy();
 
         read_to_buf(asset_path, byte_buf)?;
+
+        if let (Some(max_size), true) = (texture_max_size, file_ext.eq_ignore_ascii_case("dds")) {
+            let smaller = dds::Dds::from_bytes(byte_buf).and_then(|dds| dds.shrink_to(max_size));
+            match smaller {
+                Ok(Some(smaller)) => {
+                    byte_buf.clear();
+                    smaller.write_to(&mut *byte_buf)?;
+                },
+                Ok(None) => (),
+                Err(e) => eprintln!("WARNING: texture {} is copied as is, cannot downscale: {}", asset_path.display(), e)
+            }
+        }
+
         let asset_md5name = format!("{:x}.{}", md5::compute(byte_buf.as_mut_slice()), file_ext);
 
-        let new_key = asset_path.to_path_buf();
         let new_val = assets_root.join(&asset_md5name);
 
         if !new_val.exists() {
             fs::write(&new_val, byte_buf.as_slice())?;
         }
 
-        assets_map.insert(new_key, new_val);
+        assets_map.insert(key.clone(), new_val);
     }
 
-    let v = assets_map.get(asset_path).expect("HasMap 'get' failed right after insertion").as_path();
+    let v = assets_map.get(&key).expect("HasMap 'get' failed right after insertion").as_path();
     Ok(v)
 }
 
@@ -588,19 +678,21 @@ fn copy_nmf_with_actions(asset_path: &Path, assets_root: &Path, byte_buf: &mut V
 // panics on invalid mtl
 fn update_mtl(mtl_path: &Path, 
               old_mtl_path: &Path, 
+              texture_max_size: Option<usize>,
               dds_root: &Path, 
               assets_map: &mut AssetsMap,
               str_buf: &mut String, 
               byte_buf: &mut Vec<u8>
               ) -> Result<(), IOErr> {
     let old_mtl_root = old_mtl_path.parent().unwrap();
-    read_to_string_buf(mtl_path, str_buf)?;
+    let enc = read_to_string_buf(mtl_path, str_buf)?;
     let mut mtl = ini::parse_mtl(str_buf).expect("Invalid *.mtl");
+    mtl.set_encoding(enc);
 
     macro_rules! update_tx_token {
         ($token:ident, $path_resolver:expr) => {{
             let src_tx_path = $path_resolver($token);
-            let new_tx_path = copy_asset_md5(&src_tx_path, dds_root, byte_buf, assets_map).expect("Could not copy texture when updating mtl");
+            let new_tx_path = copy_asset_md5(&src_tx_path, dds_root, texture_max_size, byte_buf, assets_map).expect("Could not copy texture when updating mtl");
             let tx_token = make_relative_token(mtl_path, &new_tx_path).expect("Could not construct relative texture token");
             ini::common::IdStringParam::new_owned(tx_token)
         }}
@@ -624,33 +716,53 @@ fn update_mtl(mtl_path: &Path,
     mtl.write_file(mtl_path)
 }
 
+// Token of path_to, relative to the directory of path_from (which can be the same directory or its parent).
+// None if there is no relative path (e.g. the files are on different drives).
 pub fn make_relative_token(path_from: &Path, path_to: &Path) -> Option<String> {
+    use std::path::Component;
 
-    let mut iter_from = path_from.parent()?.components();
-    let mut iter_to = path_to.parent()?.components();
+    let mut iter_from = path_from.parent()?.components().peekable();
+    let mut iter_to = path_to.parent()?.components().peekable();
 
-    while let (Some(c_from), Some(c_to)) = (iter_from.next(), iter_to.next()) {
+    while let (Some(c_from), Some(c_to)) = (iter_from.peek(), iter_to.peek()) {
         if c_from != c_to {
-            let mut new_token = String::with_capacity(128);
-            new_token.push_str("../");
-            for _ in iter_from {
-                new_token.push_str("../");
-            }
+            break;
+        }
 
-            new_token.push_str(&c_to.as_os_str().to_string_lossy());
-            new_token.push_str("/");
-            for c in iter_to {
-                new_token.push_str(&c.as_os_str().to_string_lossy());
-                new_token.push_str("/");
-            }
+        iter_from.next();
+        iter_to.next();
+    }
 
-            new_token.push_str(&path_to.file_name()?.to_string_lossy());
+    let is_root = |c: Option<&Component>| matches!(c, Some(Component::Prefix(_)) | Some(Component::RootDir));
+    if is_root(iter_from.peek()) || is_root(iter_to.peek()) {
+        return None;
+    }
 
-            return Some(new_token);
-        }
+    let mut new_token = String::with_capacity(128);
+    for _ in iter_from {
+        new_token.push_str("../");
     }
 
-    None
+    for c in iter_to {
+        new_token.push_str(&c.as_os_str().to_string_lossy());
+        new_token.push('/');
+    }
+
+    new_token.push_str(&path_to.file_name()?.to_string_lossy());
+
+    Some(new_token)
+}
+
+// Token of path_to for a config file at path_from: the files of the game get a '~'-prefixed token
+// (see resolve_source_path), which does not depend on where the game is installed; others a relative one
+pub fn make_asset_token(path_from: &Path, path_to: &Path) -> Option<String> {
+    match path_to.strip_prefix(APP_SETTINGS.path_stock.as_path()) {
+        Ok(tail) => {
+            let names: Vec<_> = tail.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+            Some(format!("~{}", names.join("/")))
+        },
+        Err(_) => make_relative_token(path_from, path_to)
+    }
 }
 
 impl fmt::Display for BuildingSource {
@@ -661,18 +773,20 @@ impl fmt::Display for BuildingSource {
     }
 }
 
-impl fmt::Display for SourceError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+impl SourceError {
+    // source_dir: directory of the building source
+    fn into_diagnostics(self, source_dir: &Path) -> Vec<Diagnostic> {
         use SourceError as E;
+        let error = |code, msg: &str, file: &Path| vec![Diagnostic::error(code, msg.to_string()).in_file(file)];
         match self {
-            E::NoRenderconfig    => write!(f, "Building source is missing one of renderconfig.source or renderconfig.ref"),
-            E::MultiRenderconfig => write!(f, "Building source has both renderconfig.source and renderconfig.ref. Only one is required."),
-            E::Def(e)            => write!(f, "BuildingDef error: {}", e),
-            E::RefRead(e)        => write!(f, "Error reading building reference: {}", e),
-            E::RefParse          => write!(f, "Cannot parse building reference"),
-            E::Skins(e)          => write!(f, "Skins error: {:#?}", e),
-            E::Actions(e)        => write!(f, "Actions error: {}", e),
-            E::Nmf(e)            => write!(f, "Nmf error: {:#?}", e),
+            E::NoRenderconfig    => error("renderconfig-missing", "Building source is missing one of renderconfig.source or renderconfig.ref", source_dir),
+            E::MultiRenderconfig => error("renderconfig-missing", "Building source has both renderconfig.source and renderconfig.ref. Only one is required.", source_dir),
+            E::Def(e)            => e.into_diagnostics(),
+            E::RefRead(e)        => error("file-read", &format!("Error reading building reference: {}", e), &source_dir.join(RENDERCONFIG_REF)),
+            E::RefParse          => error("ref-invalid", "Cannot parse building reference", &source_dir.join(RENDERCONFIG_REF)),
+            E::Skins(e)          => e.into_diagnostics(&source_dir.join(BUILDING_SKINS)),
+            E::Actions(e)        => e.into_diagnostics(&source_dir.join(BUILDING_ACTIONS)),
+            E::Nmf(path, e)      => error("nmf-error", &format!("Cannot load nmf: {:?}", e), &path),
         }
     }
 }
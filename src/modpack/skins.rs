@@ -6,6 +6,7 @@ use regex::Regex;
 
 use crate::{read_to_string_buf};
 use crate::ini::{self, resolve_source_path};
+use crate::ini::diagnostic::Diagnostic;
 use crate::building_def;
 
 
@@ -14,10 +15,25 @@ use crate::building_def;
 pub enum Error {
     SkinsFileRead(IOErr),
     SkinsFileParse(String),
-    MtlRead(IOErr),
-    MtlParse(PathBuf, Vec<String>),
-    SkinValidation(Vec<String>),
-    TexturePathInvalid(PathBuf),
+    MtlRead(PathBuf, IOErr),
+    MtlParse(Vec<Diagnostic>),
+    SkinValidation(Vec<Diagnostic>),
+    TexturePathInvalid(PathBuf, PathBuf),
+}
+
+
+impl Error {
+    // skins_path: the building.skins file
+    pub fn into_diagnostics(self, skins_path: &Path) -> Vec<Diagnostic> {
+        match self {
+            Error::SkinsFileRead(e)              => vec![Diagnostic::error("file-read", format!("Could not read skins file: {}", e)).in_file(skins_path)],
+            Error::SkinsFileParse(e)             => vec![Diagnostic::error("skins-parse", format!("Could not parse skins file line: {}", e)).in_file(skins_path)],
+            Error::MtlRead(path, e)              => vec![Diagnostic::error("file-read", format!("Could not read *.mtl file: {}", e)).in_file(&path)],
+            Error::MtlParse(d)                   => d,
+            Error::SkinValidation(d)             => d,
+            Error::TexturePathInvalid(mtl, path) => vec![Diagnostic::error("file-missing", format!("Texture does not exist: {}", path.display())).in_file(&mtl)],
+        }
+    }
 }
 
 
@@ -53,26 +69,26 @@ pub fn read_skins(path: &Path, buf: &mut String) -> Result<Skins, Error> {
 }
 
 
-pub fn validate(skins: &Skins, used_submaterials: &[&str], buf: &mut String) -> Result<(), Error> {
+// Ok(warnings) when there are no errors
+pub fn validate(skins: &Skins, used_submaterials: &[&str], buf: &mut String) -> Result<Vec<Diagnostic>, Error> {
     let mut validation_errors = Vec::with_capacity(0);
 
     macro_rules! check_mtl {
         ($mtl_path:ident) => {
             buf.clear();
-            read_to_string_buf($mtl_path, buf).map_err(Error::MtlRead)?;
-            let mtl = ini::parse_mtl(buf).map_err(|e| Error::MtlParse(
-                $mtl_path.clone(), 
-                e.into_iter().map(|(_, e)|  e).collect())
-                )?;
+            read_to_string_buf($mtl_path, buf).map_err(|e| Error::MtlRead($mtl_path.clone(), e))?;
+            let mtl = ini::parse_mtl(buf).map_err(|e| Error::MtlParse(ini::diagnostic::parse_diagnostics($mtl_path, buf, &e)))?;
 
-            building_def::push_mtl_errors(&mtl, used_submaterials.iter(), &mut validation_errors, $mtl_path.display());
+            building_def::push_mtl_errors(&mtl, ($mtl_path.as_path(), used_submaterials.iter()), &mut validation_errors, $mtl_path.display());
             let mtl_root = $mtl_path.parent().unwrap();
 
             for tx in mtl.get_texture_paths(|p| resolve_source_path(mtl_root, p)) {
                 if !tx.exists() {
-                    return Err(Error::TexturePathInvalid(tx));
+                    return Err(Error::TexturePathInvalid($mtl_path.clone(), tx));
                 }
             }
+
+            building_def::push_texture_errors(&mtl, mtl_root, &mut validation_errors, $mtl_path.display());
         }
     }
 
@@ -83,9 +99,9 @@ pub fn validate(skins: &Skins, used_submaterials: &[&str], buf: &mut String) ->
         }
     }
 
-    if validation_errors.is_empty() {
-        Ok(())
-    } else {
+    if validation_errors.iter().any(Diagnostic::is_error) {
         Err(Error::SkinValidation(validation_errors))
+    } else {
+        Ok(validation_errors)
     }
 }
@@ -7,6 +7,7 @@ use std::convert::TryInto;
 pub mod object_full;
 
 pub use object_full::ObjectFull;
+use object_full::{RawBBox, RawVertex};
 
 
 #[derive(Debug)]
@@ -67,6 +68,7 @@ pub struct NameBuf {
 pub struct ObjectInfo {
     pub name: NameBuf,
     range: std::ops::Range<u64>,
+    bbox: RawBBox,
     vertices: u32,
     faces: u32,
     submat_main: u32,
@@ -155,7 +157,16 @@ impl<R: Read + Seek> ObjectReader<R> for ObjectInfo {
 
         skip(rdr, 8)?;
         let name = NameBuf::from_reader(rdr).map_err(ObjectError::FileIO)?;
-        skip(rdr, 164)?;
+        skip(rdr, 132)?;
+
+        let bbox = {
+            let mut v = [0f32; 6];
+            for x in v.iter_mut() {
+                *x = f32::from_bits(read_u32(rdr)?);
+            }
+            RawBBox { v_min: RawVertex { x: v[0], y: v[1], z: v[2] }, v_max: RawVertex { x: v[3], y: v[4], z: v[5] } }
+        };
+        skip(rdr, 8)?;
 
         let vertices = read_u32(rdr)?;
         let indices = read_u32(rdr)?;
@@ -192,6 +203,7 @@ impl<R: Read + Seek> ObjectReader<R> for ObjectInfo {
         Ok(ObjectInfo { 
             name, 
             range: start .. end,
+            bbox,
             vertices,
             faces,
             submat_main,
@@ -223,6 +235,21 @@ impl NmfInfo {
     pub fn object_names(&self) -> impl Iterator<Item = &str> + Clone {
         self.objects.iter().map(|o| o.name.as_str())
     }
+
+    pub fn triangles(&self) -> usize {
+        self.objects.iter().map(|o| o.faces as usize).sum()
+    }
+
+    // Bounding box of all objects with geometry
+    pub fn bbox(&self) -> Option<RawBBox> {
+        self.objects.iter()
+                    .filter(|o| o.vertices > 0)
+                    .map(|o| o.bbox.clone())
+                    .reduce(|a, b| RawBBox {
+                        v_min: RawVertex { x: a.v_min.x.min(b.v_min.x), y: a.v_min.y.min(b.v_min.y), z: a.v_min.z.min(b.v_min.z) },
+                        v_max: RawVertex { x: a.v_max.x.max(b.v_max.x), y: a.v_max.y.max(b.v_max.y), z: a.v_max.z.max(b.v_max.z) },
+                    })
+    }
 }
 
 
@@ -66,19 +66,19 @@ pub struct RawFaceExtra {
 
 
 #[repr(C)]
+#[derive(Clone)]
 pub struct RawBBox {
     pub v_min: RawVertex,
     pub v_max: RawVertex,
 }
 
-/*
+// Faces with indices in index_1 .. index_2 (of the index buffer) use the submaterial sm_index
 #[repr(C)]
-struct SubmaterialUsage {
-    index_1:  u32,
-    index_2:  u32,
-    sm_index: u32
+pub struct SubmaterialUsage {
+    pub index_1:  u32,
+    pub index_2:  u32,
+    pub sm_index: u32
 }
-*/
 
 
 
@@ -276,6 +276,28 @@ impl ObjectFull {
         self.get_slice::<RawPoint>(self.uv_map_start, self.vertices_count)
     }
 
+    pub fn submaterial_usage<'a>(&'a self) -> &'a [SubmaterialUsage] {
+        self.get_slice::<SubmaterialUsage>(self.submat_start, self.submat_count)
+    }
+
+    // submaterial of the first usage record (the object's main submaterial)
+    pub fn submaterial_main(&self) -> Option<usize> {
+        self.submaterial_usage().first().map(|u| u.sm_index as usize)
+    }
+
+    // Submaterial of every face by the usage records. Faces outside of all records get the main one.
+    pub fn face_submaterials(&self) -> Vec<Option<usize>> {
+        let mut result = vec![self.submaterial_main(); self.faces_count];
+        for u in self.submaterial_usage() {
+            let from = (u.index_1 as usize / 3).min(self.faces_count);
+            let to = (u.index_2 as usize / 3).clamp(from, self.faces_count);
+            for sm in result[from .. to].iter_mut() {
+                *sm = Some(u.sm_index as usize);
+            }
+        }
+        result
+    }
+
     pub fn face_extras_mut<'a>(&'a mut self) -> &'a mut [RawFaceExtra] {
         self.get_slice_mut::<RawFaceExtra>(self.face_ext_start, self.faces_count)
     }
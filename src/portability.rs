@@ -0,0 +1,238 @@
+// Portability of the paths in renderconfig, building.ini and *.mtl tokens. Windows does not care
+// about the case of file names, Linux (and Proton) does; Windows does not allow some characters
+// and trailing spaces in file names. Path components of the tokens are compared with the actual
+// directory entries.
+
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::read_text;
+use crate::cfg::APP_SETTINGS;
+use crate::encoding::TextEncoding;
+use crate::ini::{self, BuildingToken as BT, RenderToken as RT, MaterialToken as MT};
+use crate::ini::common::IdStringParam;
+use crate::ini::diagnostic::Diagnostic;
+
+
+const INVALID_CHARS: &[char] = &['<', '>', ':', '"', '|', '?', '*'];
+
+
+#[derive(Clone, Copy)]
+enum FileKind {
+    Render,
+    Building,
+    Mtl,
+}
+
+
+// Path parameter of a token: its range in the source, its issues, the corrected text
+// (when the case of the path is wrong) and the referenced *.mtl, if it is one
+struct PathToken {
+    range: Range<usize>,
+    issues: Vec<Diagnostic>,
+    fixed: Option<String>,
+    mtl: Option<PathBuf>,
+}
+
+
+// Directory listings, a building refers to the same few directories many times
+type DirCache = ahash::AHashMap<PathBuf, Vec<String>>;
+
+
+// Goes through renderconfig, building.ini and the *.mtl files they refer to (as corrected, when fix is true)
+fn scan_building<F>(render: &Path, building_ini: &Path, fix: bool, mut f: F) -> io::Result<()>
+where F: FnMut(&Path, String, TextEncoding, Vec<PathToken>) -> io::Result<()>
+{
+    let mut cache = DirCache::default();
+    let mut files = vec![(render.to_path_buf(), FileKind::Render), (building_ini.to_path_buf(), FileKind::Building)];
+    let mut i = 0;
+    while let Some((path, kind)) = files.get(i).cloned() {
+        i += 1;
+        let (src, enc, tokens) = match scan_file(&path, kind, fix, &mut cache) {
+            Some(x) => x,
+            None => continue
+        };
+
+        for mtl in tokens.iter().filter_map(|t| t.mtl.as_ref()) {
+            if files.iter().all(|(p, _)| p != mtl) {
+                files.push((mtl.clone(), FileKind::Mtl));
+            }
+        }
+
+        f(&path, src, enc, tokens)?;
+    }
+
+    Ok(())
+}
+
+
+pub fn check(render: &Path, building_ini: &Path) -> Vec<Diagnostic> {
+    let mut result = Vec::with_capacity(0);
+    scan_building(render, building_ini, false, |_, _, _, tokens| {
+        result.extend(tokens.into_iter().flat_map(|t| t.issues));
+        Ok(())
+    }).unwrap();
+
+    result
+}
+
+
+// Rewrites the path tokens with the wrong case. Returns (file, old token, new token) for every change.
+pub fn fix_case(render: &Path, building_ini: &Path) -> io::Result<Vec<(PathBuf, String, String)>> {
+    let mut result = Vec::with_capacity(0);
+    scan_building(render, building_ini, true, |path, mut src, enc, tokens| {
+        let first = result.len();
+        // from the end, so that the ranges stay valid
+        for t in tokens.into_iter().rev() {
+            if let Some(fixed) = t.fixed {
+                result.push((path.to_path_buf(), src[t.range.clone()].to_string(), fixed.clone()));
+                src.replace_range(t.range, &fixed);
+            }
+        }
+
+        if result.len() > first {
+            result[first ..].reverse();
+            fs::write(path, enc.encode(&src)?)?;
+        }
+
+        Ok(())
+    })?;
+
+    Ok(result)
+}
+
+
+// None when the file cannot be read or parsed: that is reported by the validation
+fn scan_file(path: &Path, kind: FileKind, fix: bool, cache: &mut DirCache) -> Option<(String, TextEncoding, Vec<PathToken>)> {
+    let (src, enc) = read_text(path).ok()?;
+    let root = path.parent()?;
+    let stock = APP_SETTINGS.path_stock.as_path();
+
+    // (range of the token path in src, directory it is relative to, is it *.mtl)
+    let mut params: Vec<(Range<usize>, &Path, bool)> = Vec::with_capacity(16);
+    match kind {
+        FileKind::Render => for (span, t) in ini::parse_renderconfig_ini(&src).ok()?.tokens_with_spans() {
+            params.extend(locate(&src, span, &match t {
+                RT::Model(p) | RT::ModelEmissive(p) | RT::DerbisFalledSfx(p)
+                | RT::ModelLod((p, _)) | RT::ModelLod2((p, _))
+                | RT::DerbisFallingFx((p, _)) | RT::DerbisFalledFx((p, _)) => vec![(p, root, false)],
+                RT::Material(p) | RT::MaterialEmissive(p) => vec![(p, root, true)],
+                RT::DerbisMesh((m, mtl)) => vec![(m, root, false), (mtl, root, true)],
+                _ => continue
+            }));
+        },
+        FileKind::Building => for (span, t) in ini::parse_building_ini(&src).ok()?.tokens_with_spans() {
+            params.extend(locate(&src, span, &match t {
+                BT::WorkingSfx(p) => vec![(p, root, false)],
+                BT::AnimationMesh((m, mtl)) | BT::AnimationMeshWorkshop((m, mtl))
+                | BT::UndergroundMesh((m, mtl)) => vec![(m, root, false), (mtl, root, true)],
+                _ => continue
+            }));
+        },
+        FileKind::Mtl => for (span, t) in ini::parse_mtl(&src).ok()?.tokens_with_spans() {
+            params.extend(locate(&src, span, &match t {
+                MT::Texture((_, p)) | MT::TextureNoMip((_, p))       => vec![(p, stock, false)],
+                MT::TextureMtl((_, p)) | MT::TextureNoMipMtl((_, p)) => vec![(p, root, false)],
+                _ => continue
+            }));
+        },
+    }
+
+    let tokens = params.into_iter().map(|(range, base, is_mtl)| {
+        let text = &src[range.clone()];
+        let (issues, fixed) = check_path(text, base, cache);
+        let issues = issues.into_iter().map(|d| d.in_file(path).at(&src, range.clone())).collect();
+        let resolved = if fix { fixed.as_deref().unwrap_or(text) } else { text };
+        let mtl = if is_mtl { Some(ini::resolve_source_path(base, &IdStringParam::new_borrowed(resolved))) } else { None };
+        PathToken { range, issues, fixed, mtl }
+    }).collect();
+
+    Some((src, enc, tokens))
+}
+
+
+// Ranges of the path parameters in src, by the range of their token. Parameters are searched
+// from the end of the token text: paths are the last parameters, or followed by numbers.
+fn locate<B: Copy>(src: &str, span: Range<usize>, params: &[(&IdStringParam, B, bool)]) -> Vec<(Range<usize>, B, bool)> {
+    let text = &src[span.clone()];
+    let mut end = text.len();
+    let mut result: Vec<_> = params.iter().rev().map(|(p, base, is_mtl)| {
+        let start = text[.. end].rfind(p.as_str()).expect("Token parameter is not in the token text");
+        end = start;
+        (span.start + start .. span.start + start + p.as_str().len(), *base, *is_mtl)
+    }).collect();
+
+    result.reverse();
+    result
+}
+
+
+// Issues of the token path and the token with the case of the files on disk
+fn check_path(text: &str, base: &Path, cache: &mut DirCache) -> (Vec<Diagnostic>, Option<String>) {
+    let mut issues = Vec::with_capacity(0);
+
+    if text.contains('\\') {
+        issues.push(Diagnostic::warning("path-backslash", format!("Path '{}' uses backslashes, they are not separators on Linux", text))
+                    .with_help(Some(format!("use '{}'", text.replace('\\', "/")))));
+    }
+
+    if let Some(c) = text.chars().find(|c| INVALID_CHARS.contains(c) || c.is_control()) {
+        issues.push(Diagnostic::error("path-invalid-char", format!("Path '{}' has '{}', which is not allowed in file names on Windows", text, c.escape_default())));
+    }
+
+    if text.split(['/', '\\']).any(|c| c.ends_with(' ')) {
+        issues.push(Diagnostic::error("path-trailing-space", format!("Path '{}' has a name ending with a space, Windows cannot have such files", text)));
+    }
+
+    // '#' and '~' prefixes, as in resolve_source_path
+    let (mut dir, tail_start) = match text.chars().next() {
+        Some('#') => (APP_SETTINGS.path_workshop.as_path().to_path_buf(), 1),
+        Some('~') => (APP_SETTINGS.path_stock.as_path().to_path_buf(), 1),
+        _         => (base.to_path_buf(), 0),
+    };
+
+    let mut fixed = text.to_string();
+    let mut mismatch = false;
+    let mut pos = tail_start;
+    for name in text[tail_start ..].split(['/', '\\']) {
+        let start = pos;
+        pos += name.len() + 1;
+        match name {
+            "" | "." => continue,
+            ".."     => { dir.pop(); continue; },
+            _        => { }
+        }
+
+        let entries = cache.entry(dir.clone()).or_insert_with(|| list_dir(&dir));
+        if entries.iter().any(|e| e == name) {
+            dir.push(name);
+        } else if let Some(actual) = entries.iter().find(|e| e.to_lowercase() == name.to_lowercase()) {
+            // names before this one may have changed their length
+            let start = start + fixed.len() - text.len();
+            fixed.replace_range(start .. start + name.len(), actual);
+            dir.push(actual);
+            mismatch = true;
+        } else {
+            // missing files are reported by the validation
+            break;
+        }
+    }
+
+    if mismatch {
+        issues.push(Diagnostic::warning("path-case", format!("Path '{}' differs in case from the files on disk, it only works on Windows", text))
+                    .with_help(Some(format!("use '{}' (--fix-case rewrites it)", fixed))));
+        (issues, Some(fixed))
+    } else {
+        (issues, None)
+    }
+}
+
+
+fn list_dir(dir: &Path) -> Vec<String> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.file_name().to_string_lossy().into_owned()).collect(),
+        Err(_) => Vec::with_capacity(0)
+    }
+}
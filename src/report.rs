@@ -0,0 +1,187 @@
+// Output of validation commands (--format). Text diagnostics are printed as they come,
+// json and sarif documents are printed at the end, so that stdout has nothing else.
+
+use std::fmt;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::ini::diagnostic::Diagnostic;
+use crate::ini::building::lint;
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Text,
+    Json,
+    Sarif,
+}
+
+
+pub struct Report {
+    format: Format,
+    diagnostics: Vec<Diagnostic>,
+    errors: usize,
+}
+
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text"  => Some(Self::Text),
+            "json"  => Some(Self::Json),
+            "sarif" => Some(Self::Sarif),
+            _       => None
+        }
+    }
+}
+
+
+impl Report {
+    pub fn new(format: Format) -> Self {
+        Report { format, diagnostics: Vec::with_capacity(0), errors: 0 }
+    }
+
+    pub fn push(&mut self, d: Diagnostic) {
+        if d.is_error() {
+            self.errors += 1;
+        }
+
+        match self.format {
+            Format::Text => eprint!("{}", d),
+            _            => self.diagnostics.push(d),
+        }
+    }
+
+    pub fn extend<I: IntoIterator<Item = Diagnostic>>(&mut self, diagnostics: I) {
+        for d in diagnostics {
+            self.push(d);
+        }
+    }
+
+    pub fn errors(&self) -> usize {
+        self.errors
+    }
+
+    // Progress messages. They go to stderr with json and sarif.
+    pub fn note(&self, msg: fmt::Arguments) {
+        match self.format {
+            Format::Text => println!("{}", msg),
+            _            => eprintln!("{}", msg),
+        }
+    }
+
+    // Prints the document and returns the exit code: 1 when there were errors
+    pub fn finish(self) -> i32 {
+        let code = if self.errors == 0 { 0 } else { 1 };
+        let doc = match self.format {
+            Format::Text  => return code,
+            Format::Json  => to_json(&self.diagnostics),
+            Format::Sarif => to_sarif(&self.diagnostics),
+        };
+
+        println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+        code
+    }
+}
+
+
+fn to_json(diagnostics: &[Diagnostic]) -> Value {
+    let items: Vec<Value> = diagnostics.iter().map(|d| json!({
+        "code":       d.code,
+        "severity":   d.severity.name(),
+        "file":       d.file.as_ref().map(|p| p.display().to_string()),
+        "line":       d.span.as_ref().map(|s| s.line),
+        "column":     d.span.as_ref().map(|s| s.column),
+        "end_column": d.span.as_ref().map(|s| s.end_column),
+        "message":    d.message,
+        "help":       d.help,
+    })).collect();
+
+    json!({
+        "errors":      diagnostics.iter().filter(|d| d.is_error()).count(),
+        "warnings":    diagnostics.iter().filter(|d| !d.is_error()).count(),
+        "diagnostics": items,
+    })
+}
+
+
+// SARIF 2.1.0, one run with the codes as rules
+fn to_sarif(diagnostics: &[Diagnostic]) -> Value {
+    let mut codes: Vec<&str> = Vec::with_capacity(16);
+    for d in diagnostics.iter() {
+        if !codes.contains(&d.code) {
+            codes.push(d.code);
+        }
+    }
+
+    let rules: Vec<Value> = codes.iter().map(|code| match lint::RULES.iter().find(|r| r.id == *code) {
+        Some(rule) => json!({ "id": code, "shortDescription": { "text": rule.description } }),
+        None       => json!({ "id": code }),
+    }).collect();
+
+    let results: Vec<Value> = diagnostics.iter().map(|d| {
+        let text = match d.help.as_ref() {
+            Some(help) => format!("{} (help: {})", d.message, help),
+            None       => d.message.clone(),
+        };
+
+        let mut result = json!({
+            "ruleId":    d.code,
+            "ruleIndex": codes.iter().position(|c| *c == d.code).unwrap(),
+            "level":     d.severity.name(),
+            "message":   { "text": text },
+        });
+
+        if let Some(path) = d.file.as_ref() {
+            let mut location = json!({ "artifactLocation": { "uri": file_uri(path) } });
+            if let Some(span) = d.span.as_ref() {
+                location["region"] = json!({ "startLine": span.line, "startColumn": span.column, "endColumn": span.end_column });
+            }
+            result["locations"] = json!([{ "physicalLocation": location }]);
+        }
+
+        result
+    }).collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name":           "wrsr-mt",
+                    "version":        env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/Kromgart/wrsr-mt",
+                    "rules":          rules,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+
+// Relative to the current directory when possible (CI tools map these to the repository),
+// an absolute file:// uri otherwise
+fn file_uri(path: &Path) -> String {
+    let cwd = std::env::current_dir().ok();
+    let (path, prefix) = match cwd.as_ref().and_then(|d| path.strip_prefix(d).ok()) {
+        Some(rel) => (rel, ""),
+        None if path.is_absolute() => (path, if cfg!(windows) { "file:///" } else { "file://" }),
+        None => (path, ""),
+    };
+
+    let mut uri = String::from(prefix);
+    for c in path.to_string_lossy().chars() {
+        match c {
+            '\\' => uri.push('/'),
+            ' '  => uri.push_str("%20"),
+            '%'  => uri.push_str("%25"),
+            '#'  => uri.push_str("%23"),
+            c    => uri.push(c),
+        }
+    }
+
+    uri
+}
@@ -0,0 +1,82 @@
+// New building from a model (mod-building new): renderconfig.ini, material.mtl with a block per
+// submaterial of the model and a building.ini skeleton, which pass the validation as they are.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::cfg::{RENDERCONFIG_INI, BUILDING_INI};
+use crate::ini::{self, BuildingToken as BT, RenderToken as RT, MaterialToken as MT};
+use crate::ini::building::{BuildingType, ConstructionPhase, ConstructionAutoCost};
+use crate::ini::common::{IdStringParam, Rect};
+use crate::nmf::NmfInfo;
+
+
+const MATERIAL_MTL: &str = "material.mtl";
+
+
+// Copies the model and the textures into dir and writes the config files
+pub fn create(dir: &Path, model: &Path, nmf: &NmfInfo, textures: &[PathBuf], building_type: BuildingType) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let model_name = file_name(model);
+    fs::copy(model, dir.join(&model_name))?;
+    let mut tx_names = Vec::with_capacity(textures.len());
+    for tx in textures.iter() {
+        let name = file_name(tx);
+        fs::copy(tx, dir.join(&name))?;
+        tx_names.push(name);
+    }
+
+    let render = vec![
+        RT::ObjectTypeWorkshop,
+        RT::Model(IdStringParam::new_owned(model_name)),
+        RT::Material(IdStringParam::new_cloned(MATERIAL_MTL)),
+        RT::End,
+    ];
+    write(&dir.join(RENDERCONFIG_INI), &render, None)?;
+
+    // diffuse texture of a submaterial: the one named as the submaterial, or the first one
+    let mut mtl = Vec::with_capacity(nmf.submaterials.len() * 2);
+    for sm in nmf.submaterials.iter() {
+        let sm = sm.as_str();
+        mtl.push(MT::Submaterial(IdStringParam::new_cloned(sm)));
+        let tx = tx_names.iter().find(|n| Path::new(n).file_stem().is_some_and(|s| s.to_string_lossy().eq_ignore_ascii_case(sm)))
+                                .or_else(|| tx_names.first());
+        if let Some(tx) = tx {
+            mtl.push(MT::TextureMtl((0, IdStringParam::new_cloned(tx))));
+        }
+    }
+    mtl.push(MT::End);
+    write(&dir.join(MATERIAL_MTL), &mtl, None)?;
+
+    let mut building = vec![BT::BuildingType(building_type)];
+    // footprint of the model, rounded outwards to 0.1 m
+    if let Some(bbox) = nmf.bbox() {
+        let (down, up) = (|x: f32| (x * 10.0).floor() / 10.0, |x: f32| (x * 10.0).ceil() / 10.0);
+        building.push(BT::ConnectionsSpace(Rect { x1: down(bbox.v_min.x), z1: down(bbox.v_min.z), x2: up(bbox.v_max.x), z2: up(bbox.v_max.z) }));
+    }
+
+    building.push(BT::CostWork((ConstructionPhase::Groundworks, 1.0)));
+    building.push(BT::CostWork((ConstructionPhase::BricksLaying, 1.0)));
+    let mut nodes: Vec<&str> = Vec::with_capacity(nmf.objects.len());
+    for name in nmf.object_names() {
+        if !nodes.contains(&name) {
+            nodes.push(name);
+            building.push(BT::CostWorkBuildingNode(IdStringParam::new_cloned(name)));
+        }
+    }
+    building.push(BT::CostResourceAuto((ConstructionAutoCost::Ground, 1.0)));
+    write(&dir.join(BUILDING_INI), &building, Some("end"))
+}
+
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_else(|| panic!("{} is not a file", path.display())).to_string_lossy().into_owned()
+}
+
+
+fn write<T: ini::IniToken>(path: &Path, tokens: &[T], footer: Option<&str>) -> io::Result<()> {
+    let wr = io::BufWriter::new(fs::OpenOptions::new().write(true).create_new(true).open(path)?);
+    ini::json::write_ini(tokens, footer, wr)
+}
@@ -0,0 +1,237 @@
+use std::path::Path;
+
+use crate::read_to_string_buf;
+use crate::dds::{Dds, RgbaImage};
+use crate::ini::{self, MaterialToken as MT};
+use crate::nmf::NmfBufFull;
+
+
+// Orthographic software renderer for building previews (imagegui.png).
+// Camera orbits around Y axis by `angle` degrees and looks down at a fixed elevation.
+
+const ELEVATION_DEG: f32 = 30.0;
+const SUPERSAMPLING: usize = 2;
+const MARGIN: f32 = 0.05;
+
+const LIGHT_DIR: (f32, f32, f32) = (0.3, 1.0, -0.5);
+const AMBIENT: f32 = 0.4;
+
+const NO_TEXTURE_COLOR: [u8; 4] = [160, 160, 160, 255];
+
+
+// Loads diffuse textures (slot 0) for every submaterial of the nmf.
+// Returns textures indexed as nmf submaterials, and the list of problems encountered.
+pub fn load_diffuse_textures(nmf: &NmfBufFull, mtl_path: &Path) -> (Vec<Option<RgbaImage>>, Vec<String>) {
+    let mut textures: Vec<Option<RgbaImage>> = nmf.submaterials.iter().map(|_| None).collect();
+    let mut warnings = Vec::with_capacity(0);
+
+    let mut buf = String::with_capacity(4 * 1024);
+    if let Err(e) = read_to_string_buf(mtl_path, &mut buf) {
+        warnings.push(format!("Cannot read {}: {}", mtl_path.display(), e));
+        return (textures, warnings);
+    }
+
+    let mtl = match ini::parse_mtl(&buf) {
+        Ok(mtl) => mtl,
+        Err(_) => {
+            warnings.push(format!("Cannot parse {}", mtl_path.display()));
+            return (textures, warnings);
+        }
+    };
+
+    let mtl_root = mtl_path.parent().unwrap();
+    let mut submaterial = None;
+
+    for t in mtl.tokens() {
+        let tx_path = match t {
+            MT::Submaterial(name) => {
+                submaterial = nmf.submaterials.iter().position(|sm| sm.as_str() == name.as_str());
+                continue;
+            },
+            MT::Texture((0, p)) | MT::TextureNoMip((0, p))       => ini::resolve_stock_path(p),
+            MT::TextureMtl((0, p)) | MT::TextureNoMipMtl((0, p)) => ini::normalize_join(mtl_root, p),
+            _ => continue
+        };
+
+        if let Some(sm_idx) = submaterial {
+            if textures[sm_idx].is_none() {
+                match Dds::from_path(&tx_path) {
+                    Ok(dds) => textures[sm_idx] = Some(dds.decode_rgba()),
+                    Err(e)  => warnings.push(format!("Cannot load texture {}: {}", tx_path.display(), e)),
+                }
+            }
+        }
+    }
+
+    (textures, warnings)
+}
+
+
+pub fn render(nmf: &NmfBufFull, textures: &[Option<RgbaImage>], size: usize, angle_deg: f32) -> RgbaImage {
+    let (sin_a, cos_a) = angle_deg.to_radians().sin_cos();
+    let (sin_e, cos_e) = ELEVATION_DEG.to_radians().sin_cos();
+
+    // world -> view: x right, y up, z into the screen
+    let project = |x: f32, y: f32, z: f32| -> (f32, f32, f32) {
+        let x1 = x * cos_a - z * sin_a;
+        let z1 = x * sin_a + z * cos_a;
+        (x1, y * cos_e + z1 * sin_e, z1 * cos_e - y * sin_e)
+    };
+
+    let mut min = (f32::INFINITY, f32::INFINITY);
+    let mut max = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for o in nmf.objects.iter() {
+        for v in o.vertices() {
+            let (x, y, _) = project(v.x, v.y, v.z);
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+    }
+
+    let dim = size * SUPERSAMPLING;
+    let extent = (max.0 - min.0).max(max.1 - min.1).max(f32::EPSILON);
+    let scale = dim as f32 * (1.0 - 2.0 * MARGIN) / extent;
+    let center = ((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0);
+
+    let to_screen = |x: f32, y: f32, z: f32| -> (f32, f32, f32) {
+        let (vx, vy, vz) = project(x, y, z);
+        (dim as f32 / 2.0 + (vx - center.0) * scale,
+         dim as f32 / 2.0 - (vy - center.1) * scale,
+         vz)
+    };
+
+    let light = {
+        let (x, y, z) = LIGHT_DIR;
+        let len = (x * x + y * y + z * z).sqrt();
+        (x / len, y / len, z / len)
+    };
+
+    let mut color = vec![0u8; dim * dim * 4];
+    let mut depth = vec![f32::INFINITY; dim * dim];
+
+    for o in nmf.objects.iter() {
+        let verts = o.vertices();
+        let uvs = o.uv_map();
+        let face_submaterials = o.face_submaterials();
+        let screen: Vec<_> = verts.iter().map(|v| to_screen(v.x, v.y, v.z)).collect();
+
+        for (f, sm) in o.faces().iter().zip(face_submaterials) {
+            let (i0, i1, i2) = (f.v1 as usize, f.v2 as usize, f.v3 as usize);
+            let texture = sm.and_then(|sm| textures.get(sm)).and_then(Option::as_ref);
+
+            // lighting by face normal, both sides lit
+            let shade = {
+                let (a, b, c) = (&verts[i0], &verts[i1], &verts[i2]);
+                let (ux, uy, uz) = (b.x - a.x, b.y - a.y, b.z - a.z);
+                let (vx, vy, vz) = (c.x - a.x, c.y - a.y, c.z - a.z);
+                let (nx, ny, nz) = (uy * vz - uz * vy, uz * vx - ux * vz, ux * vy - uy * vx);
+                let len = (nx * nx + ny * ny + nz * nz).sqrt();
+                if len > 0.0 {
+                    AMBIENT + (1.0 - AMBIENT) * ((nx * light.0 + ny * light.1 + nz * light.2) / len).abs()
+                } else {
+                    AMBIENT
+                }
+            };
+
+            let (p0, p1, p2) = (screen[i0], screen[i1], screen[i2]);
+            let area = edge(p0, p1, p2.0, p2.1);
+            if area.abs() < f32::EPSILON {
+                continue;
+            }
+
+            let x_from = p0.0.min(p1.0).min(p2.0).floor().max(0.0) as usize;
+            let y_from = p0.1.min(p1.1).min(p2.1).floor().max(0.0) as usize;
+            let x_to = (p0.0.max(p1.0).max(p2.0).ceil().max(0.0) as usize).min(dim);
+            let y_to = (p0.1.max(p1.1).max(p2.1).ceil().max(0.0) as usize).min(dim);
+
+            for y in y_from .. y_to {
+                for x in x_from .. x_to {
+                    let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                    let w0 = edge(p1, p2, px, py) / area;
+                    let w1 = edge(p2, p0, px, py) / area;
+                    let w2 = edge(p0, p1, px, py) / area;
+                    if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                        continue;
+                    }
+
+                    let z = w0 * p0.2 + w1 * p1.2 + w2 * p2.2;
+                    let idx = y * dim + x;
+                    if z >= depth[idx] {
+                        continue;
+                    }
+
+                    let texel = match texture {
+                        Some(tx) => {
+                            let u = w0 * uvs[i0].x + w1 * uvs[i1].x + w2 * uvs[i2].x;
+                            let v = w0 * uvs[i0].y + w1 * uvs[i1].y + w2 * uvs[i2].y;
+                            sample(tx, u, v)
+                        },
+                        None => NO_TEXTURE_COLOR
+                    };
+
+                    // alpha-tested materials (fences, foliage)
+                    if texel[3] < 128 {
+                        continue;
+                    }
+
+                    depth[idx] = z;
+                    let dst = &mut color[idx * 4 .. idx * 4 + 4];
+                    for k in 0 .. 3 {
+                        dst[k] = (texel[k] as f32 * shade).min(255.0) as u8;
+                    }
+                    dst[3] = 255;
+                }
+            }
+        }
+    }
+
+    downsample(&color, dim, SUPERSAMPLING)
+}
+
+
+#[inline]
+fn edge(a: (f32, f32, f32), b: (f32, f32, f32), x: f32, y: f32) -> f32 {
+    (b.0 - a.0) * (y - a.1) - (b.1 - a.1) * (x - a.0)
+}
+
+
+fn sample(tx: &RgbaImage, u: f32, v: f32) -> [u8; 4] {
+    let wrap = |t: f32, n: usize| -> usize {
+        let i = (t * n as f32).floor() as i64;
+        i.rem_euclid(n as i64) as usize
+    };
+
+    let idx = (wrap(v, tx.height) * tx.width + wrap(u, tx.width)) * 4;
+    [tx.pixels[idx], tx.pixels[idx + 1], tx.pixels[idx + 2], tx.pixels[idx + 3]]
+}
+
+
+// box filter, colors are weighted by alpha
+fn downsample(src: &[u8], dim: usize, factor: usize) -> RgbaImage {
+    let size = dim / factor;
+    let mut pixels = vec![0u8; size * size * 4];
+
+    for y in 0 .. size {
+        for x in 0 .. size {
+            let mut acc = [0u32; 4];
+            for sy in 0 .. factor {
+                for sx in 0 .. factor {
+                    let s = ((y * factor + sy) * dim + x * factor + sx) * 4;
+                    let a = src[s + 3] as u32;
+                    for k in 0 .. 3 {
+                        acc[k] += src[s + k] as u32 * a;
+                    }
+                    acc[3] += a;
+                }
+            }
+
+            let dst = (y * size + x) * 4;
+            for k in 0 .. 3 {
+                pixels[dst + k] = acc[k].checked_div(acc[3]).unwrap_or(0) as u8;
+            }
+            pixels[dst + 3] = (acc[3] / (factor * factor) as u32) as u8;
+        }
+    }
+
+    RgbaImage { width: size, height: size, pixels }
+}